@@ -0,0 +1,492 @@
+// Keyframe animation.
+//
+// Every animatable property of a node can have a `Track` of keyframes. The
+// renderer asks for the node's values at a given frame via `sample`, which
+// starts from the node's static values and overrides each animated property
+// with its interpolated track value. Sampling is a pure function of the
+// document and the frame number, so renders are deterministic.
+
+use serde::{Deserialize, Serialize};
+
+use crate::easing::Easing;
+use crate::scene::{
+    Color, Document, Fill, Geometry, Node, NodeId, NodeKind, PathPoint, Point, Transform,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Property {
+    Position,
+    Rotation,
+    Scale,
+    Opacity,
+    FillColor,
+    StrokeWidth,
+    PathPoints,
+}
+
+impl Property {
+    pub const ALL: [Property; 7] = [
+        Property::Position,
+        Property::Rotation,
+        Property::Scale,
+        Property::Opacity,
+        Property::FillColor,
+        Property::StrokeWidth,
+        Property::PathPoints,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Property::Position => "Position",
+            Property::Rotation => "Rotation",
+            Property::Scale => "Scale",
+            Property::Opacity => "Opacity",
+            Property::FillColor => "Fill",
+            Property::StrokeWidth => "Stroke width",
+            Property::PathPoints => "Path",
+        }
+    }
+}
+
+/// Value of a property at one point in time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Scalar(f32),
+    Point(Point),
+    Color(Color),
+    Path(Vec<PathPoint>),
+}
+
+impl Value {
+    /// Interpolates towards `other` by `t`. Values of different kinds, or
+    /// paths with a different number of points, cannot be blended and hold
+    /// the start value until the next keyframe.
+    pub fn lerp(&self, other: &Value, t: f32) -> Value {
+        match (self, other) {
+            (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(a + (b - a) * t),
+            (Value::Point(a), Value::Point(b)) => Value::Point(a.lerp(*b, t)),
+            (Value::Color(a), Value::Color(b)) => Value::Color(a.lerp(*b, t)),
+            (Value::Path(a), Value::Path(b)) if a.len() == b.len() => Value::Path(
+                a.iter()
+                    .zip(b)
+                    .map(|(p, q)| PathPoint {
+                        anchor: p.anchor.lerp(q.anchor, t),
+                        handle_in: p.handle_in.lerp(q.handle_in, t),
+                        handle_out: p.handle_out.lerp(q.handle_out, t),
+                    })
+                    .collect(),
+            ),
+            _ => self.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub frame: f32,
+    pub value: Value,
+    /// Easing of the segment that starts at this keyframe.
+    pub easing: Easing,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub property: Property,
+    /// Sorted by frame, at most one keyframe per frame.
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(property: Property) -> Self {
+        Self {
+            property,
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Adds a keyframe, replacing any existing one on the same frame.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        match self
+            .keyframes
+            .binary_search_by(|k| k.frame.total_cmp(&keyframe.frame))
+        {
+            Ok(index) => self.keyframes[index] = keyframe,
+            Err(index) => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    pub fn remove(&mut self, frame: f32) -> Option<Keyframe> {
+        let index = self.keyframes.iter().position(|k| k.frame == frame)?;
+        Some(self.keyframes.remove(index))
+    }
+
+    pub fn keyframe_at(&self, frame: f32) -> Option<&Keyframe> {
+        self.keyframes.iter().find(|k| k.frame == frame)
+    }
+
+    /// Evaluates the track at `frame`. Before the first and after the last
+    /// keyframe the track holds the nearest keyframe's value.
+    pub fn sample(&self, frame: f32) -> Option<Value> {
+        let first = self.keyframes.first()?;
+        if frame <= first.frame {
+            return Some(first.value.clone());
+        }
+        // Index of the first keyframe strictly after `frame`.
+        let next = self.keyframes.partition_point(|k| k.frame <= frame);
+        if next == self.keyframes.len() {
+            return self.keyframes.last().map(|k| k.value.clone());
+        }
+        let (from, to) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = (frame - from.frame) / (to.frame - from.frame);
+        Some(from.value.lerp(&to.value, from.easing.apply(t)))
+    }
+}
+
+/// Animated properties of one node.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Animation {
+    pub tracks: Vec<Track>,
+}
+
+impl Animation {
+    pub fn is_empty(&self) -> bool {
+        self.tracks.iter().all(|t| t.keyframes.is_empty())
+    }
+
+    pub fn track(&self, property: Property) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.property == property)
+    }
+
+    /// Returns the track for `property`, creating an empty one if needed.
+    pub fn track_mut(&mut self, property: Property) -> &mut Track {
+        match self.tracks.iter().position(|t| t.property == property) {
+            Some(index) => &mut self.tracks[index],
+            None => {
+                self.tracks.push(Track::new(property));
+                self.tracks.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn is_animated(&self, property: Property) -> bool {
+        self.track(property)
+            .is_some_and(|t| !t.keyframes.is_empty())
+    }
+
+    pub fn set_keyframe(&mut self, property: Property, frame: f32, value: Value) {
+        let easing = self
+            .track(property)
+            .and_then(|t| t.keyframe_at(frame))
+            .map(|k| k.easing)
+            .unwrap_or_default();
+        self.track_mut(property).insert(Keyframe {
+            frame,
+            value,
+            easing,
+        });
+    }
+
+    /// Sets the easing of the segment leaving the keyframe at `frame`.
+    /// Returns `false` if there is no such keyframe.
+    pub fn set_easing(&mut self, property: Property, frame: f32, easing: Easing) -> bool {
+        let Some(track) = self.tracks.iter_mut().find(|t| t.property == property) else {
+            return false;
+        };
+        match track.keyframes.iter_mut().find(|k| k.frame == frame) {
+            Some(keyframe) => {
+                keyframe.easing = easing;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// All distinct keyframe frames across tracks, ascending.
+    pub fn keyframe_frames(&self) -> Vec<f32> {
+        let mut frames: Vec<f32> = self
+            .tracks
+            .iter()
+            .flat_map(|t| t.keyframes.iter().map(|k| k.frame))
+            .collect();
+        frames.sort_by(f32::total_cmp);
+        frames.dedup();
+        frames
+    }
+}
+
+/// Resolved values of every animatable property of a node at one frame.
+/// Shape-specific properties are `None` when the node does not have them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyValues {
+    pub position: Point,
+    pub rotation: f32,
+    pub scale: Point,
+    pub opacity: f32,
+    pub fill_color: Option<Color>,
+    pub stroke_width: Option<f32>,
+    pub path_points: Option<Vec<PathPoint>>,
+}
+
+impl PropertyValues {
+    /// The node's static (un-animated) values.
+    pub fn of(node: &Node) -> Self {
+        let shape = node.shape();
+        Self {
+            position: node.transform.position,
+            rotation: node.transform.rotation,
+            scale: node.transform.scale,
+            opacity: node.opacity,
+            fill_color: shape.and_then(|s| match &s.fill {
+                Some(Fill::Solid(color)) => Some(*color),
+                _ => None,
+            }),
+            stroke_width: shape.and_then(|s| s.stroke.as_ref()).map(|s| s.width),
+            path_points: shape.and_then(|s| match &s.geometry {
+                Geometry::Path(data) => Some(data.points.clone()),
+                _ => None,
+            }),
+        }
+    }
+
+    pub fn get(&self, property: Property) -> Option<Value> {
+        match property {
+            Property::Position => Some(Value::Point(self.position)),
+            Property::Rotation => Some(Value::Scalar(self.rotation)),
+            Property::Scale => Some(Value::Point(self.scale)),
+            Property::Opacity => Some(Value::Scalar(self.opacity)),
+            Property::FillColor => self.fill_color.map(Value::Color),
+            Property::StrokeWidth => self.stroke_width.map(Value::Scalar),
+            Property::PathPoints => self.path_points.clone().map(Value::Path),
+        }
+    }
+
+    /// Overrides one property. Values of the wrong kind, or for properties
+    /// the node does not have, are ignored.
+    pub fn set(&mut self, property: Property, value: Value) {
+        match (property, value) {
+            (Property::Position, Value::Point(p)) => self.position = p,
+            (Property::Rotation, Value::Scalar(v)) => self.rotation = v,
+            (Property::Scale, Value::Point(p)) => self.scale = p,
+            (Property::Opacity, Value::Scalar(v)) => self.opacity = v.clamp(0.0, 1.0),
+            (Property::FillColor, Value::Color(c)) if self.fill_color.is_some() => {
+                self.fill_color = Some(c)
+            }
+            (Property::StrokeWidth, Value::Scalar(v)) if self.stroke_width.is_some() => {
+                self.stroke_width = Some(v.max(0.0))
+            }
+            (Property::PathPoints, Value::Path(points)) if self.path_points.is_some() => {
+                self.path_points = Some(points)
+            }
+            _ => {}
+        }
+    }
+
+    pub fn transform(&self, base: &Transform) -> Transform {
+        Transform {
+            position: self.position,
+            rotation: self.rotation,
+            scale: self.scale,
+            anchor: base.anchor,
+        }
+    }
+
+    /// Writes the values back into the node as its static properties.
+    pub fn apply_to(&self, node: &mut Node) {
+        node.transform = self.transform(&node.transform);
+        node.opacity = self.opacity;
+        if let NodeKind::Shape(shape) = &mut node.kind {
+            if let (Some(Fill::Solid(color)), Some(value)) = (&mut shape.fill, self.fill_color) {
+                *color = value;
+            }
+            if let (Some(stroke), Some(width)) = (&mut shape.stroke, self.stroke_width) {
+                stroke.width = width;
+            }
+            if let (Geometry::Path(data), Some(points)) = (&mut shape.geometry, &self.path_points) {
+                if data.points.len() == points.len() {
+                    data.points.clone_from(points);
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates every property of `node` at `frame`.
+pub fn sample(node: &Node, frame: f32) -> PropertyValues {
+    let mut values = PropertyValues::of(node);
+    for track in &node.animation.tracks {
+        if let Some(value) = track.sample(frame) {
+            values.set(track.property, value);
+        }
+    }
+    values
+}
+
+/// Sets `property` of `node` to `value` as seen at `frame`. Animated
+/// properties, and every property when `auto_key` is on, get a keyframe
+/// there; otherwise the static value changes.
+pub fn write(node: &mut Node, property: Property, value: Value, frame: f32, auto_key: bool) {
+    if auto_key || node.animation.is_animated(property) {
+        node.animation.set_keyframe(property, frame, value);
+    } else {
+        let mut values = PropertyValues::of(node);
+        values.set(property, value);
+        values.apply_to(node);
+    }
+}
+
+/// Sampled transforms from the top-level layer down to `id`, outermost
+/// first.
+pub fn transform_chain(doc: &Document, id: NodeId, frame: f32) -> Vec<Transform> {
+    doc.ancestry(id)
+        .into_iter()
+        .filter_map(|id| doc.get(id))
+        .map(|node| sample(node, frame).transform(&node.transform))
+        .collect()
+}
+
+/// How much node `id` scales lengths into document space: the geometric
+/// mean of the accumulated x and y scales, never zero.
+pub fn document_scale(doc: &Document, id: NodeId, frame: f32) -> f32 {
+    transform_chain(doc, id, frame)
+        .iter()
+        .map(|t| (t.scale.x * t.scale.y).abs())
+        .product::<f32>()
+        .sqrt()
+        .max(1e-6)
+}
+
+/// Maps a document-space point into the local space of node `id`.
+pub fn to_local(doc: &Document, id: NodeId, frame: f32, p: Point) -> Point {
+    transform_chain(doc, id, frame)
+        .iter()
+        .fold(p, |p, t| t.invert(p))
+}
+
+/// Maps a point in the local space of node `id` into document space.
+pub fn to_document(doc: &Document, id: NodeId, frame: f32, p: Point) -> Point {
+    transform_chain(doc, id, frame)
+        .iter()
+        .rev()
+        .fold(p, |p, t| t.apply(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Shape;
+
+    fn key(frame: f32, value: f32) -> Keyframe {
+        Keyframe {
+            frame,
+            value: Value::Scalar(value),
+            easing: Easing::Linear,
+        }
+    }
+
+    fn scalar(value: Option<Value>) -> f32 {
+        match value {
+            Some(Value::Scalar(v)) => v,
+            other => panic!("expected a scalar, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn insert_keeps_keyframes_sorted_and_replaces_same_frame() {
+        let mut track = Track::new(Property::Rotation);
+        track.insert(key(20.0, 2.0));
+        track.insert(key(0.0, 0.0));
+        track.insert(key(10.0, 1.0));
+        track.insert(key(10.0, 5.0));
+        let frames: Vec<f32> = track.keyframes.iter().map(|k| k.frame).collect();
+        assert_eq!(frames, [0.0, 10.0, 20.0]);
+        assert_eq!(track.keyframe_at(10.0).unwrap().value, Value::Scalar(5.0));
+    }
+
+    #[test]
+    fn linear_segments_interpolate() {
+        let mut track = Track::new(Property::Rotation);
+        track.insert(key(0.0, 0.0));
+        track.insert(key(10.0, 100.0));
+        track.insert(key(20.0, 50.0));
+        assert_eq!(scalar(track.sample(5.0)), 50.0);
+        assert_eq!(scalar(track.sample(10.0)), 100.0);
+        assert_eq!(scalar(track.sample(15.0)), 75.0);
+    }
+
+    #[test]
+    fn hold_keeps_the_value_until_the_next_keyframe() {
+        let mut track = Track::new(Property::Rotation);
+        track.insert(Keyframe {
+            easing: Easing::Hold,
+            ..key(0.0, 1.0)
+        });
+        track.insert(key(10.0, 2.0));
+        assert_eq!(scalar(track.sample(9.9)), 1.0);
+        assert_eq!(scalar(track.sample(10.0)), 2.0);
+    }
+
+    #[test]
+    fn sampling_outside_the_keyframes_holds_the_ends() {
+        let mut track = Track::new(Property::Rotation);
+        assert_eq!(track.sample(0.0), None);
+        track.insert(key(10.0, 1.0));
+        track.insert(key(20.0, 2.0));
+        assert_eq!(scalar(track.sample(-5.0)), 1.0);
+        assert_eq!(scalar(track.sample(0.0)), 1.0);
+        assert_eq!(scalar(track.sample(25.0)), 2.0);
+    }
+
+    #[test]
+    fn mismatched_paths_hold_the_start_value() {
+        let a = Value::Path(vec![PathPoint::corner(Point::ZERO)]);
+        let b = Value::Path(vec![PathPoint::corner(Point::ZERO); 2]);
+        assert_eq!(a.lerp(&b, 0.5), a);
+    }
+
+    #[test]
+    fn sample_overrides_static_values_with_tracks() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let id = doc
+            .add_shape(
+                layer,
+                "Rect",
+                Shape::new(Geometry::Rect {
+                    size: Point::new(10.0, 10.0),
+                    corner_radius: 0.0,
+                }),
+            )
+            .unwrap();
+        let node = doc.get_mut(id).unwrap();
+        node.transform.rotation = 45.0;
+        node.opacity = 0.25;
+        node.animation
+            .set_keyframe(Property::Position, 0.0, Value::Point(Point::ZERO));
+        node.animation.set_keyframe(
+            Property::Position,
+            10.0,
+            Value::Point(Point::new(100.0, 50.0)),
+        );
+
+        let values = sample(node, 5.0);
+        assert_eq!(values.position, Point::new(50.0, 25.0));
+        assert_eq!(values.rotation, 45.0);
+        assert_eq!(values.opacity, 0.25);
+        assert_eq!(values.stroke_width, None);
+    }
+
+    #[test]
+    fn write_keys_animated_properties_only() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let node = doc.get_mut(layer).unwrap();
+        write(node, Property::Rotation, Value::Scalar(30.0), 5.0, false);
+        assert_eq!(node.transform.rotation, 30.0);
+        assert!(!node.animation.is_animated(Property::Rotation));
+
+        write(node, Property::Rotation, Value::Scalar(60.0), 5.0, true);
+        assert_eq!(node.transform.rotation, 30.0);
+        write(node, Property::Rotation, Value::Scalar(90.0), 15.0, false);
+        assert_eq!(node.animation.keyframe_frames(), [5.0, 15.0]);
+    }
+}
@@ -0,0 +1,1534 @@
+// Editor window: owns the shared state and wires it to `AppWindow`.
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::Instant;
+
+use anyhow::{anyhow, bail, Result};
+use slint::winit_030::winit::event::WindowEvent;
+use slint::winit_030::{EventResult, WinitWindowAccessor};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, Timer, TimerMode, VecModel};
+
+use crate::animation::Property;
+use crate::cel;
+use crate::easing::Easing;
+use crate::export::gif::{self, GifOptions, PaletteMode};
+use crate::export::quantize::Quantizer;
+use crate::export::sprite::{self, SheetLayout, SheetOptions};
+use crate::history::{self, History};
+use crate::inspector::{self, FieldId, FieldKind, FieldValue};
+use crate::playback::{self, LoopMode, Playback};
+use crate::render::Background;
+use crate::scene::{Color, Document, NodeId, Point};
+use crate::snap::{self, Axis, GridKind, Guide};
+use crate::timeline::{self, Timeline, TimelineContext};
+use crate::tools::{
+    Key, KeyEvent, Modifiers, OverlayContext, Palette, PointerEvent, Style, Tool, ToolContext,
+    HIT_PIXELS,
+};
+use crate::viewport::Viewport;
+use crate::{
+    lottie, project, render, svg, AppWindow, GifSettings, OnionData, PropertyField, SheetSettings,
+    SnapData, ToolOptionData,
+};
+
+/// Editor state shared by every UI callback.
+pub struct Editor {
+    pub document: Document,
+    /// File the document was last opened from or saved to.
+    pub path: Option<PathBuf>,
+    /// Playhead position in frames.
+    pub frame: f32,
+    pub selection: Vec<NodeId>,
+    /// Layer that receives newly drawn content.
+    pub active_layer: Option<NodeId>,
+    pub style: Style,
+    pub palette: Palette,
+    pub history: History<Document>,
+    /// Property edits always set keyframes at the playhead.
+    pub auto_key: bool,
+    pub timeline: Timeline,
+    pub playback: Playback,
+    pub viewport: Viewport,
+    pub guide_drag: Option<GuideDrag>,
+}
+
+/// A guide being dragged out of or along a ruler.
+#[derive(Clone, Copy, Debug)]
+pub struct GuideDrag {
+    index: usize,
+    /// Dropping the guide on a ruler deletes it. Off for a grabbed guide
+    /// until it leaves the rulers, so a click on its marker keeps it.
+    remove_on_ruler: bool,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let mut document = Document::default();
+        let layer = document.add_layer("Layer 1");
+        Self {
+            document,
+            path: None,
+            frame: 0.0,
+            selection: Vec::new(),
+            active_layer: Some(layer),
+            style: Style::default(),
+            palette: Palette::default(),
+            history: History::new(undo_depth()),
+            auto_key: false,
+            timeline: Timeline::default(),
+            playback: Playback::default(),
+            viewport: Viewport::default(),
+            guide_drag: None,
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        self.path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".into())
+    }
+
+    /// Base name for exported files: the project's file stem.
+    pub fn export_stem(&self) -> String {
+        self.path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".into())
+    }
+
+    /// Frames covered by animation exports: the playback range, or the
+    /// whole document.
+    pub fn export_range(&self) -> Range<u32> {
+        let (start, end) = self.playback.bounds(self.document.duration);
+        start..end + 1
+    }
+
+    /// Runs `f` with the active tool and a context borrowing the rest of
+    /// the editor.
+    pub fn with_tool(&mut self, pixel_size: f32, f: impl FnOnce(&mut dyn Tool, &mut ToolContext)) {
+        let Editor {
+            document,
+            frame,
+            selection,
+            active_layer,
+            style,
+            palette,
+            auto_key,
+            ..
+        } = self;
+        let mut ctx = ToolContext {
+            document,
+            selection,
+            layer: active_layer,
+            style,
+            frame: *frame,
+            pixel_size,
+            auto_key: *auto_key,
+        };
+        if let Some(tool) = palette.active_mut() {
+            f(tool, &mut ctx);
+        }
+    }
+
+    /// Runs `f` with the timeline and a context borrowing the rest of the
+    /// editor.
+    pub fn with_timeline(&mut self, f: impl FnOnce(&mut Timeline, &mut TimelineContext)) {
+        let mut ctx = TimelineContext {
+            document: &mut self.document,
+            frame: &mut self.frame,
+            selection: &mut self.selection,
+            layer: &mut self.active_layer,
+        };
+        f(&mut self.timeline, &mut ctx);
+    }
+
+    pub fn select_tool(&mut self, index: usize) {
+        let Editor {
+            document,
+            frame,
+            selection,
+            active_layer,
+            style,
+            palette,
+            auto_key,
+            ..
+        } = self;
+        let mut ctx = ToolContext {
+            document,
+            selection,
+            layer: active_layer,
+            style,
+            frame: *frame,
+            pixel_size: 1.0,
+            auto_key: *auto_key,
+        };
+        palette.activate(index, &mut ctx);
+    }
+
+    /// Opens an undo group named after the active tool.
+    pub fn begin_tool_edit(&mut self) {
+        let label = self.palette.active().map_or("Edit", |tool| tool.name());
+        self.history.begin(label, &self.document);
+    }
+
+    pub fn commit_edit(&mut self) {
+        self.history.commit(&self.document);
+    }
+
+    /// Abandons the edit in progress, if any: the active tool drops its
+    /// interaction and the document goes back to where the edit started.
+    /// Returns `false` when there was nothing to cancel.
+    pub fn cancel_edit(&mut self) -> bool {
+        if !self.history.in_group() {
+            return false;
+        }
+        let pixel_size = self.viewport.pixel_size();
+        self.with_tool(pixel_size, |tool, ctx| tool.deactivate(ctx));
+        self.history.cancel(&mut self.document);
+        self.guide_drag = None;
+        self.timeline.pointer_up();
+        self.forget_missing_nodes();
+        true
+    }
+
+    /// Track of the first keyframe selected in the timeline at the playhead
+    /// on a selected node; the inspector's easing field edits that track.
+    pub fn easing_track(&self) -> Option<Property> {
+        self.timeline
+            .selection
+            .iter()
+            .find(|key| key.frame == self.frame && self.selection.contains(&key.node))
+            .map(|key| key.property)
+    }
+
+    pub fn fields(&self) -> Vec<inspector::Field> {
+        let track = self.easing_track();
+        inspector::fields(&self.document, &self.selection, self.frame, track)
+    }
+
+    /// Applies an inspector edit to the selection as one undo step.
+    pub fn edit_field(&mut self, id: FieldId, value: FieldValue) {
+        let label = format!("Edit {}", id.label().to_lowercase());
+        let track = self.easing_track();
+        let (selection, frame, auto_key) = (&self.selection, self.frame, self.auto_key);
+        self.history.edit(&label, &mut self.document, |document| {
+            inspector::apply(document, selection, frame, track, id, value, auto_key)
+        });
+    }
+
+    /// Changes one 0..255 channel of a color field, starting from the
+    /// first selected node's color.
+    pub fn edit_channel(&mut self, id: FieldId, channel: usize, value: f32) {
+        let Some(FieldValue::Color(mut color)) = self
+            .fields()
+            .into_iter()
+            .find(|f| f.id == id)
+            .map(|f| f.value)
+        else {
+            return;
+        };
+        let value = (value / 255.0).clamp(0.0, 1.0);
+        match channel {
+            0 => color.r = value,
+            1 => color.g = value,
+            2 => color.b = value,
+            _ => color.a = value,
+        }
+        self.edit_field(id, FieldValue::Color(color));
+    }
+
+    pub fn toggle_keyframe(&mut self, id: FieldId) {
+        let label = format!("Toggle {} keyframe", id.label().to_lowercase());
+        let (selection, frame) = (&self.selection, self.frame);
+        self.history.edit(&label, &mut self.document, |document| {
+            inspector::toggle_keyframe(document, selection, frame, id)
+        });
+    }
+
+    /// Runs a cel command from the timeline as one undo step. Commands that
+    /// add a drawing move the playhead to it.
+    pub fn cel_command(&mut self, command: &str) -> Result<()> {
+        if command == "new-layer" {
+            let name = format!("Cels {}", self.document.layers.len() + 1);
+            let layer = self
+                .history
+                .edit("New cel layer", &mut self.document, |doc| {
+                    doc.add_cel_layer(name)
+                });
+            self.active_layer = Some(layer);
+            self.selection.clear();
+            return Ok(());
+        }
+        let layer = self
+            .active_layer
+            .ok_or_else(|| anyhow!("no active layer"))?;
+        let frame = self.frame;
+        let label = match command {
+            "insert" => "Insert drawing",
+            "duplicate" => "Duplicate drawing",
+            "delete" => "Delete drawing",
+            _ => bail!("unknown cel command {command}"),
+        };
+        let start = self
+            .history
+            .edit(label, &mut self.document, |doc| match command {
+                "insert" => cel::insert_blank(doc, layer, frame).map(|(_, start)| Some(start)),
+                "duplicate" => cel::duplicate(doc, layer, frame).map(|(_, start)| Some(start)),
+                _ => cel::delete(doc, layer, frame).map(|_| None),
+            })?;
+        if let Some(start) = start {
+            self.frame = start as f32;
+        }
+        self.forget_missing_nodes();
+        Ok(())
+    }
+
+    /// Runs a navigation command of the viewport.
+    pub fn view_command(&mut self, command: &str) -> Result<()> {
+        let viewport = &mut self.viewport;
+        match command {
+            "fit" => viewport.fit_artboard(&self.document),
+            "actual-size" => viewport.actual_size(),
+            "fit-selection" => {
+                let corners: Vec<Point> = self
+                    .selection
+                    .iter()
+                    .flat_map(|&id| render::document_corners(&self.document, id, self.frame))
+                    .collect();
+                let Some((min, max)) = snap::bounds(&corners) else {
+                    bail!("nothing selected to fit");
+                };
+                viewport.fit(min, max);
+            }
+            "rotate-left" => viewport.rotate_by(-ROTATE_STEP),
+            "rotate-right" => viewport.rotate_by(ROTATE_STEP),
+            "reset-rotation" => viewport.set_rotation(0.0),
+            _ => bail!("unknown view command {command}"),
+        }
+        Ok(())
+    }
+
+    /// Ruler under the panel position `at`, as the axis of the guides it
+    /// pulls out: the top ruler makes vertical guides.
+    fn ruler_at(&self, at: Point) -> Option<Axis> {
+        if !self.document.snap.rulers {
+            return None;
+        }
+        match (at.x < render::RULER_SIZE, at.y < render::RULER_SIZE) {
+            (false, true) => Some(Axis::Vertical),
+            (true, false) => Some(Axis::Horizontal),
+            _ => None,
+        }
+    }
+
+    /// Routes a canvas pointer event at the panel position `at` to guide
+    /// dragging. A press on a ruler grabs the guide under it or pulls out a
+    /// new one, and dropping a guide back on a ruler deletes it. Returns
+    /// whether the event was used.
+    pub fn guide_pointer(&mut self, kind: &str, at: Point) -> bool {
+        let p = self.viewport.to_document(at);
+        let along = |axis: Axis| match axis {
+            Axis::Vertical => p.x,
+            Axis::Horizontal => p.y,
+        };
+        if kind == "down" {
+            let Some(axis) = self.ruler_at(at) else {
+                return false;
+            };
+            let position = along(axis);
+            let tolerance = HIT_PIXELS * self.viewport.pixel_size();
+            let existing = self
+                .document
+                .snap
+                .guides
+                .iter()
+                .position(|g| g.axis == axis && (g.position - position).abs() <= tolerance);
+            let label = if existing.is_some() {
+                "Move guide"
+            } else {
+                "Add guide"
+            };
+            self.history.begin(label, &self.document);
+            let guides = &mut self.document.snap.guides;
+            let index = existing.unwrap_or_else(|| {
+                guides.push(Guide { axis, position });
+                guides.len() - 1
+            });
+            self.guide_drag = Some(GuideDrag {
+                index,
+                remove_on_ruler: existing.is_none(),
+            });
+            return true;
+        }
+
+        let Some(mut drag) = self.guide_drag else {
+            return false;
+        };
+        let on_ruler =
+            self.document.snap.rulers && (at.x < render::RULER_SIZE || at.y < render::RULER_SIZE);
+        drag.remove_on_ruler |= !on_ruler;
+        let guides = &mut self.document.snap.guides;
+        if let Some(guide) = guides.get_mut(drag.index) {
+            guide.position = along(guide.axis);
+        }
+        if kind == "up" {
+            if on_ruler && drag.remove_on_ruler && drag.index < guides.len() {
+                guides.remove(drag.index);
+            }
+            self.guide_drag = None;
+            self.history.commit(&self.document);
+        } else {
+            self.guide_drag = Some(drag);
+        }
+        true
+    }
+
+    /// Imports an SVG file as new layers in one undo step. Returns a status
+    /// line summarizing what could not be imported, if anything.
+    pub fn import_svg(&mut self, path: &Path) -> Result<Option<String>> {
+        let imported = self.history.edit("Import SVG", &mut self.document, |doc| {
+            svg::import_file(doc, path)
+        })?;
+        if let Some(&top) = imported.layers.last() {
+            self.active_layer = Some(top);
+        }
+        self.selection = imported.layers;
+        Ok(warning_notice("Imported", path, &imported.warnings))
+    }
+
+    pub fn undo(&mut self) -> Option<String> {
+        let label = self.history.undo(&mut self.document);
+        self.forget_missing_nodes();
+        label
+    }
+
+    pub fn redo(&mut self) -> Option<String> {
+        let label = self.history.redo(&mut self.document);
+        self.forget_missing_nodes();
+        label
+    }
+
+    /// Drops references to nodes that an undo or redo took away.
+    fn forget_missing_nodes(&mut self) {
+        let document = &self.document;
+        self.selection.retain(|id| document.get(*id).is_some());
+        if self
+            .active_layer
+            .is_some_and(|id| document.get(id).is_none())
+        {
+            self.active_layer = document.layers.last().map(|layer| layer.id);
+        }
+        self.timeline.forget_missing(document);
+    }
+}
+
+/// View rotation per step of the rotate buttons and Alt+wheel, in degrees.
+const ROTATE_STEP: f32 = 15.0;
+
+/// History depth, overridable with `MOTION_SKETCH_UNDO_DEPTH`.
+fn undo_depth() -> usize {
+    std::env::var("MOTION_SKETCH_UNDO_DEPTH")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(history::DEFAULT_DEPTH)
+}
+
+/// Cheap handle given to every callback closure.
+#[derive(Clone)]
+struct EditorHandle {
+    ui: slint::Weak<AppWindow>,
+    editor: Rc<RefCell<Editor>>,
+}
+
+impl EditorHandle {
+    /// Runs an editor action, reports its error in the status bar and
+    /// refreshes the window.
+    fn run(&self, f: impl FnOnce(&mut Editor, &AppWindow) -> Result<()>) {
+        let Some(ui) = self.ui.upgrade() else {
+            return;
+        };
+        let mut editor = self.editor.borrow_mut();
+        sync_viewport(&mut editor, &ui);
+        let result = f(&mut editor, &ui).and_then(|_| refresh(&ui, &editor));
+        report(&ui, result);
+    }
+
+    /// Imports an SVG file, asking for one when `path` is `None`, and keeps
+    /// any import warnings in the status bar.
+    fn import_svg(&self, path: Option<PathBuf>) {
+        self.run_with_notice(|editor, _| {
+            let path = path.or_else(|| {
+                rfd::FileDialog::new()
+                    .add_filter("SVG image", &["svg"])
+                    .pick_file()
+            });
+            match path {
+                Some(path) => editor.import_svg(&path),
+                None => Ok(None),
+            }
+        });
+    }
+
+    /// Like [`EditorHandle::run`] for actions that succeed with a notice,
+    /// such as a list of export warnings, to keep in the status bar.
+    fn run_with_notice(&self, f: impl FnOnce(&mut Editor, &AppWindow) -> Result<Option<String>>) {
+        let mut notice = None;
+        self.run(|editor, ui| {
+            notice = f(editor, ui)?;
+            Ok(())
+        });
+        // `run` clears the status text after a successful action.
+        if let (Some(notice), Some(ui)) = (notice, self.ui.upgrade()) {
+            ui.set_status_text(notice.into());
+        }
+    }
+
+    /// Advances playback to the current time. Returns `false` once
+    /// playback has stopped.
+    fn tick_playback(&self) -> bool {
+        let Some(ui) = self.ui.upgrade() else {
+            return false;
+        };
+        let mut editor = self.editor.borrow_mut();
+        let (frame_rate, duration) = (editor.document.frame_rate, editor.document.duration);
+        let Some(frame) = editor.playback.tick(Instant::now(), frame_rate, duration) else {
+            return false;
+        };
+        let playing = editor.playback.is_playing();
+        // Timer ticks can outpace the frame rate; only redraw on a new frame.
+        if frame != editor.frame || !playing {
+            editor.frame = frame;
+            report(&ui, refresh(&ui, &editor));
+        }
+        playing
+    }
+}
+
+/// Starts or stops the playback timer to match the editor's state.
+fn sync_playback_timer(timer: &Rc<Timer>, handle: &EditorHandle, editor: &Editor) {
+    if !editor.playback.is_playing() {
+        timer.stop();
+        return;
+    }
+    let interval = editor.playback.tick_interval(editor.document.frame_rate);
+    let (handle, weak_timer) = (handle.clone(), Rc::downgrade(timer));
+    timer.start(TimerMode::Repeated, interval, move || {
+        if !handle.tick_playback() {
+            if let Some(timer) = Weak::upgrade(&weak_timer) {
+                timer.stop();
+            }
+        }
+    });
+}
+
+pub fn run() -> Result<()> {
+    let ui = AppWindow::new()?;
+    let handle = EditorHandle {
+        ui: ui.as_weak(),
+        editor: Rc::new(RefCell::new(Editor::new())),
+    };
+    sync_viewport(&mut handle.editor.borrow_mut(), &ui);
+    refresh(&ui, &handle.editor.borrow())?;
+    refresh_tool_options(&ui, &handle.editor.borrow());
+    let modes: Vec<SharedString> = LoopMode::ALL.iter().map(|m| m.label().into()).collect();
+    ui.set_loop_modes(ModelRc::new(VecModel::from(modes)));
+    let speeds: Vec<SharedString> = playback::SPEEDS
+        .iter()
+        .map(|speed| format!("{speed}x").into())
+        .collect();
+    ui.set_playback_speeds(ModelRc::new(VecModel::from(speeds)));
+    let quantizers: Vec<SharedString> = Quantizer::ALL.iter().map(|q| q.label().into()).collect();
+    ui.set_gif_quantizers(ModelRc::new(VecModel::from(quantizers)));
+
+    ui.on_open_project({
+        let handle = handle.clone();
+        move || handle.run(|editor, _| open_project(editor))
+    });
+    ui.on_save_project({
+        let handle = handle.clone();
+        move || handle.run(|editor, _| save_project(editor, false))
+    });
+    ui.on_save_project_as({
+        let handle = handle.clone();
+        move || handle.run(|editor, _| save_project(editor, true))
+    });
+    ui.on_export_svg({
+        let handle = handle.clone();
+        move || handle.run(|editor, _| export_svg(editor))
+    });
+    ui.on_export_lottie({
+        let handle = handle.clone();
+        move || handle.run_with_notice(|editor, _| export_lottie(editor))
+    });
+    ui.on_open_gif_export({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, ui| {
+                ui.set_export_range_text(export_range_text(editor).into());
+                ui.set_gif_dialog_open(true);
+                Ok(())
+            })
+        }
+    });
+    ui.on_export_gif({
+        let handle = handle.clone();
+        move |settings| handle.run(|editor, _| export_gif(editor, &settings))
+    });
+    ui.on_open_sprite_export({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, ui| {
+                ui.set_export_range_text(export_range_text(editor).into());
+                ui.set_sprite_dialog_open(true);
+                Ok(())
+            })
+        }
+    });
+    ui.on_export_sprites({
+        let handle = handle.clone();
+        move |settings| handle.run(|editor, _| export_sprites(editor, &settings))
+    });
+    ui.on_import_svg({
+        let handle = handle.clone();
+        move || handle.import_svg(None)
+    });
+    // Files dropped on the window are imported; slint has no drop events of
+    // its own, so this listens to the winit window directly.
+    ui.window().on_winit_window_event({
+        let handle = handle.clone();
+        move |_, event| {
+            let WindowEvent::DroppedFile(path) = event else {
+                return EventResult::Propagate;
+            };
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+            {
+                handle.import_svg(Some(path.clone()));
+            } else {
+                handle.run(|_, _| bail!("only SVG files can be dropped: {}", path.display()));
+            }
+            EventResult::PreventDefault
+        }
+    });
+
+    ui.on_select_tool({
+        let handle = handle.clone();
+        move |index| {
+            handle.run(|editor, ui| {
+                // Switching tools can finish or discard the tool's work.
+                editor.begin_tool_edit();
+                editor.select_tool(index.max(0) as usize);
+                editor.commit_edit();
+                refresh_tool_options(ui, editor);
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_tool_option({
+        let handle = handle.clone();
+        move |name, value| {
+            handle.run(|editor, _| {
+                if let Some(tool) = editor.palette.active_mut() {
+                    tool.set_option(&name, value);
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_canvas_pointer({
+        let handle = handle.clone();
+        move |kind, x, y, shift, alt, control| {
+            handle.run(|editor, _| {
+                if editor.guide_pointer(&kind, Point::new(x, y)) {
+                    return Ok(());
+                }
+                let pixel_size = editor.viewport.pixel_size();
+                let event = PointerEvent {
+                    position: editor.viewport.to_document(Point::new(x, y)),
+                    modifiers: Modifiers {
+                        shift,
+                        alt,
+                        control,
+                    },
+                };
+                // Everything from press to release is one undo step.
+                match kind.as_str() {
+                    "down" => {
+                        editor.begin_tool_edit();
+                        editor.with_tool(pixel_size, |tool, ctx| tool.pointer_down(ctx, &event));
+                    }
+                    "up" => {
+                        editor.with_tool(pixel_size, |tool, ctx| tool.pointer_up(ctx, &event));
+                        editor.commit_edit();
+                    }
+                    _ => editor.with_tool(pixel_size, |tool, ctx| tool.pointer_move(ctx, &event)),
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_canvas_key({
+        let handle = handle.clone();
+        move |text, shift, alt, control| {
+            let Some(key) = key_from_text(&text) else {
+                return false;
+            };
+            let event = KeyEvent {
+                key,
+                modifiers: Modifiers {
+                    shift,
+                    alt,
+                    control,
+                },
+            };
+            let mut handled = false;
+            handle.run(|editor, _| {
+                // Escape during a drag aborts it before the tool sees the key.
+                if event.key == Key::Escape && editor.cancel_edit() {
+                    handled = true;
+                    return Ok(());
+                }
+                let pixel_size = editor.viewport.pixel_size();
+                editor.begin_tool_edit();
+                editor.with_tool(pixel_size, |tool, ctx| handled = tool.key(ctx, &event));
+                editor.commit_edit();
+                if !handled && matches!(event.key, Key::Delete | Key::Backspace) {
+                    let Editor {
+                        document,
+                        history,
+                        timeline,
+                        ..
+                    } = editor;
+                    handled = history.edit("Delete keyframes", document, |document| {
+                        timeline.delete_selected(document)
+                    });
+                }
+                Ok(())
+            });
+            handled
+        }
+    });
+    ui.on_canvas_scroll({
+        let handle = handle.clone();
+        move |x, y, dy, rotate| {
+            handle.run(|editor, _| {
+                if rotate {
+                    editor.viewport.rotate_by(ROTATE_STEP.copysign(-dy));
+                } else {
+                    editor
+                        .viewport
+                        .zoom_by(1.1f32.powf(dy / 40.0), Point::new(x, y));
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_canvas_pan({
+        let handle = handle.clone();
+        move |dx, dy| {
+            handle.run(|editor, _| {
+                editor.viewport.pan_by(Point::new(dx, dy));
+                Ok(())
+            })
+        }
+    });
+    ui.on_view_command({
+        let handle = handle.clone();
+        move |command| handle.run(|editor, _| editor.view_command(&command))
+    });
+    // The view follows the panel size on every action; this one just
+    // redraws at the new size.
+    ui.on_viewport_resized({
+        let handle = handle.clone();
+        move || handle.run(|_, _| Ok(()))
+    });
+
+    ui.on_begin_property_edit({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.history.begin("Edit properties", &editor.document);
+                Ok(())
+            })
+        }
+    });
+    ui.on_end_property_edit({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.commit_edit();
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_property_number({
+        let handle = handle.clone();
+        move |key, value| {
+            handle.run(|editor, _| {
+                if let Some(id) = FieldId::from_key(&key) {
+                    editor.edit_field(id, FieldValue::Number(value));
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_property_channel({
+        let handle = handle.clone();
+        move |key, channel, value| {
+            handle.run(|editor, _| {
+                if let Some(id) = FieldId::from_key(&key) {
+                    editor.edit_channel(id, channel.max(0) as usize, value);
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_property_choice({
+        let handle = handle.clone();
+        move |key, index| {
+            handle.run(|editor, _| {
+                let (Some(id), Ok(index)) = (FieldId::from_key(&key), usize::try_from(index))
+                else {
+                    return Ok(());
+                };
+                // The entry after the easing presets is "custom", which only
+                // reveals the text field.
+                let value = match id.kind() {
+                    FieldKind::Easing => {
+                        Easing::presets().get(index).map(|e| FieldValue::Easing(*e))
+                    }
+                    _ => Some(FieldValue::Choice(index)),
+                };
+                if let Some(value) = value {
+                    editor.edit_field(id, value);
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_property_text({
+        let handle = handle.clone();
+        move |key, text| {
+            handle.run(|editor, _| {
+                if let Some(id @ FieldId::Easing) = FieldId::from_key(&key) {
+                    let easing: Easing = text.parse()?;
+                    editor.edit_field(id, FieldValue::Easing(easing));
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_toggle_keyframe({
+        let handle = handle.clone();
+        move |key| {
+            handle.run(|editor, _| {
+                if let Some(id) = FieldId::from_key(&key) {
+                    editor.toggle_keyframe(id);
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_auto_key({
+        let handle = handle.clone();
+        move |on| {
+            handle.run(|editor, _| {
+                editor.auto_key = on;
+                Ok(())
+            })
+        }
+    });
+
+    ui.on_set_onion_value({
+        let handle = handle.clone();
+        move |key, value| {
+            handle.run(|editor, _| {
+                let count = value.round().max(0.0) as u32;
+                editor
+                    .history
+                    .edit("Onion skin", &mut editor.document, |doc| {
+                        let onion = &mut doc.onion;
+                        match key.as_str() {
+                            "enabled" => onion.enabled = value != 0.0,
+                            "keyframes-only" => onion.keyframes_only = value != 0.0,
+                            "before" => onion.before = count,
+                            "after" => onion.after = count,
+                            "spacing" => onion.spacing = count.max(1),
+                            "opacity" => onion.opacity = value.clamp(0.0, 1.0),
+                            _ => {}
+                        }
+                    });
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_onion_tint({
+        let handle = handle.clone();
+        move |after, tint| {
+            handle.run(|editor, _| {
+                let tint = Color::from_rgba8(tint.red(), tint.green(), tint.blue(), tint.alpha());
+                editor
+                    .history
+                    .edit("Onion skin", &mut editor.document, |doc| {
+                        if after {
+                            doc.onion.after_tint = tint;
+                        } else {
+                            doc.onion.before_tint = tint;
+                        }
+                    });
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_snap_value({
+        let handle = handle.clone();
+        move |key, value| {
+            handle.run(|editor, _| {
+                editor
+                    .history
+                    .edit("Snapping", &mut editor.document, |doc| {
+                        let snap = &mut doc.snap;
+                        let on = value != 0.0;
+                        match key.as_str() {
+                            "enabled" => snap.enabled = on,
+                            "to-grid" => snap.to_grid = on,
+                            "to-guides" => snap.to_guides = on,
+                            "to-objects" => snap.to_objects = on,
+                            "to-anchors" => snap.to_anchors = on,
+                            "grid-visible" => snap.grid.visible = on,
+                            "isometric" => {
+                                snap.grid.kind = if on {
+                                    GridKind::Isometric
+                                } else {
+                                    GridKind::Square
+                                }
+                            }
+                            "spacing" => snap.grid.spacing = value.max(1.0),
+                            "rulers" => snap.rulers = on,
+                            "clear-guides" => snap.guides.clear(),
+                            _ => {}
+                        }
+                    });
+                Ok(())
+            })
+        }
+    });
+    ui.on_timeline_pointer({
+        let handle = handle.clone();
+        move |kind, x, y, shift, alt, control| {
+            handle.run(|editor, _| {
+                let modifiers = Modifiers {
+                    shift,
+                    alt,
+                    control,
+                };
+                match kind.as_str() {
+                    "down" => {
+                        editor.history.begin("Edit timeline", &editor.document);
+                        editor.with_timeline(|timeline, ctx| {
+                            timeline.pointer_down(ctx, x, y, modifiers)
+                        });
+                    }
+                    "up" => {
+                        editor.timeline.pointer_up();
+                        editor.commit_edit();
+                    }
+                    _ => editor.with_timeline(|timeline, ctx| timeline.pointer_move(ctx, x, y)),
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_timeline_scroll({
+        let handle = handle.clone();
+        move |x, dx, dy, zoom| {
+            handle.run(|editor, _| {
+                editor.timeline.scroll_by(&editor.document, x, dx, dy, zoom);
+                Ok(())
+            })
+        }
+    });
+    ui.on_timeline_zoom({
+        let handle = handle.clone();
+        move |factor| {
+            handle.run(|editor, _| {
+                editor.timeline.zoom_by(factor, timeline::HEADER_WIDTH);
+                Ok(())
+            })
+        }
+    });
+    ui.on_toggle_timeline_units({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.timeline.show_seconds = !editor.timeline.show_seconds;
+                Ok(())
+            })
+        }
+    });
+    ui.on_copy({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.timeline.copy(&editor.document);
+                Ok(())
+            })
+        }
+    });
+    ui.on_paste({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                let Editor {
+                    document,
+                    history,
+                    timeline,
+                    selection,
+                    frame,
+                    ..
+                } = editor;
+                history.edit("Paste keyframes", document, |document| {
+                    timeline.paste(document, *frame, selection)
+                });
+                Ok(())
+            })
+        }
+    });
+
+    let timer = Rc::new(Timer::default());
+    ui.on_toggle_play({
+        let (handle, timer) = (handle.clone(), timer.clone());
+        move || {
+            handle.run(|editor, _| {
+                if editor.playback.is_playing() {
+                    editor.playback.pause();
+                } else {
+                    let duration = editor.document.duration;
+                    editor.playback.play(Instant::now(), editor.frame, duration);
+                }
+                sync_playback_timer(&timer, &handle, editor);
+                Ok(())
+            })
+        }
+    });
+    ui.on_stop_playback({
+        let (handle, timer) = (handle.clone(), timer.clone());
+        move || {
+            handle.run(|editor, _| {
+                editor.frame = editor.playback.stop(editor.document.duration);
+                sync_playback_timer(&timer, &handle, editor);
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_loop_mode({
+        let handle = handle.clone();
+        move |index| {
+            handle.run(|editor, _| {
+                if let Some(mode) = usize::try_from(index)
+                    .ok()
+                    .and_then(|i| LoopMode::ALL.get(i))
+                {
+                    editor.playback.mode = *mode;
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_playback_speed({
+        let (handle, timer) = (handle.clone(), timer.clone());
+        move |index| {
+            handle.run(|editor, _| {
+                if let Some(speed) = usize::try_from(index)
+                    .ok()
+                    .and_then(|i| playback::SPEEDS.get(i))
+                {
+                    let frame_rate = editor.document.frame_rate;
+                    editor
+                        .playback
+                        .set_speed(*speed, Instant::now(), frame_rate);
+                    sync_playback_timer(&timer, &handle, editor);
+                }
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_loop_in({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                let start = editor.frame as u32;
+                let end = editor
+                    .playback
+                    .range
+                    .map_or(editor.document.duration.saturating_sub(1), |r| r.1);
+                editor.playback.range = Some((start, end.max(start)));
+                Ok(())
+            })
+        }
+    });
+    ui.on_set_loop_out({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                let end = editor.frame as u32;
+                let start = editor.playback.range.map_or(0, |r| r.0);
+                editor.playback.range = Some((start.min(end), end));
+                Ok(())
+            })
+        }
+    });
+    ui.on_clear_loop_range({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.playback.range = None;
+                Ok(())
+            })
+        }
+    });
+
+    ui.on_cel_command({
+        let handle = handle.clone();
+        move |command| handle.run(|editor, _| editor.cel_command(&command))
+    });
+
+    ui.on_undo({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.undo();
+                Ok(())
+            })
+        }
+    });
+    ui.on_redo({
+        let handle = handle.clone();
+        move || {
+            handle.run(|editor, _| {
+                editor.redo();
+                Ok(())
+            })
+        }
+    });
+
+    ui.run()?;
+    Ok(())
+}
+
+/// Pushes the editor state into the window.
+fn refresh(ui: &AppWindow, editor: &Editor) -> Result<()> {
+    let doc = &editor.document;
+    let pixel_size = editor.viewport.pixel_size();
+    // Draw the viewport on the CPU so the app also runs without a GPU
+    let frame = render::render_editor_view(doc, editor.frame, &editor.viewport, |canvas| {
+        if let Some(tool) = editor.palette.active() {
+            let ctx = OverlayContext {
+                document: doc,
+                selection: &editor.selection,
+                frame: editor.frame,
+                pixel_size,
+            };
+            tool.draw_overlay(canvas, &ctx);
+        }
+    })?;
+    ui.set_viewport_image(frame.to_slint_image());
+    let viewport = &editor.viewport;
+    let mut view_text = format!("{:.0}%", viewport.zoom * 100.0);
+    if viewport.rotation != 0.0 {
+        view_text += &format!(" {:.0}°", viewport.rotation);
+    }
+    ui.set_view_text(view_text.into());
+    ui.set_document_name(editor.display_name().into());
+
+    let names: Vec<SharedString> = editor.palette.names().into_iter().map(Into::into).collect();
+    ui.set_tool_names(ModelRc::new(VecModel::from(names)));
+    ui.set_active_tool(editor.palette.active_index() as i32);
+    ui.set_can_undo(editor.history.can_undo());
+    ui.set_can_redo(editor.history.can_redo());
+    let step = |verb: &str, label: Option<&str>| match label {
+        Some(label) => format!("{verb} {}", label.to_lowercase()),
+        None => verb.to_owned(),
+    };
+    ui.set_undo_text(step("Undo", editor.history.undo_label()).into());
+    ui.set_redo_text(step("Redo", editor.history.redo_label()).into());
+    refresh_properties(ui, editor);
+
+    ui.set_current_frame(editor.frame as i32);
+    ui.set_duration_frames(doc.duration as i32);
+    ui.set_timeline_seconds(editor.timeline.show_seconds);
+    ui.set_playing(editor.playback.is_playing());
+    let speed = editor.playback.speed();
+    if let Some(index) = playback::SPEEDS.iter().position(|s| *s == speed) {
+        ui.set_playback_speed_index(index as i32);
+    }
+    let onion = &doc.onion;
+    ui.set_onion(OnionData {
+        enabled: onion.enabled,
+        keyframes_only: onion.keyframes_only,
+        before: onion.before as i32,
+        after: onion.after as i32,
+        spacing: onion.spacing as i32,
+        opacity: onion.opacity,
+        before_tint: slint_color(onion.before_tint),
+        after_tint: slint_color(onion.after_tint),
+    });
+    let snap = &doc.snap;
+    ui.set_snap(SnapData {
+        enabled: snap.enabled,
+        to_grid: snap.to_grid,
+        to_guides: snap.to_guides,
+        to_objects: snap.to_objects,
+        to_anchors: snap.to_anchors,
+        grid_visible: snap.grid.visible,
+        isometric: snap.grid.kind == GridKind::Isometric,
+        spacing: snap.grid.spacing,
+        rulers: snap.rulers,
+        guide_count: snap.guides.len() as i32,
+    });
+    let (width, height) = (ui.get_timeline_width(), ui.get_timeline_height());
+    if width >= 1.0 && height >= 1.0 {
+        let image = timeline::render(
+            doc,
+            &editor.timeline,
+            editor.frame,
+            editor.playback.range,
+            width as u32,
+            height as u32,
+        )?;
+        ui.set_timeline_image(image.to_slint_image());
+    }
+    Ok(())
+}
+
+fn refresh_properties(ui: &AppWindow, editor: &Editor) {
+    let title = match editor.selection.as_slice() {
+        [] => String::new(),
+        [id] => editor
+            .document
+            .get(*id)
+            .map(|node| node.name.clone())
+            .unwrap_or_default(),
+        ids => format!("{} objects", ids.len()),
+    };
+    ui.set_selection_title(title.into());
+
+    let fields: Vec<PropertyField> = editor.fields().into_iter().map(property_field).collect();
+
+    // Update rows in place while the same fields are shown, so an editor
+    // being dragged is not recreated under the pointer.
+    let model = ui.get_property_fields();
+    if let Some(rows) = model.as_any().downcast_ref::<VecModel<PropertyField>>() {
+        let same_rows = rows.row_count() == fields.len()
+            && rows
+                .iter()
+                .zip(&fields)
+                .all(|(old, new)| old.key == new.key);
+        if same_rows {
+            for (index, field) in fields.into_iter().enumerate() {
+                if rows.row_data(index).as_ref() != Some(&field) {
+                    rows.set_row_data(index, field);
+                }
+            }
+            return;
+        }
+    }
+    ui.set_property_fields(ModelRc::new(VecModel::from(fields)));
+}
+
+fn slint_color(color: Color) -> slint::Color {
+    let [r, g, b, a] = color.to_rgba8();
+    slint::Color::from_argb_u8(a, r, g, b)
+}
+
+fn property_field(field: inspector::Field) -> PropertyField {
+    let mut row = PropertyField {
+        key: field.id.key().into(),
+        label: field.id.label().into(),
+        mixed: field.mixed,
+        animatable: field.id.property().is_some(),
+        animated: field.animated,
+        keyed: field.keyed,
+        ..Default::default()
+    };
+    match field.kind {
+        FieldKind::Number { min, max, step } => {
+            row.kind = "number".into();
+            row.minimum = min;
+            row.maximum = max;
+            row.step = step;
+        }
+        FieldKind::Color => row.kind = "color".into(),
+        FieldKind::Choice(choices) => {
+            row.kind = "choice".into();
+            let choices: Vec<SharedString> = choices.into_iter().map(Into::into).collect();
+            row.choices = ModelRc::new(VecModel::from(choices));
+        }
+        FieldKind::Easing => {
+            row.kind = "easing".into();
+            let mut choices: Vec<SharedString> = Easing::presets()
+                .iter()
+                .map(|e| e.to_string().into())
+                .collect();
+            choices.push("custom".into());
+            row.choices = ModelRc::new(VecModel::from(choices));
+        }
+    }
+    match field.value {
+        FieldValue::Number(value) => row.value = value,
+        FieldValue::Color(color) => {
+            row.color = slint_color(color);
+            let channels: Vec<f32> = color.to_rgba8().into_iter().map(f32::from).collect();
+            row.channels = ModelRc::new(VecModel::from(channels));
+        }
+        FieldValue::Choice(index) => row.choice = index as i32,
+        FieldValue::Easing(easing) => {
+            let presets = Easing::presets();
+            let index = presets.iter().position(|preset| *preset == easing);
+            row.choice = index.unwrap_or(presets.len()) as i32;
+            row.text = easing.to_string().into();
+        }
+    }
+    row
+}
+
+// Kept out of `refresh` because replacing the model while a slider is being
+// dragged would recreate the slider under the pointer.
+fn refresh_tool_options(ui: &AppWindow, editor: &Editor) {
+    let options: Vec<ToolOptionData> = editor
+        .palette
+        .active()
+        .map(|tool| tool.options())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|option| ToolOptionData {
+            name: option.name.into(),
+            value: option.value,
+            min: option.min,
+            max: option.max,
+        })
+        .collect();
+    ui.set_tool_options(ModelRc::new(VecModel::from(options)));
+}
+
+fn report(ui: &AppWindow, result: Result<()>) {
+    match result {
+        Ok(()) => ui.set_status_text("".into()),
+        Err(err) => {
+            eprintln!("{err:#}");
+            ui.set_status_text(format!("{err:#}").into());
+        }
+    }
+}
+
+/// Keeps the view in step with the size of the viewport panel.
+fn sync_viewport(editor: &mut Editor, ui: &AppWindow) {
+    let Editor {
+        viewport, document, ..
+    } = editor;
+    viewport.resize(ui.get_viewport_width(), ui.get_viewport_height(), document);
+}
+
+fn key_from_text(text: &str) -> Option<Key> {
+    match text {
+        "\u{1b}" => Some(Key::Escape),
+        "\n" | "\r" => Some(Key::Enter),
+        "\u{7f}" => Some(Key::Delete),
+        "\u{8}" => Some(Key::Backspace),
+        _ => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if !c.is_control() => Some(Key::Char(c)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn project_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new().add_filter("Motion Sketch project", &[project::EXTENSION])
+}
+
+fn open_project(editor: &mut Editor) -> Result<()> {
+    let Some(path) = project_dialog().pick_file() else {
+        return Ok(());
+    };
+    editor.document = project::load(&path)?;
+    editor.path = Some(path);
+    editor.frame = 0.0;
+    editor.selection.clear();
+    editor.active_layer = editor.document.layers.last().map(|layer| layer.id);
+    editor.history.clear();
+    editor.viewport.fit_artboard(&editor.document);
+    Ok(())
+}
+
+fn save_project(editor: &mut Editor, choose_path: bool) -> Result<()> {
+    let path = match (&editor.path, choose_path) {
+        (Some(path), false) => path.clone(),
+        _ => {
+            let Some(mut path) = project_dialog().set_file_name("Untitled.msk").save_file() else {
+                return Ok(());
+            };
+            if path.extension().is_none() {
+                path.set_extension(project::EXTENSION);
+            }
+            path
+        }
+    };
+    project::save(&editor.document, &path)?;
+    editor.path = Some(path);
+    Ok(())
+}
+
+/// Exports the frame at the playhead as SVG.
+fn export_svg(editor: &Editor) -> Result<()> {
+    let stem = editor.export_stem();
+    let Some(mut path) = rfd::FileDialog::new()
+        .add_filter("SVG image", &["svg"])
+        .set_file_name(format!("{stem}_{:04}.svg", editor.frame as u32))
+        .save_file()
+    else {
+        return Ok(());
+    };
+    if path.extension().is_none() {
+        path.set_extension("svg");
+    }
+    svg::save(&editor.document, editor.frame, 1.0, &path)
+}
+
+/// Exports the playback range as Lottie JSON. Returns a notice listing
+/// the features that could not be exported, if any.
+fn export_lottie(editor: &Editor) -> Result<Option<String>> {
+    let stem = editor.export_stem();
+    let Some(mut path) = rfd::FileDialog::new()
+        .add_filter("Lottie animation", &["json"])
+        .set_file_name(format!("{stem}.json"))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    if path.extension().is_none() {
+        path.set_extension("json");
+    }
+    let warnings = lottie::save(&editor.document, editor.export_range(), &path)?;
+    Ok(warning_notice("Exported", &path, &warnings))
+}
+
+/// Exports the playback range as an animated GIF.
+fn export_gif(editor: &Editor, settings: &GifSettings) -> Result<()> {
+    let stem = editor.export_stem();
+    let Some(mut path) = rfd::FileDialog::new()
+        .add_filter("GIF animation", &["gif"])
+        .set_file_name(format!("{stem}.gif"))
+        .save_file()
+    else {
+        return Ok(());
+    };
+    if path.extension().is_none() {
+        path.set_extension("gif");
+    }
+    let options = GifOptions {
+        quantizer: Quantizer::ALL
+            .get(settings.quantizer.max(0) as usize)
+            .copied()
+            .unwrap_or_default(),
+        palette: if settings.per_frame_palette {
+            PaletteMode::PerFrame
+        } else {
+            PaletteMode::Global
+        },
+        dither: settings.dither,
+        loop_count: settings.loop_count.clamp(0, u16::MAX as i32) as u16,
+        background: Background::Document,
+    };
+    gif::save(
+        &editor.document,
+        editor.export_range(),
+        1.0,
+        &options,
+        &path,
+    )
+}
+
+/// Exports the playback range as a PNG sequence into a chosen folder, or
+/// as a sprite sheet with its JSON descriptor.
+fn export_sprites(editor: &Editor, settings: &SheetSettings) -> Result<()> {
+    let stem = editor.export_stem();
+    let layout = match settings.layout {
+        0 => {
+            let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+                return Ok(());
+            };
+            return sprite::save_sequence(
+                &editor.document,
+                editor.export_range(),
+                1.0,
+                &dir,
+                &stem,
+            );
+        }
+        1 => SheetLayout::Grid,
+        _ => SheetLayout::Packed,
+    };
+    let Some(mut path) = rfd::FileDialog::new()
+        .add_filter("PNG sprite sheet", &["png"])
+        .set_file_name(format!("{stem}.png"))
+        .save_file()
+    else {
+        return Ok(());
+    };
+    if path.extension().is_none() {
+        path.set_extension("png");
+    }
+    let options = SheetOptions {
+        layout,
+        columns: u32::try_from(settings.columns).ok().filter(|&c| c > 0),
+        trim: settings.trim,
+        padding: settings.padding.max(0) as u32,
+        ..SheetOptions::default()
+    };
+    sprite::save(
+        &editor.document,
+        editor.export_range(),
+        1.0,
+        &options,
+        &path,
+    )?;
+    Ok(())
+}
+
+/// Describes the frames an animation export will cover.
+fn export_range_text(editor: &Editor) -> String {
+    let range = editor.export_range();
+    format!(
+        "Frames {} to {} at {} fps",
+        range.start,
+        range.end - 1,
+        editor.document.frame_rate
+    )
+}
+
+/// Status line for an import or export that skipped some content. The
+/// warnings are also printed in full to stderr.
+fn warning_notice(done: &str, path: &Path, warnings: &[String]) -> Option<String> {
+    for warning in warnings {
+        eprintln!("{}: {warning}", path.display());
+    }
+    match warnings {
+        [] => None,
+        [warning] => Some(format!("{done} with a warning: {warning}")),
+        _ => Some(format!(
+            "{done} with {} warnings: {}",
+            warnings.len(),
+            warnings.join("; ")
+        )),
+    }
+}
@@ -0,0 +1,312 @@
+// Cubic Bezier path editing helpers used by the pen tool.
+//
+// Paths are lists of `PathPoint`s; segment `i` runs from point `i` to point
+// `i + 1`, and closed paths have an extra segment from the last point back
+// to the first. The functions work on plain point lists so the same edit
+// can be applied to a path and to every keyframe of its animated points.
+
+use crate::scene::{PathPoint, Point};
+
+pub fn segment_count(len: usize, closed: bool) -> usize {
+    match (len, closed) {
+        (0 | 1, _) => 0,
+        (n, true) => n,
+        (n, false) => n - 1,
+    }
+}
+
+/// Control polygon of segment `index`.
+pub fn segment(points: &[PathPoint], closed: bool, index: usize) -> Option<[Point; 4]> {
+    if index >= segment_count(points.len(), closed) {
+        return None;
+    }
+    let a = points[index];
+    let b = points[(index + 1) % points.len()];
+    Some([a.anchor, a.handle_out, b.handle_in, b.anchor])
+}
+
+pub fn eval(c: [Point; 4], t: f32) -> Point {
+    let u = 1.0 - t;
+    c[0] * (u * u * u) + c[1] * (3.0 * u * u * t) + c[2] * (3.0 * u * t * t) + c[3] * (t * t * t)
+}
+
+/// Closest point on the path to `p`: `(segment, t, distance)`.
+pub fn nearest(points: &[PathPoint], closed: bool, p: Point) -> Option<(usize, f32, f32)> {
+    const SAMPLES: usize = 32;
+    let mut best: Option<(usize, f32, f32)> = None;
+    for index in 0..segment_count(points.len(), closed) {
+        let c = segment(points, closed, index)?;
+        for step in 0..=SAMPLES {
+            let t = step as f32 / SAMPLES as f32;
+            let distance = eval(c, t).distance(p);
+            if best.is_none_or(|(_, _, d)| distance < d) {
+                best = Some((index, t, distance));
+            }
+        }
+    }
+
+    // Refine the coarse hit with a few rounds of local search.
+    let (index, mut t, mut distance) = best?;
+    let c = segment(points, closed, index)?;
+    let mut step = 0.5 / SAMPLES as f32;
+    for _ in 0..16 {
+        for candidate in [t - step, t + step] {
+            let candidate = candidate.clamp(0.0, 1.0);
+            let d = eval(c, candidate).distance(p);
+            if d < distance {
+                (t, distance) = (candidate, d);
+            }
+        }
+        step /= 2.0;
+    }
+    Some((index, t, distance))
+}
+
+/// Inserts an anchor at `t` on segment `index` without changing the shape
+/// of the curve (de Casteljau subdivision). Returns the new point's index.
+pub fn split_segment(points: &mut Vec<PathPoint>, closed: bool, index: usize, t: f32) -> usize {
+    let Some([p0, p1, p2, p3]) = segment(points, closed, index) else {
+        return index;
+    };
+    let (p01, p12, p23) = (p0.lerp(p1, t), p1.lerp(p2, t), p2.lerp(p3, t));
+    let (p012, p123) = (p01.lerp(p12, t), p12.lerp(p23, t));
+    let anchor = p012.lerp(p123, t);
+
+    let next = (index + 1) % points.len();
+    points[index].handle_out = p01;
+    points[next].handle_in = p23;
+    let new_point = PathPoint {
+        anchor,
+        handle_in: p012,
+        handle_out: p123,
+    };
+    points.insert(index + 1, new_point);
+    index + 1
+}
+
+pub fn remove_point(points: &mut Vec<PathPoint>, index: usize) {
+    if index < points.len() {
+        points.remove(index);
+    }
+}
+
+/// A point is smooth when both handles are pulled out and collinear with
+/// the anchor.
+pub fn is_smooth(point: &PathPoint) -> bool {
+    let a = point.handle_in - point.anchor;
+    let b = point.handle_out - point.anchor;
+    if a.length() < 1e-3 || b.length() < 1e-3 {
+        return false;
+    }
+    let cross = a.x * b.y - a.y * b.x;
+    let dot = a.x * b.x + a.y * b.y;
+    dot < 0.0 && cross.abs() < 1e-3 * a.length() * b.length()
+}
+
+/// Retracts both handles onto the anchor.
+pub fn make_corner(point: &mut PathPoint) {
+    point.handle_in = point.anchor;
+    point.handle_out = point.anchor;
+}
+
+/// Pulls out symmetric handles parallel to the line between the neighbouring
+/// anchors, a third of the distance to each neighbour long.
+pub fn make_smooth(points: &mut [PathPoint], closed: bool, index: usize) {
+    let len = points.len();
+    if index >= len || len < 2 {
+        return;
+    }
+    let prev = if index > 0 {
+        Some(index - 1)
+    } else if closed {
+        Some(len - 1)
+    } else {
+        None
+    };
+    let next = if index + 1 < len {
+        Some(index + 1)
+    } else if closed {
+        Some(0)
+    } else {
+        None
+    };
+
+    let anchor = points[index].anchor;
+    let before = prev.map(|i| points[i].anchor).unwrap_or(anchor);
+    let after = next.map(|i| points[i].anchor).unwrap_or(anchor);
+    let direction = after - before;
+    let length = direction.length();
+    if length == 0.0 {
+        return;
+    }
+    let unit = direction * (1.0 / length);
+    points[index].handle_in = anchor - unit * (anchor.distance(before) / 3.0);
+    points[index].handle_out = anchor + unit * (anchor.distance(after) / 3.0);
+}
+
+/// Moves a handle. Unless `break_symmetry` is set, the opposite handle of
+/// a smooth point is rotated to stay collinear, keeping its own length.
+pub fn set_handle(point: &mut PathPoint, outgoing: bool, position: Point, break_symmetry: bool) {
+    let was_smooth = is_smooth(point);
+    let (moved, opposite) = if outgoing {
+        (&mut point.handle_out, &mut point.handle_in)
+    } else {
+        (&mut point.handle_in, &mut point.handle_out)
+    };
+    *moved = position;
+    if was_smooth && !break_symmetry {
+        let direction = point.anchor - position;
+        let length = direction.length();
+        if length > 0.0 {
+            let keep = opposite.distance(point.anchor);
+            *opposite = point.anchor + direction * (keep / length);
+        }
+    }
+}
+
+/// Moves an anchor together with its handles.
+pub fn move_anchor(point: &mut PathPoint, position: Point) {
+    let delta = position - point.anchor;
+    point.anchor = position;
+    point.handle_in = point.handle_in + delta;
+    point.handle_out = point.handle_out + delta;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(anchor: (f32, f32), handle_in: (f32, f32), handle_out: (f32, f32)) -> PathPoint {
+        let p = |(x, y)| Point::new(x, y);
+        PathPoint {
+            anchor: p(anchor),
+            handle_in: p(handle_in),
+            handle_out: p(handle_out),
+        }
+    }
+
+    fn close(a: Point, b: Point) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    /// An S-curve from (0, 0) to (30, 0).
+    fn curve() -> Vec<PathPoint> {
+        vec![
+            point((0.0, 0.0), (0.0, 0.0), (10.0, 20.0)),
+            point((30.0, 0.0), (20.0, -20.0), (30.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn segments_wrap_around_on_closed_paths() {
+        assert_eq!(segment_count(1, true), 0);
+        assert_eq!(segment_count(3, false), 2);
+        assert_eq!(segment_count(3, true), 3);
+        let points = curve();
+        let last = segment(&points, true, 1).unwrap();
+        assert_eq!(last[0], points[1].anchor);
+        assert_eq!(last[3], points[0].anchor);
+        assert_eq!(segment(&points, false, 1), None);
+    }
+
+    #[test]
+    fn splitting_keeps_the_curve_shape() {
+        let original = segment(&curve(), false, 0).unwrap();
+        let mut points = curve();
+        assert_eq!(split_segment(&mut points, false, 0, 0.25), 1);
+        assert_eq!(points.len(), 3);
+        assert!(close(points[1].anchor, eval(original, 0.25)));
+
+        let first = segment(&points, false, 0).unwrap();
+        let second = segment(&points, false, 1).unwrap();
+        for step in 0..=10 {
+            let t = step as f32 / 10.0;
+            assert!(close(eval(first, t), eval(original, t * 0.25)));
+            assert!(close(eval(second, t), eval(original, 0.25 + t * 0.75)));
+        }
+    }
+
+    #[test]
+    fn splitting_the_closing_segment_inserts_after_the_last_point() {
+        let mut points = vec![
+            PathPoint::corner(Point::new(0.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 10.0)),
+        ];
+        assert_eq!(split_segment(&mut points, true, 2, 0.5), 3);
+        assert!(close(points[3].anchor, Point::new(5.0, 5.0)));
+        assert_eq!(split_segment(&mut points, false, 9, 0.5), 9);
+        assert_eq!(points.len(), 4);
+    }
+
+    #[test]
+    fn nearest_finds_the_closest_segment_and_parameter() {
+        let points = vec![
+            PathPoint::corner(Point::new(0.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 10.0)),
+        ];
+        let (index, t, distance) = nearest(&points, false, Point::new(13.0, 7.0)).unwrap();
+        assert_eq!(index, 1);
+        let c = segment(&points, false, index).unwrap();
+        assert!(close(eval(c, t), Point::new(10.0, 7.0)));
+        assert!((distance - 3.0).abs() < 1e-3);
+
+        // Only closed paths have the diagonal back to the start.
+        let (index, _, _) = nearest(&points, true, Point::new(4.0, 6.0)).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(nearest(&points[..1], false, Point::ZERO), None);
+    }
+
+    #[test]
+    fn corner_and_smooth_conversion() {
+        let mut points = vec![
+            PathPoint::corner(Point::new(0.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 0.0)),
+            PathPoint::corner(Point::new(10.0, 30.0)),
+        ];
+        assert!(!is_smooth(&points[1]));
+        make_smooth(&mut points, false, 1);
+        assert!(is_smooth(&points[1]));
+        // Parallel to the neighbours' chord, a third of each distance long.
+        let chord = Point::new(10.0, 30.0) * (1.0 / 1000f32.sqrt());
+        assert!(close(
+            points[1].handle_in,
+            Point::new(10.0, 0.0) - chord * (10.0 / 3.0)
+        ));
+        assert!(close(
+            points[1].handle_out,
+            Point::new(10.0, 0.0) + chord * 10.0
+        ));
+
+        // An open path's end point only has its neighbour to go by.
+        make_smooth(&mut points, false, 0);
+        assert_eq!(points[0].handle_in, points[0].anchor);
+        assert!(close(points[0].handle_out, Point::new(10.0 / 3.0, 0.0)));
+
+        make_corner(&mut points[1]);
+        assert_eq!(points[1], PathPoint::corner(Point::new(10.0, 0.0)));
+    }
+
+    #[test]
+    fn handles_of_smooth_points_stay_collinear() {
+        let smooth = point((0.0, 0.0), (-10.0, 0.0), (5.0, 0.0));
+        let mut moved = smooth;
+        set_handle(&mut moved, true, Point::new(0.0, 8.0), false);
+        assert_eq!(moved.handle_out, Point::new(0.0, 8.0));
+        // The opposite handle turns along and keeps its length.
+        assert!(close(moved.handle_in, Point::new(0.0, -10.0)));
+
+        let mut broken = smooth;
+        set_handle(&mut broken, false, Point::new(0.0, 8.0), true);
+        assert_eq!(broken.handle_in, Point::new(0.0, 8.0));
+        assert_eq!(broken.handle_out, smooth.handle_out);
+    }
+
+    #[test]
+    fn moving_an_anchor_carries_its_handles() {
+        let mut p = point((1.0, 1.0), (0.0, 1.0), (2.0, 3.0));
+        move_anchor(&mut p, Point::new(11.0, 21.0));
+        assert_eq!(p, point((11.0, 21.0), (10.0, 21.0), (12.0, 23.0)));
+    }
+}
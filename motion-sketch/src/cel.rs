@@ -0,0 +1,344 @@
+// Frame-by-frame (cel) animation.
+//
+// A cel layer holds a sequence of drawings. Each drawing stays on screen for
+// its `hold` frames and the next one starts where it ends, so all timing
+// lives in the holds: retiming one exposure shifts every later drawing. The
+// first drawing starts at frame 0 and nothing is shown after the last one.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::scene::{Document, Node, NodeId, NodeKind};
+
+/// Hold given to new drawings when there is no neighbour to copy it from:
+/// animating "on twos".
+pub const DEFAULT_HOLD: u32 = 2;
+
+/// Where and for how long a drawing is exposed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exposure {
+    pub drawing: NodeId,
+    pub start: u32,
+    pub hold: u32,
+}
+
+impl Exposure {
+    /// First frame after the exposure.
+    pub fn end(&self) -> u32 {
+        self.start + self.hold
+    }
+
+    pub fn contains(&self, frame: f32) -> bool {
+        frame >= self.start as f32 && frame < self.end() as f32
+    }
+}
+
+/// Hold of a drawing node, `None` for any other node.
+pub fn hold(node: &Node) -> Option<u32> {
+    match node.kind {
+        NodeKind::Drawing { hold, .. } => Some(hold),
+        _ => None,
+    }
+}
+
+/// Exposures of the drawings of a cel layer, in order.
+pub fn exposures(layer: &Node) -> Vec<Exposure> {
+    let mut start = 0;
+    layer
+        .children()
+        .iter()
+        .filter_map(|child| {
+            let hold = hold(child)?;
+            let exposure = Exposure {
+                drawing: child.id,
+                start,
+                hold,
+            };
+            start += hold;
+            Some(exposure)
+        })
+        .collect()
+}
+
+/// Index among the layer's children of the drawing shown at `frame`.
+pub fn exposed_index(layer: &Node, frame: f32) -> Option<usize> {
+    let drawing = exposures(layer)
+        .into_iter()
+        .find(|e| e.contains(frame))?
+        .drawing;
+    layer.children().iter().position(|c| c.id == drawing)
+}
+
+/// False if `id` sits in a drawing that is not exposed at `frame`.
+pub fn is_exposed(doc: &Document, id: NodeId, frame: f32) -> bool {
+    let chain = doc.ancestry(id);
+    chain.windows(2).all(|pair| {
+        let (Some(parent), Some(child)) = (doc.get(pair[0]), doc.get(pair[1])) else {
+            return false;
+        };
+        if !parent.is_cel_layer() {
+            return true;
+        }
+        exposed_index(parent, frame).is_some_and(|i| parent.children()[i].id == child.id)
+    })
+}
+
+/// Frames at which any cel drawing starts, for keyframe-style navigation.
+pub fn drawing_starts(doc: &Document) -> Vec<f32> {
+    doc.layers
+        .iter()
+        .filter(|layer| layer.is_cel_layer())
+        .flat_map(exposures)
+        .map(|e| e.start as f32)
+        .collect()
+}
+
+/// The drawing new strokes on `layer` go to at `frame`: the exposed one, or
+/// a new drawing starting at `frame` when the playhead is past the end.
+pub fn drawing_for_edit(doc: &mut Document, layer: NodeId, frame: f32) -> Result<NodeId> {
+    let node = cel_layer(doc, layer)?;
+    if let Some(index) = exposed_index(node, frame) {
+        return Ok(node.children()[index].id);
+    }
+    append_blank(doc, layer, frame.max(0.0).floor() as u32)
+}
+
+/// Inserts an empty drawing right after the one exposed at `frame` (or at
+/// the end) with the same hold. Returns it and the frame it starts on.
+pub fn insert_blank(doc: &mut Document, layer: NodeId, frame: f32) -> Result<(NodeId, u32)> {
+    let node = cel_layer(doc, layer)?;
+    let Some(index) = exposed_index(node, frame) else {
+        let start = frame.max(0.0).floor() as u32;
+        return Ok((append_blank(doc, layer, start)?, start));
+    };
+    let exposure = exposures(node)[index];
+    let drawing = blank(doc, layer, exposure.hold);
+    let id = drawing.id;
+    doc.insert(Some(layer), index + 1, drawing)?;
+    Ok((id, exposure.end()))
+}
+
+/// Copies the drawing exposed at `frame` right after itself. Returns the
+/// copy and the frame it starts on.
+pub fn duplicate(doc: &mut Document, layer: NodeId, frame: f32) -> Result<(NodeId, u32)> {
+    let node = cel_layer(doc, layer)?;
+    let index = exposed_index(node, frame).ok_or_else(|| anyhow!("no drawing at this frame"))?;
+    let end = exposures(node)[index].end();
+    let original = node.children()[index].clone();
+    let mut copy = doc.duplicate(&original);
+    copy.name = next_name(doc, layer);
+    let id = copy.id;
+    doc.insert(Some(layer), index + 1, copy)?;
+    Ok((id, end))
+}
+
+/// Removes the drawing exposed at `frame`; later drawings move up.
+pub fn delete(doc: &mut Document, layer: NodeId, frame: f32) -> Result<()> {
+    let node = cel_layer(doc, layer)?;
+    let index = exposed_index(node, frame).ok_or_else(|| anyhow!("no drawing at this frame"))?;
+    let id = node.children()[index].id;
+    doc.remove(id)?;
+    Ok(())
+}
+
+/// Sets how many frames `drawing` stays on screen (at least one).
+pub fn set_hold(doc: &mut Document, drawing: NodeId, frames: u32) -> Result<()> {
+    let node = doc
+        .get_mut(drawing)
+        .ok_or_else(|| anyhow!("no node with id {drawing}"))?;
+    match &mut node.kind {
+        NodeKind::Drawing { hold, .. } => {
+            *hold = frames.max(1);
+            Ok(())
+        }
+        _ => bail!("node {drawing} is not a drawing"),
+    }
+}
+
+fn cel_layer(doc: &Document, layer: NodeId) -> Result<&Node> {
+    match doc.get(layer) {
+        Some(node) if node.is_cel_layer() => Ok(node),
+        Some(_) => bail!("the active layer is not a cel layer"),
+        None => bail!("no node with id {layer}"),
+    }
+}
+
+/// Appends a drawing starting at `start`, stretching the last drawing over
+/// any gap (or filling it with a blank drawing when the layer is empty).
+fn append_blank(doc: &mut Document, layer: NodeId, start: u32) -> Result<NodeId> {
+    let node = cel_layer(doc, layer)?;
+    let last = exposures(node).last().copied();
+    let end = last.map_or(0, |e| e.end());
+    let hold = last.map_or(DEFAULT_HOLD, |e| e.hold);
+    match last {
+        Some(last) if start > end => set_hold(doc, last.drawing, last.hold + start - end)?,
+        None if start > 0 => {
+            let filler = blank(doc, layer, start);
+            doc.insert(Some(layer), usize::MAX, filler)?;
+        }
+        _ => {}
+    }
+    let drawing = blank(doc, layer, hold);
+    let id = drawing.id;
+    doc.insert(Some(layer), usize::MAX, drawing)?;
+    Ok(id)
+}
+
+fn blank(doc: &mut Document, layer: NodeId, hold: u32) -> Node {
+    let name = next_name(doc, layer);
+    let children = Vec::new();
+    doc.make_node(name, NodeKind::Drawing { children, hold })
+}
+
+fn next_name(doc: &Document, layer: NodeId) -> String {
+    let count = doc.get(layer).map_or(0, |n| n.children().len());
+    format!("Drawing {}", count + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Geometry, Point, Shape};
+
+    /// `(start, hold)` of every exposure of `layer`.
+    fn timing(doc: &Document, layer: NodeId) -> Vec<(u32, u32)> {
+        exposures(doc.get(layer).unwrap())
+            .iter()
+            .map(|e| (e.start, e.hold))
+            .collect()
+    }
+
+    fn drawing(doc: &Document, layer: NodeId, index: usize) -> NodeId {
+        doc.get(layer).unwrap().children()[index].id
+    }
+
+    #[test]
+    fn exposures_follow_each_other() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        insert_blank(&mut doc, layer, 0.0).unwrap();
+        let id = drawing(&doc, layer, 1);
+        set_hold(&mut doc, id, 3).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 2), (2, 3)]);
+
+        let node = doc.get(layer).unwrap();
+        assert_eq!(exposed_index(node, 1.5), Some(0));
+        assert_eq!(exposed_index(node, 2.0), Some(1));
+        assert_eq!(exposed_index(node, 5.0), None);
+        assert_eq!(drawing_starts(&doc), [0.0, 2.0]);
+
+        let shape = Shape::new(Geometry::Ellipse {
+            radii: Point::new(5.0, 5.0),
+        });
+        let second = drawing(&doc, layer, 1);
+        let dot = doc.add_shape(second, "Dot", shape).unwrap();
+        assert!(!is_exposed(&doc, dot, 1.0));
+        assert!(is_exposed(&doc, dot, 4.0));
+        assert!(!is_exposed(&doc, dot, 5.0));
+    }
+
+    #[test]
+    fn retiming_shifts_later_drawings() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        for _ in 0..2 {
+            insert_blank(&mut doc, layer, 0.0).unwrap();
+        }
+        assert_eq!(timing(&doc, layer), [(0, 2), (2, 2), (4, 2)]);
+
+        let id = drawing(&doc, layer, 0);
+        set_hold(&mut doc, id, 5).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 5), (5, 2), (7, 2)]);
+        // Holds never drop below one frame.
+        let id = drawing(&doc, layer, 1);
+        set_hold(&mut doc, id, 0).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 5), (5, 1), (6, 2)]);
+        assert!(set_hold(&mut doc, layer, 3).is_err());
+    }
+
+    #[test]
+    fn drawing_past_the_end_stretches_the_last_drawing() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        let first = drawing(&doc, layer, 0);
+        assert_eq!(drawing_for_edit(&mut doc, layer, 1.0).unwrap(), first);
+
+        let new = drawing_for_edit(&mut doc, layer, 10.5).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 10), (10, 2)]);
+        assert_eq!(drawing(&doc, layer, 1), new);
+    }
+
+    #[test]
+    fn drawing_on_an_empty_layer_fills_the_gap() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        delete(&mut doc, layer, 0.0).unwrap();
+        assert!(timing(&doc, layer).is_empty());
+
+        let new = drawing_for_edit(&mut doc, layer, 4.0).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 4), (4, DEFAULT_HOLD)]);
+        assert_eq!(drawing(&doc, layer, 1), new);
+    }
+
+    #[test]
+    fn insert_blank_goes_after_the_exposed_drawing() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        let id = drawing(&doc, layer, 0);
+        set_hold(&mut doc, id, 3).unwrap();
+        let (last, start) = insert_blank(&mut doc, layer, 0.0).unwrap();
+        assert_eq!(start, 3);
+
+        let (middle, start) = insert_blank(&mut doc, layer, 1.0).unwrap();
+        assert_eq!(start, 3);
+        assert_eq!(timing(&doc, layer), [(0, 3), (3, 3), (6, 3)]);
+        assert_eq!(drawing(&doc, layer, 1), middle);
+        assert_eq!(drawing(&doc, layer, 2), last);
+
+        // Past the end the new drawing starts at the given frame.
+        let (_, start) = insert_blank(&mut doc, layer, 12.0).unwrap();
+        assert_eq!(start, 12);
+        assert_eq!(timing(&doc, layer), [(0, 3), (3, 3), (6, 6), (12, 3)]);
+    }
+
+    #[test]
+    fn duplicate_copies_the_exposed_drawing() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        let first = drawing(&doc, layer, 0);
+        let shape = Shape::new(Geometry::Ellipse {
+            radii: Point::new(5.0, 5.0),
+        });
+        doc.add_shape(first, "Dot", shape).unwrap();
+        insert_blank(&mut doc, layer, 0.0).unwrap();
+
+        let (copy, start) = duplicate(&mut doc, layer, 1.0).unwrap();
+        assert_eq!(start, 2);
+        assert_eq!(timing(&doc, layer), [(0, 2), (2, 2), (4, 2)]);
+        let node = doc.get(copy).unwrap();
+        assert_eq!(node.name, "Drawing 3");
+        assert_eq!(node.children().len(), 1);
+        assert_ne!(
+            node.children()[0].id,
+            doc.get(first).unwrap().children()[0].id
+        );
+        assert!(duplicate(&mut doc, layer, 6.0).is_err());
+    }
+
+    #[test]
+    fn delete_moves_later_drawings_up() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_cel_layer("Cels");
+        insert_blank(&mut doc, layer, 0.0).unwrap();
+        let id = drawing(&doc, layer, 1);
+        set_hold(&mut doc, id, 4).unwrap();
+        let last = drawing(&doc, layer, 1);
+
+        delete(&mut doc, layer, 0.0).unwrap();
+        assert_eq!(timing(&doc, layer), [(0, 4)]);
+        assert_eq!(drawing(&doc, layer, 0), last);
+        assert!(delete(&mut doc, layer, 9.0).is_err());
+
+        let plain = doc.add_layer("Plain");
+        assert!(delete(&mut doc, plain, 0.0).is_err());
+    }
+}
@@ -0,0 +1,497 @@
+// Headless command-line mode.
+//
+// `motion-sketch render project.msk --frames 0..120 --out frames/%04d.png`
+// renders through the raster path without ever creating a Slint window, so
+// it can run on build servers with no display. An `--out` path ending in
+// `.svg` writes SVG files instead of PNGs. Paths ending in `.json`, `.gif`,
+// `.apng` or `.webp` write a single Lottie, GIF, APNG or WebP animation
+// covering the frame range, and `--sheet` packs the range into one sprite
+// sheet PNG with a JSON descriptor. For video, `.y4m` writes uncompressed
+// YUV4MPEG2, and `--out -` or `--pipe <command>` stream raw frames to
+// stdout or to another program such as ffmpeg.
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::export::apng::{self, ApngOptions};
+use crate::export::gif::{self, GifOptions, PaletteMode};
+use crate::export::quantize::Quantizer;
+use crate::export::sprite::{self, DescriptorFormat, SheetLayout, SheetOptions};
+use crate::export::video::{self, StreamFormat};
+use crate::export::webp::{self, WebpOptions};
+use crate::export::yuv::{Chroma, ColorRange, Matrix, YuvOptions};
+use crate::render::{self, Background};
+use crate::{export, lottie, project, svg};
+
+pub const USAGE: &str = "\
+usage: motion-sketch [render <project.msk> [options]]
+
+Without arguments the editor window is opened.
+
+render options:
+  --frames <a..b>    frame range, end exclusive (`a..=b` for inclusive); default: whole document
+  --out <pattern>    output path, `%d` or `%04d` is replaced by the frame number;
+                     default: frame_%04d.png, or sheet.png with --sheet
+                     a `.svg` extension writes SVG instead of PNG
+                     a `.json` extension writes one Lottie animation of the whole range
+                     a `.gif` extension writes one animated GIF of the whole range
+                     a `.apng` extension writes one animated PNG of the whole range
+                     a `.webp` extension writes one animated WebP of the whole range
+                     a `.y4m` extension writes uncompressed YUV4MPEG2 video
+                     `-` streams frames to stdout in the --stream format
+  --pipe <command>   run a shell command and stream frames to its stdin in the --stream
+                     format; `{width}`, `{height}` and `{fps}` are replaced, e.g.
+                     --pipe \"ffmpeg -f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - out.mp4\"
+  --scale <factor>   output size relative to the artboard; default: 1
+  --transparent      leave out the artboard background in PNG, GIF, APNG and WebP output
+  --sheet <layout>   write one sprite sheet PNG and a JSON descriptor beside it,
+                     `grid` (one equal cell per frame) or `packed` (tight atlas)
+
+Sprite sheet options:
+  --columns <n>      grid columns; default: near-square
+  --no-trim          keep fully transparent borders
+  --padding <px>     transparent pixels between frames; default: 2
+  --pivot <x,y>      pivot as a fraction of the frame size; default: 0.5,0.5
+  --json <format>    descriptor layout, `hash` or `array` (TexturePacker JSON); default: hash
+
+Animation options:
+  --loop <n>         number of times a GIF, APNG or WebP plays, 0 to loop forever; default: 0
+
+GIF options:
+  --quantizer <q>    palette algorithm, `median-cut` or `neuquant`; default: median-cut
+  --palette <p>      `global` (shared by all frames) or `per-frame`; default: global
+  --no-dither        map colors to the palette without dithering
+
+APNG options:
+  --compression <c>  `fast`, `default` or `best`; default: best
+
+Video options:
+  --stream <format>  what --out - and --pipe send, `rgba` (raw straight-alpha RGBA, no
+                     header) or `y4m`; default: rgba
+  --matrix <m>       Y4M color matrix, `bt601` or `bt709`; default: bt709
+                     Y4M cannot record it, so pass the same to the encoder
+  --range <r>        Y4M range, `limited` (16-235) or `full` (0-255); default: limited
+  --chroma <c>       Y4M chroma subsampling, `420` or `444`; default: 420
+
+WebP options:
+  --lossy            lossy color (alpha stays exact); default: lossless
+  --quality <q>      0 to 100, image quality when lossy, compression effort when
+                     lossless; default: 75
+  --effort <n>       0 (fastest) to 6 (smallest file); default: 4";
+
+pub enum Command {
+    Render(RenderArgs),
+    /// Print the usage text.
+    Help,
+}
+
+pub struct RenderArgs {
+    pub project: PathBuf,
+    pub frames: Option<Range<u32>>,
+    pub out: String,
+    pub scale: f32,
+    /// Background of PNG frames; the animation formats carry their own.
+    pub background: Background,
+    pub gif: GifOptions,
+    pub apng: ApngOptions,
+    pub webp: WebpOptions,
+    pub sheet: Option<SheetOptions>,
+    pub yuv: YuvOptions,
+    pub stream: StreamFormat,
+    pub pipe: Option<String>,
+}
+
+impl Command {
+    /// Parses the process arguments (without the program name). Returns
+    /// `None` when no subcommand is given and the GUI should start.
+    pub fn parse(args: &[String]) -> Result<Option<Command>> {
+        let Some((command, rest)) = args.split_first() else {
+            return Ok(None);
+        };
+        match command.as_str() {
+            "render" => Ok(Some(Command::Render(parse_render(rest)?))),
+            "-h" | "--help" | "help" => Ok(Some(Command::Help)),
+            other => bail!("unknown command `{other}`\n\n{USAGE}"),
+        }
+    }
+
+    pub fn run(self) -> Result<()> {
+        match self {
+            Command::Render(args) => run_render(args),
+            Command::Help => {
+                println!("{USAGE}");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_render(args: &[String]) -> Result<RenderArgs> {
+    let mut project = None;
+    let mut frames = None;
+    let mut out = None;
+    let mut scale: f32 = 1.0;
+    let mut background = Background::Document;
+    let mut gif = GifOptions::default();
+    let mut apng = ApngOptions::default();
+    let mut webp = WebpOptions::default();
+    let mut loop_count = 0;
+    let mut layout = None;
+    let mut sheet = SheetOptions::default();
+    let mut yuv = YuvOptions::default();
+    let mut stream = StreamFormat::default();
+    let mut pipe = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| anyhow!("missing value for {name}\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--frames" => frames = Some(parse_range(value("--frames")?)?),
+            "--out" => out = Some(value("--out")?.clone()),
+            "--scale" => {
+                let text = value("--scale")?;
+                scale = text
+                    .parse()
+                    .with_context(|| format!("invalid scale `{text}`"))?;
+                if !scale.is_finite() || scale <= 0.0 {
+                    bail!("scale must be positive");
+                }
+            }
+            "--transparent" => background = Background::Transparent,
+            "--quantizer" => {
+                let text = value("--quantizer")?;
+                gif.quantizer = Quantizer::ALL
+                    .into_iter()
+                    .find(|q| q.name() == text)
+                    .ok_or_else(|| anyhow!("unknown quantizer `{text}`"))?;
+            }
+            "--palette" => {
+                gif.palette = match value("--palette")?.as_str() {
+                    "global" => PaletteMode::Global,
+                    "per-frame" => PaletteMode::PerFrame,
+                    other => bail!("unknown palette mode `{other}`"),
+                }
+            }
+            "--no-dither" => gif.dither = false,
+            "--loop" => {
+                let text = value("--loop")?;
+                loop_count = text
+                    .parse()
+                    .with_context(|| format!("invalid loop count `{text}`"))?;
+            }
+            "--compression" => {
+                apng.compression = match value("--compression")?.as_str() {
+                    "fast" => png::Compression::Fast,
+                    "default" => png::Compression::Default,
+                    "best" => png::Compression::Best,
+                    other => bail!("unknown compression `{other}`"),
+                }
+            }
+            "--lossy" => webp.lossless = false,
+            "--quality" => {
+                let text = value("--quality")?;
+                webp.quality = text
+                    .parse()
+                    .with_context(|| format!("invalid quality `{text}`"))?;
+                if !(0.0..=100.0).contains(&webp.quality) {
+                    bail!("quality must be between 0 and 100");
+                }
+            }
+            "--effort" => {
+                let text = value("--effort")?;
+                webp.effort = text
+                    .parse()
+                    .ok()
+                    .filter(|effort| *effort <= 6)
+                    .ok_or_else(|| anyhow!("invalid effort `{text}`, expected 0 to 6"))?;
+            }
+            "--sheet" => {
+                layout = Some(match value("--sheet")?.as_str() {
+                    "grid" => SheetLayout::Grid,
+                    "packed" => SheetLayout::Packed,
+                    other => bail!("unknown sheet layout `{other}`"),
+                })
+            }
+            "--columns" => {
+                let text = value("--columns")?;
+                let columns: u32 = text
+                    .parse()
+                    .with_context(|| format!("invalid column count `{text}`"))?;
+                if columns == 0 {
+                    bail!("column count must be positive");
+                }
+                sheet.columns = Some(columns);
+            }
+            "--no-trim" => sheet.trim = false,
+            "--padding" => {
+                let text = value("--padding")?;
+                sheet.padding = text
+                    .parse()
+                    .with_context(|| format!("invalid padding `{text}`"))?;
+            }
+            "--pivot" => {
+                let text = value("--pivot")?;
+                let invalid = || anyhow!("invalid pivot `{text}`, expected e.g. 0.5,1");
+                let (x, y) = text.split_once(',').ok_or_else(invalid)?;
+                sheet.pivot = [
+                    x.trim().parse().map_err(|_| invalid())?,
+                    y.trim().parse().map_err(|_| invalid())?,
+                ];
+            }
+            "--json" => {
+                sheet.format = match value("--json")?.as_str() {
+                    "hash" => DescriptorFormat::Hash,
+                    "array" => DescriptorFormat::Array,
+                    other => bail!("unknown descriptor format `{other}`"),
+                }
+            }
+            "--pipe" => pipe = Some(value("--pipe")?.clone()),
+            "--stream" => {
+                stream = match value("--stream")?.as_str() {
+                    "rgba" => StreamFormat::Rgba,
+                    "y4m" => StreamFormat::Y4m,
+                    other => bail!("unknown stream format `{other}`"),
+                }
+            }
+            "--matrix" => {
+                yuv.matrix = match value("--matrix")?.as_str() {
+                    "bt601" => Matrix::Bt601,
+                    "bt709" => Matrix::Bt709,
+                    other => bail!("unknown color matrix `{other}`"),
+                }
+            }
+            "--range" => {
+                yuv.range = match value("--range")?.as_str() {
+                    "limited" => ColorRange::Limited,
+                    "full" => ColorRange::Full,
+                    other => bail!("unknown color range `{other}`"),
+                }
+            }
+            "--chroma" => {
+                yuv.chroma = match value("--chroma")?.as_str() {
+                    "420" => Chroma::C420,
+                    "444" => Chroma::C444,
+                    other => bail!("unknown chroma subsampling `{other}`"),
+                }
+            }
+            flag if flag.starts_with("--") => bail!("unknown option `{flag}`\n\n{USAGE}"),
+            path if project.is_none() => project = Some(PathBuf::from(path)),
+            extra => bail!("unexpected argument `{extra}`\n\n{USAGE}"),
+        }
+    }
+
+    let project = project.ok_or_else(|| anyhow!("missing project file\n\n{USAGE}"))?;
+    let sheet = layout.map(|layout| SheetOptions { layout, ..sheet });
+    gif.loop_count = loop_count;
+    apng.loop_count = loop_count;
+    webp.loop_count = loop_count;
+    gif.background = background;
+    apng.background = background;
+    webp.background = background;
+    let out = out.unwrap_or_else(|| match sheet {
+        Some(_) => "sheet.png".into(),
+        None => "frame_%04d.png".into(),
+    });
+    Ok(RenderArgs {
+        project,
+        frames,
+        out,
+        scale,
+        background,
+        gif,
+        apng,
+        webp,
+        sheet,
+        yuv,
+        stream,
+        pipe,
+    })
+}
+
+/// Parses `a..b` (end exclusive) or `a..=b` (end inclusive).
+pub fn parse_range(text: &str) -> Result<Range<u32>> {
+    let invalid = || anyhow!("invalid frame range `{text}`, expected e.g. 0..120");
+    let (start, end) = text.split_once("..").ok_or_else(invalid)?;
+    let start: u32 = start.trim().parse().map_err(|_| invalid())?;
+    let end: u32 = match end.strip_prefix('=') {
+        Some(end) => end
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| invalid())?
+            .checked_add(1)
+            .ok_or_else(invalid)?,
+        None => end.trim().parse().map_err(|_| invalid())?,
+    };
+    if end <= start {
+        bail!("frame range `{text}` is empty");
+    }
+    Ok(start..end)
+}
+
+/// Substitutes the frame number into a printf-like `%d` / `%0Nd` pattern.
+/// Patterns without a placeholder get the number appended before the
+/// extension so frames never overwrite each other.
+pub fn frame_path(pattern: &str, frame: u32) -> PathBuf {
+    if let Some(start) = pattern.find('%') {
+        let rest = &pattern[start + 1..];
+        if let Some(end) = rest.find('d') {
+            let spec = &rest[..end];
+            if spec.chars().all(|c| c.is_ascii_digit()) {
+                let width: usize = spec.trim_start_matches('0').parse().unwrap_or(0);
+                let number = format!("{frame:0width$}");
+                return PathBuf::from(format!("{}{number}{}", &pattern[..start], &rest[end + 1..]));
+            }
+        }
+    }
+
+    let path = Path::new(pattern);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{stem}_{frame:04}.{ext}"),
+        None => format!("{stem}_{frame:04}"),
+    };
+    path.with_file_name(name)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn run_render(args: RenderArgs) -> Result<()> {
+    let doc = project::load(&args.project)?;
+    let frames = args.frames.unwrap_or(0..doc.duration);
+    let (width, height) = export::output_size(&doc, args.scale);
+
+    if let Some(command) = &args.pipe {
+        video::pipe(
+            &doc,
+            frames.clone(),
+            args.scale,
+            args.stream,
+            &args.yuv,
+            command,
+        )?;
+        eprintln!("streamed frames {frames:?} -> `{command}`");
+        return Ok(());
+    }
+    if args.out == "-" {
+        let mut out = BufWriter::new(io::stdout().lock());
+        video::write_stream(
+            &doc,
+            frames.clone(),
+            args.scale,
+            args.stream,
+            &args.yuv,
+            &mut out,
+        )?;
+        out.flush()?;
+        eprintln!("streamed frames {frames:?} -> stdout");
+        return Ok(());
+    }
+
+    let out = Path::new(&args.out);
+    if let Some(options) = &args.sheet {
+        if args.out.contains('%') {
+            bail!("a sprite sheet is a single file; `--out` cannot contain a frame number");
+        }
+        create_parent(out)?;
+        let descriptor = sprite::save(&doc, frames.clone(), args.scale, options, out)?;
+        eprintln!(
+            "exported frames {frames:?} -> {} and {}",
+            out.display(),
+            descriptor.display()
+        );
+        return Ok(());
+    }
+    if has_extension(out, "json") {
+        create_parent(out)?;
+        for warning in lottie::save(&doc, frames.clone(), out)? {
+            eprintln!("warning: {warning}");
+        }
+        eprintln!("exported frames {frames:?} -> {}", out.display());
+        return Ok(());
+    }
+    let animated = ["gif", "apng", "webp", "y4m"]
+        .into_iter()
+        .find(|extension| has_extension(out, extension));
+    if let Some(extension) = animated {
+        create_parent(out)?;
+        match extension {
+            "gif" => gif::save(&doc, frames.clone(), args.scale, &args.gif, out)?,
+            "apng" => apng::save(&doc, frames.clone(), args.scale, &args.apng, out)?,
+            "y4m" => video::save_y4m(&doc, frames.clone(), args.scale, &args.yuv, out)?,
+            _ => webp::save(&doc, frames.clone(), args.scale, &args.webp, out)?,
+        }
+        eprintln!("exported frames {frames:?} -> {}", out.display());
+        return Ok(());
+    }
+
+    for frame in frames {
+        let path = frame_path(&args.out, frame);
+        create_parent(&path)?;
+        if has_extension(&path, "svg") {
+            svg::save(&doc, frame as f32, args.scale, &path)?;
+        } else {
+            let image = render::render(&doc, frame as f32, width, height, args.background)
+                .with_context(|| format!("failed to render frame {frame}"))?;
+            image.save_png(&path)?;
+        }
+        eprintln!("rendered frame {frame} -> {}", path.display());
+    }
+    Ok(())
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("cannot create directory {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_parse_with_exclusive_and_inclusive_ends() {
+        assert_eq!(parse_range("0..120").unwrap(), 0..120);
+        assert_eq!(parse_range("10..=19").unwrap(), 10..20);
+        assert!(parse_range("5..5").is_err());
+        assert!(parse_range("5").is_err());
+    }
+
+    #[test]
+    fn inclusive_end_at_u32_max_is_rejected() {
+        assert!(parse_range("0..=4294967295").is_err());
+    }
+
+    #[test]
+    fn help_is_returned_rather_than_exiting() {
+        let args = vec!["--help".to_string()];
+        assert!(matches!(Command::parse(&args), Ok(Some(Command::Help))));
+    }
+
+    #[test]
+    fn transparent_applies_to_every_raster_format() {
+        let args: Vec<String> = ["render", "a.msk", "--transparent"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let Ok(Some(Command::Render(render))) = Command::parse(&args) else {
+            panic!("expected a render command");
+        };
+        assert_eq!(render.background, Background::Transparent);
+        assert_eq!(render.gif.background, Background::Transparent);
+        assert_eq!(render.apng.background, Background::Transparent);
+        assert_eq!(render.webp.background, Background::Transparent);
+    }
+}
@@ -0,0 +1,373 @@
+// Easing curves for keyframe segments.
+//
+// An easing maps linear progress `t` through a segment (0 at the start
+// keyframe, 1 at the next) to eased progress. The named families follow
+// Robert Penner's equations; `CubicBezier` matches CSS `cubic-bezier()`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Curve {
+    Quad,
+    Cubic,
+    Expo,
+    Back,
+    Elastic,
+    Bounce,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 6] = [
+        Curve::Quad,
+        Curve::Cubic,
+        Curve::Expo,
+        Curve::Back,
+        Curve::Elastic,
+        Curve::Bounce,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Curve::Quad => "quad",
+            Curve::Cubic => "cubic",
+            Curve::Expo => "expo",
+            Curve::Back => "back",
+            Curve::Elastic => "elastic",
+            Curve::Bounce => "bounce",
+        }
+    }
+
+    /// The "in" flavour of the curve; the other flavours are derived from it.
+    fn ease_in(self, t: f64) -> f64 {
+        use std::f64::consts::PI;
+        match self {
+            Curve::Quad => t * t,
+            Curve::Cubic => t * t * t,
+            Curve::Expo if t <= 0.0 => 0.0,
+            Curve::Expo => 2f64.powf(10.0 * t - 10.0),
+            Curve::Back => {
+                const C1: f64 = 1.70158;
+                (C1 + 1.0) * t * t * t - C1 * t * t
+            }
+            Curve::Elastic if t <= 0.0 || t >= 1.0 => t.clamp(0.0, 1.0),
+            Curve::Elastic => {
+                -(2f64.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin()
+            }
+            Curve::Bounce => 1.0 - bounce_out(1.0 - t),
+        }
+    }
+}
+
+fn bounce_out(t: f64) -> f64 {
+    const N1: f64 = 7.5625;
+    const D1: f64 = 2.75;
+    if t < 1.0 / D1 {
+        N1 * t * t
+    } else if t < 2.0 / D1 {
+        let t = t - 1.5 / D1;
+        N1 * t * t + 0.75
+    } else if t < 2.5 / D1 {
+        let t = t - 2.25 / D1;
+        N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D1;
+        N1 * t * t + 0.984375
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Keep the keyframe's value until the next keyframe.
+    Hold,
+    In(Curve),
+    Out(Curve),
+    InOut(Curve),
+    /// CSS-style `cubic-bezier(x1, y1, x2, y2)` through (0, 0) and (1, 1).
+    CubicBezier {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+}
+
+impl Easing {
+    /// CSS `ease`.
+    pub const EASE: Easing = Easing::CubicBezier {
+        x1: 0.25,
+        y1: 0.1,
+        x2: 0.25,
+        y2: 1.0,
+    };
+
+    /// Every named easing, in the order the Properties panel lists them.
+    pub fn presets() -> Vec<Easing> {
+        let mut presets = vec![Easing::Linear, Easing::Hold, Easing::EASE];
+        for curve in Curve::ALL {
+            presets.extend([Easing::In(curve), Easing::Out(curve), Easing::InOut(curve)]);
+        }
+        presets
+    }
+
+    /// Maps linear segment progress `t` in `0..=1` to eased progress.
+    /// Back, elastic and bezier easings may overshoot outside `0..=1`.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0) as f64;
+        let eased = match self {
+            Easing::Linear => t,
+            Easing::Hold => 0.0,
+            Easing::In(curve) => curve.ease_in(t),
+            Easing::Out(curve) => 1.0 - curve.ease_in(1.0 - t),
+            Easing::InOut(curve) if t < 0.5 => curve.ease_in(2.0 * t) / 2.0,
+            Easing::InOut(curve) => 1.0 - curve.ease_in(2.0 - 2.0 * t) / 2.0,
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                cubic_bezier(x1 as f64, y1 as f64, x2 as f64, y2 as f64, t)
+            }
+        };
+        eased as f32
+    }
+}
+
+/// Evaluates a CSS cubic-bezier timing function at `x`. The curve's x
+/// coordinate is inverted with Newton's method, falling back to bisection
+/// where the slope is too flat for Newton to converge.
+pub fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-7;
+    // CSS requires the x coordinates to stay in range so the curve is a
+    // function of x.
+    let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+
+    // Polynomial coefficients of B(s) = ((a s + b) s + c) s.
+    let coefficients = |p1: f64, p2: f64| {
+        let c = 3.0 * p1;
+        let b = 3.0 * (p2 - p1) - c;
+        let a = 1.0 - c - b;
+        (a, b, c)
+    };
+    let (ax, bx, cx) = coefficients(x1, x2);
+    let (ay, by, cy) = coefficients(y1, y2);
+    let curve_x = |s: f64| ((ax * s + bx) * s + cx) * s;
+    let slope_x = |s: f64| (3.0 * ax * s + 2.0 * bx) * s + cx;
+    let curve_y = |s: f64| ((ay * s + by) * s + cy) * s;
+
+    if x <= 0.0 || x >= 1.0 {
+        return x.clamp(0.0, 1.0);
+    }
+
+    let mut s = x;
+    for _ in 0..8 {
+        let error = curve_x(s) - x;
+        if error.abs() < EPSILON {
+            return curve_y(s);
+        }
+        let slope = slope_x(s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    let (mut lo, mut hi) = (0.0, 1.0);
+    s = x;
+    for _ in 0..64 {
+        let value = curve_x(s);
+        if (value - x).abs() < EPSILON {
+            break;
+        }
+        if value < x {
+            lo = s;
+        } else {
+            hi = s;
+        }
+        s = (lo + hi) / 2.0;
+    }
+    curve_y(s)
+}
+
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Easing::Linear => f.write_str("linear"),
+            Easing::Hold => f.write_str("hold"),
+            Easing::In(curve) => write!(f, "ease-in-{}", curve.name()),
+            Easing::Out(curve) => write!(f, "ease-out-{}", curve.name()),
+            Easing::InOut(curve) => write!(f, "ease-in-out-{}", curve.name()),
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                write!(f, "cubic-bezier({x1}, {y1}, {x2}, {y2})")
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseEasingError(String);
+
+impl fmt::Display for ParseEasingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown easing `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseEasingError {}
+
+/// Parses the names produced by `Display`, the CSS keywords `ease`,
+/// `ease-in`, `ease-out`, `ease-in-out` and `step-end`, and
+/// `cubic-bezier(x1, y1, x2, y2)`.
+impl FromStr for Easing {
+    type Err = ParseEasingError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseEasingError(text.to_owned());
+        let name = text.trim().to_ascii_lowercase();
+        let bezier = |x1, y1, x2, y2| Easing::CubicBezier { x1, y1, x2, y2 };
+
+        match name.as_str() {
+            "linear" => return Ok(Easing::Linear),
+            "hold" | "step-end" => return Ok(Easing::Hold),
+            "ease" => return Ok(Easing::EASE),
+            "ease-in" => return Ok(bezier(0.42, 0.0, 1.0, 1.0)),
+            "ease-out" => return Ok(bezier(0.0, 0.0, 0.58, 1.0)),
+            "ease-in-out" => return Ok(bezier(0.42, 0.0, 0.58, 1.0)),
+            _ => {}
+        }
+
+        if let Some(args) = name
+            .strip_prefix("cubic-bezier(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let values: Vec<f32> = args
+                .split(',')
+                .map(|v| v.trim().parse().map_err(|_| error()))
+                .collect::<Result<_, _>>()?;
+            return match values[..] {
+                [x1, y1, x2, y2] if (0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2) => {
+                    Ok(bezier(x1, y1, x2, y2))
+                }
+                _ => Err(error()),
+            };
+        }
+
+        let (flavour, curve): (fn(Curve) -> Easing, &str) =
+            if let Some(curve) = name.strip_prefix("ease-in-out-") {
+                (Easing::InOut, curve)
+            } else if let Some(curve) = name.strip_prefix("ease-in-") {
+                (Easing::In, curve)
+            } else if let Some(curve) = name.strip_prefix("ease-out-") {
+                (Easing::Out, curve)
+            } else {
+                return Err(error());
+            };
+        Curve::ALL
+            .into_iter()
+            .find(|c| c.name() == curve)
+            .map(flavour)
+            .ok_or_else(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f32, expected: f64, tolerance: f64) -> bool {
+        (actual as f64 - expected).abs() <= tolerance
+    }
+
+    /// Reference inverse of a bezier's x coordinate by plain bisection.
+    fn reference_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+        let bezier = |p1: f64, p2: f64, s: f64| {
+            3.0 * s * (1.0 - s) * (1.0 - s) * p1 + 3.0 * s * s * (1.0 - s) * p2 + s * s * s
+        };
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..100 {
+            let mid = (lo + hi) / 2.0;
+            if bezier(x1, x2, mid) < x {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        bezier(y1, y2, (lo + hi) / 2.0)
+    }
+
+    #[test]
+    fn penner_curves_match_published_values() {
+        // (curve, in, out, in-out) at t = 0.5, from Robert Penner's
+        // equations as published on easings.net.
+        let midpoints = [
+            (Curve::Quad, 0.25, 0.75, 0.5),
+            (Curve::Cubic, 0.125, 0.875, 0.5),
+            (Curve::Expo, 0.03125, 0.96875, 0.5),
+            (Curve::Back, -0.0876975, 1.0876975, 0.5),
+            (Curve::Elastic, -0.015625, 1.015625, 0.5),
+            (Curve::Bounce, 0.234375, 0.765625, 0.5),
+        ];
+        for (curve, ease_in, ease_out, ease_in_out) in midpoints {
+            for (easing, half) in [
+                (Easing::In(curve), ease_in),
+                (Easing::Out(curve), ease_out),
+                (Easing::InOut(curve), ease_in_out),
+            ] {
+                assert!(close(easing.apply(0.0), 0.0, 1e-6), "{easing} at 0");
+                assert!(close(easing.apply(0.5), half, 1e-6), "{easing} at 0.5");
+                assert!(close(easing.apply(1.0), 1.0, 1e-6), "{easing} at 1");
+            }
+        }
+    }
+
+    #[test]
+    fn linear_and_hold() {
+        assert_eq!(Easing::Linear.apply(0.3), 0.3);
+        assert_eq!(Easing::Hold.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn css_ease_matches_the_reference_curve() {
+        assert!(close(Easing::EASE.apply(0.5), 0.8024034, 1e-3));
+        for step in 0..=100 {
+            let x = step as f64 / 100.0;
+            let expected = reference_bezier(0.25, 0.1, 0.25, 1.0, x);
+            assert!(
+                close(Easing::EASE.apply(x as f32), expected, 1e-3),
+                "ease at {x}"
+            );
+        }
+    }
+
+    #[test]
+    fn steep_curves_fall_back_to_bisection() {
+        // x'(s) is zero at both ends, where Newton's method stalls.
+        for x in [
+            1e-6,
+            1e-4,
+            0.01,
+            0.3,
+            0.5,
+            0.7,
+            0.99,
+            1.0 - 1e-4,
+            1.0 - 1e-6,
+        ] {
+            let expected = reference_bezier(0.0, 1.0, 1.0, 0.0, x);
+            let actual = cubic_bezier(0.0, 1.0, 1.0, 0.0, x);
+            assert!(
+                (actual - expected).abs() < 1e-4,
+                "{x}: {actual} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for easing in Easing::presets() {
+            assert_eq!(easing.to_string().parse::<Easing>(), Ok(easing));
+        }
+        assert_eq!("ease".parse::<Easing>(), Ok(Easing::EASE));
+        assert!("cubic-bezier(2, 0, 0, 1)".parse::<Easing>().is_err());
+    }
+}
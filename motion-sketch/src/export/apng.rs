@@ -0,0 +1,129 @@
+// Animated PNG export.
+//
+// APNG keeps full 8-bit alpha and lossless color, so it suits overlays
+// that GIF's one-bit transparency cannot show. Every frame covers the
+// whole canvas and replaces the previous one outright, which keeps the
+// file correct in viewers that handle blending poorly. The frame delay is
+// a fraction of a second, so rates like 29.97 fps are stored exactly.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use png::{AdaptiveFilterType, BlendOp, ColorType, Compression, DisposeOp};
+
+use super::{output_size, render_frames};
+use crate::render::Background;
+use crate::scene::Document;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ApngOptions {
+    /// Trade-off between encoding speed and file size.
+    pub compression: Compression,
+    /// How many times the animation plays; 0 loops forever.
+    pub loop_count: u16,
+    pub background: Background,
+}
+
+impl Default for ApngOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::Best,
+            loop_count: 0,
+            background: Background::Document,
+        }
+    }
+}
+
+/// Writes `frames` of `doc` at `scale` as an animated PNG.
+pub fn save(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    options: &ApngOptions,
+    path: &Path,
+) -> Result<()> {
+    if frames.is_empty() {
+        bail!("the frame range is empty");
+    }
+    let (width, height) = output_size(doc, scale);
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(options.compression);
+    encoder.set_adaptive_filter(AdaptiveFilterType::Adaptive);
+    encoder.set_animated(frames.len() as u32, options.loop_count as u32)?;
+
+    let mut writer = encoder.write_header()?;
+    let (numerator, denominator) = frame_delay(doc.frame_rate);
+    writer.set_frame_delay(numerator, denominator)?;
+    writer.set_dispose_op(DisposeOp::None)?;
+    writer.set_blend_op(BlendOp::Source)?;
+    for frame in render_frames(doc, frames, scale, options.background) {
+        writer.write_image_data(&frame?.pixels)?;
+    }
+    writer
+        .finish()
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
+/// One frame's duration in seconds as a 16-bit fraction, exact to a
+/// hundredth of a frame per second.
+fn frame_delay(frame_rate: f32) -> (u16, u16) {
+    let mut numerator = 100u32;
+    let mut denominator = (frame_rate as f64 * 100.0).round().max(1.0) as u32;
+    let divisor = gcd(numerator, denominator);
+    numerator /= divisor;
+    denominator /= divisor;
+    // Too fine for 16 bits; fall back to milliseconds.
+    if denominator > u16::MAX as u32 {
+        let millis = (1000.0 / frame_rate.max(0.001) as f64).round().max(1.0);
+        return (millis.min(u16::MAX as f64) as u16, 1000);
+    }
+    (numerator as u16, denominator as u16)
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{assert_half_covered, half_covered};
+
+    #[test]
+    fn partial_alpha_survives() {
+        let path = std::env::temp_dir().join(format!("apng-alpha-{}.png", std::process::id()));
+        let options = ApngOptions {
+            compression: Compression::Fast,
+            background: Background::Transparent,
+            ..ApngOptions::default()
+        };
+        save(&half_covered(), 0..2, 1.0, &options, &path).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 2);
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        for _ in 0..2 {
+            let info = reader.next_frame(&mut pixels).unwrap();
+            assert_eq!(info.color_type, ColorType::Rgba);
+            assert_half_covered(&pixels);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn delays_are_exact_fractions() {
+        assert_eq!(frame_delay(24.0), (1, 24));
+        assert_eq!(frame_delay(29.97), (100, 2997));
+    }
+}
@@ -0,0 +1,204 @@
+// Animated GIF export.
+//
+// Each frame is reduced to at most 256 colors. With a global palette the
+// animation is rendered twice: once to sample colors from every frame and
+// once to encode, which keeps memory at one frame instead of the whole
+// animation. Per-frame palettes adapt to each frame at the cost of a color
+// table per frame. GIF transparency is one bit, so pixels below half alpha
+// become the reserved transparent index and the rest are treated as
+// opaque.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::Path;
+
+use ::gif::{DisposalMethod, Encoder, Repeat};
+use anyhow::{bail, Context, Result};
+
+use super::quantize::{self, Quantizer, Rgb, ALPHA_THRESHOLD};
+use super::{frame_delays, output_size, render_frames};
+use crate::render::Background;
+use crate::scene::Document;
+
+/// Opaque pixels sampled per frame when building a global palette.
+const SAMPLES_PER_FRAME: usize = 20_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaletteMode {
+    /// One palette shared by every frame.
+    #[default]
+    Global,
+    /// A palette of its own for each frame.
+    PerFrame,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GifOptions {
+    pub quantizer: Quantizer,
+    pub palette: PaletteMode,
+    /// Floyd-Steinberg dithering.
+    pub dither: bool,
+    /// How many times the animation plays; 0 loops forever.
+    pub loop_count: u16,
+    pub background: Background,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            quantizer: Quantizer::default(),
+            palette: PaletteMode::default(),
+            dither: true,
+            loop_count: 0,
+            background: Background::Document,
+        }
+    }
+}
+
+/// Writes `frames` of `doc` at `scale` as an animated GIF.
+pub fn save(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    options: &GifOptions,
+    path: &Path,
+) -> Result<()> {
+    let (width, height) = output_size(doc, scale);
+    let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height)) else {
+        bail!("GIF images are limited to 65535x65535 pixels, not {width}x{height}");
+    };
+    if frames.is_empty() {
+        bail!("the frame range is empty");
+    }
+
+    // Sampling pass: the shared palette and whether any pixel is
+    // transparent, which costs the palette one entry.
+    let (global, global_transparent) = match options.palette {
+        PaletteMode::Global => {
+            let mut samples = Vec::new();
+            let mut transparent = false;
+            for frame in render_frames(doc, frames.clone(), scale, options.background) {
+                let frame = frame?;
+                transparent |= has_transparency(&frame.pixels);
+                let step = (frame.pixels.len() / 4 / SAMPLES_PER_FRAME).max(1);
+                samples.extend(quantize::opaque_samples(&frame.pixels, step));
+            }
+            let colors = if transparent { 255 } else { 256 };
+            let palette = quantize::palette(&samples, colors, options.quantizer);
+            (Some(palette), transparent)
+        }
+        PaletteMode::PerFrame => (None, false),
+    };
+
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    let global_table = global.as_deref().map(color_table).unwrap_or_default();
+    let mut encoder = Encoder::new(BufWriter::new(file), gif_width, gif_height, &global_table)?;
+    encoder.set_repeat(match options.loop_count {
+        0 => Repeat::Infinite,
+        n => Repeat::Finite(n),
+    })?;
+
+    let delays = frame_delays(doc.frame_rate, frames.len(), 100);
+    for (frame, delay) in render_frames(doc, frames, scale, options.background).zip(delays) {
+        let frame = frame?;
+        let (palette, transparent) = match &global {
+            Some(palette) => (Cow::Borrowed(palette), global_transparent),
+            None => {
+                let transparent = has_transparency(&frame.pixels);
+                let samples: Vec<Rgb> = quantize::opaque_samples(&frame.pixels, 1).collect();
+                let colors = if transparent { 255 } else { 256 };
+                let palette = quantize::palette(&samples, colors, options.quantizer);
+                (Cow::Owned(palette), transparent)
+            }
+        };
+        // The transparent entry goes after the real colors.
+        let transparent_index = transparent.then_some(palette.len() as u8);
+        let indices = quantize::index_image(
+            &frame.pixels,
+            width as usize,
+            &palette,
+            transparent_index,
+            options.dither,
+        );
+
+        let mut gif_frame = ::gif::Frame {
+            width: gif_width,
+            height: gif_height,
+            buffer: Cow::Owned(indices),
+            delay: delay.min(u16::MAX as u32) as u16,
+            transparent: transparent_index,
+            // Clear each frame before the next so transparent areas do not
+            // show the previous frame through.
+            dispose: if transparent {
+                DisposalMethod::Background
+            } else {
+                DisposalMethod::Keep
+            },
+            ..Default::default()
+        };
+        if global.is_none() {
+            gif_frame.palette = Some(color_table(&palette));
+        }
+        encoder.write_frame(&gif_frame)?;
+    }
+    encoder
+        .into_inner()
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
+fn has_transparency(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4).any(|p| p[3] < ALPHA_THRESHOLD)
+}
+
+/// Flat RGB color table, padded with the transparent entry's black when
+/// the palette has room for it.
+fn color_table(palette: &[Rgb]) -> Vec<u8> {
+    let mut table: Vec<u8> = palette.iter().flatten().copied().collect();
+    if palette.len() < 256 {
+        table.extend([0, 0, 0]);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::half_covered;
+
+    #[test]
+    fn decodes_with_frames_delays_and_transparency() {
+        let path = std::env::temp_dir().join(format!("gif-export-{}.gif", std::process::id()));
+        let options = GifOptions {
+            background: Background::Transparent,
+            ..GifOptions::default()
+        };
+        save(&half_covered(), 0..3, 1.0, &options, &path).unwrap();
+
+        let mut decoder = ::gif::DecodeOptions::new();
+        decoder.set_color_output(::gif::ColorOutput::Indexed);
+        let mut decoder = decoder.read_info(File::open(&path).unwrap()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (8, 6));
+        // The covered half is the only color; the transparent entry follows.
+        let palette = decoder.global_palette().unwrap().to_vec();
+        let near = palette[..3]
+            .iter()
+            .zip([51, 102, 153])
+            .all(|(&value, expected)| value.abs_diff(expected) <= 2);
+        assert!(near, "palette {palette:?}");
+
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.transparent, Some(1));
+            assert_eq!(frame.dispose, DisposalMethod::Background);
+            for row in frame.buffer.chunks_exact(8) {
+                assert_eq!(row, [0, 0, 0, 0, 1, 1, 1, 1]);
+            }
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, [4, 4, 5]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
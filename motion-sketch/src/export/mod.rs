@@ -0,0 +1,114 @@
+// Animation exporters.
+//
+// Raster formats share one pipeline: frames are rendered one at a time
+// through the same raster path as the headless CLI and handed to the
+// encoder, so memory stays bounded by a single frame no matter how long
+// the animation is. Frame timing is derived from the document frame rate
+// by `frame_delays`, which carries rounding error over to later frames so
+// long animations do not drift.
+
+pub mod apng;
+pub mod gif;
+pub mod quantize;
+pub mod sprite;
+pub mod video;
+pub mod webp;
+pub mod yuv;
+
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+
+use crate::render::{self, Background, Frame};
+use crate::scene::Document;
+
+/// Output size in pixels of the artboard at `scale`.
+pub fn output_size(doc: &Document, scale: f32) -> (u32, u32) {
+    let width = ((doc.width as f32 * scale).round() as u32).max(1);
+    let height = ((doc.height as f32 * scale).round() as u32).max(1);
+    (width, height)
+}
+
+/// Renders each frame of `frames` at `scale` over `background`, lazily
+/// and in order.
+pub fn render_frames(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    background: Background,
+) -> impl Iterator<Item = Result<Frame>> + '_ {
+    let (width, height) = output_size(doc, scale);
+    frames.map(move |frame| {
+        render::render(doc, frame as f32, width, height, background)
+            .with_context(|| format!("failed to render frame {frame}"))
+    })
+}
+
+/// How long each of `count` frames is shown, in `1 / units_per_second`
+/// ticks. Each delay is rounded so that the running total stays as close
+/// as possible to the exact time, which matters for formats with coarse
+/// ticks such as GIF's hundredths of a second.
+pub fn frame_delays(frame_rate: f32, count: usize, units_per_second: u32) -> Vec<u32> {
+    let ticks_per_frame = units_per_second as f64 / frame_rate.max(0.001) as f64;
+    let end = |i: usize| (i as f64 * ticks_per_frame).round() as u32;
+    (0..count).map(|i| (end(i + 1) - end(i)).max(1)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Color, Fill, Geometry, Point, Shape};
+
+    /// An 8x6 artboard whose left half is covered by a half-transparent
+    /// rectangle, for checking that encoders keep partial alpha.
+    pub(super) fn half_covered() -> Document {
+        let mut doc = Document::new(8, 6);
+        let layer = doc.add_layer("Layer");
+        let mut shape = Shape::new(Geometry::Rect {
+            size: Point::new(4.0, 6.0),
+            corner_radius: 0.0,
+        });
+        shape.fill = Some(Fill::Solid(Color::rgba(0.2, 0.4, 0.6, 0.5)));
+        doc.add_shape(layer, "Half", shape).unwrap();
+        doc
+    }
+
+    /// Checks RGBA pixels of [`half_covered`] rendered over a transparent
+    /// background.
+    pub(super) fn assert_half_covered(pixels: &[u8]) {
+        assert_eq!(pixels.len(), 8 * 6 * 4);
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..][..4];
+        for y in 0..6 {
+            let covered = pixel(1, y);
+            let near = |value: u8, expected: u8| value.abs_diff(expected) <= 2;
+            assert!(
+                near(covered[0], 51) && near(covered[1], 102) && near(covered[2], 153),
+                "covered pixel {covered:?}"
+            );
+            assert!(
+                (127..=128).contains(&covered[3]),
+                "covered pixel {covered:?}"
+            );
+            assert_eq!(pixel(6, y)[3], 0, "uncovered pixel {:?}", pixel(6, y));
+        }
+    }
+
+    #[test]
+    fn background_choice() {
+        let doc = half_covered();
+        let frame = |background| render_frames(&doc, 0..1, 1.0, background).next();
+        let transparent = frame(Background::Transparent).unwrap().unwrap();
+        assert_half_covered(&transparent.pixels);
+
+        let opaque = frame(Background::Document).unwrap().unwrap();
+        assert!(opaque.pixels.chunks_exact(4).all(|p| p[3] == 255));
+        assert_eq!(&opaque.pixels[6 * 4..][..4], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn delays_carry_rounding_over() {
+        let delays = frame_delays(30.0, 3, 100);
+        assert_eq!(delays, [3, 4, 3]);
+        assert_eq!(frame_delays(24.0, 24, 1000).iter().sum::<u32>(), 1000);
+    }
+}
@@ -0,0 +1,327 @@
+// Palette quantization for indexed-color output.
+//
+// A palette is built from a sample of RGB pixels, either by median cut
+// (split the color box with the widest channel at its weighted median until
+// there are enough boxes) or by NeuQuant (a self-organizing map, slower but
+// better on photographic gradients). Images whose colors already fit are
+// kept exact. Mapping pixels to the palette can spread the rounding error
+// to neighbours with Floyd-Steinberg dithering.
+
+use std::collections::HashMap;
+
+/// Palette construction algorithm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quantizer {
+    #[default]
+    MedianCut,
+    NeuQuant,
+}
+
+impl Quantizer {
+    pub const ALL: [Quantizer; 2] = [Quantizer::MedianCut, Quantizer::NeuQuant];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Quantizer::MedianCut => "Median cut",
+            Quantizer::NeuQuant => "NeuQuant",
+        }
+    }
+
+    /// Name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantizer::MedianCut => "median-cut",
+            Quantizer::NeuQuant => "neuquant",
+        }
+    }
+}
+
+pub type Rgb = [u8; 3];
+
+/// Alpha below which a pixel counts as transparent in 1-bit output.
+pub const ALPHA_THRESHOLD: u8 = 128;
+
+/// Builds a palette of at most `max_colors` colors from RGB `samples`.
+pub fn palette(samples: &[Rgb], max_colors: usize, quantizer: Quantizer) -> Vec<Rgb> {
+    let max_colors = max_colors.clamp(1, 256);
+    if samples.is_empty() {
+        return vec![[0, 0, 0]];
+    }
+    let mut histogram: HashMap<Rgb, u32> = HashMap::new();
+    for &color in samples {
+        *histogram.entry(color).or_default() += 1;
+    }
+    if histogram.len() <= max_colors {
+        let mut colors: Vec<Rgb> = histogram.into_keys().collect();
+        colors.sort_unstable();
+        return colors;
+    }
+    match quantizer {
+        Quantizer::MedianCut => median_cut(histogram.into_iter().collect(), max_colors),
+        Quantizer::NeuQuant => neuquant(samples, max_colors),
+    }
+}
+
+/// Opaque pixels of an RGBA buffer, every `step`-th one.
+pub fn opaque_samples(rgba: &[u8], step: usize) -> impl Iterator<Item = Rgb> + '_ {
+    rgba.chunks_exact(4)
+        .step_by(step.max(1))
+        .filter(|p| p[3] >= ALPHA_THRESHOLD)
+        .map(|p| [p[0], p[1], p[2]])
+}
+
+fn median_cut(colors: Vec<(Rgb, u32)>, max_colors: usize) -> Vec<Rgb> {
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        // Split the box whose widest channel spans the most, weighted by
+        // how many pixels it covers so busy regions get more colors.
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                let count: u64 = colors.iter().map(|(_, n)| *n as u64).sum();
+                (index, channel, range as u64 * count)
+            })
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+        let total: u64 = colors.iter().map(|(_, n)| *n as u64).sum();
+        let mut seen = 0;
+        let mut split = colors.len() / 2;
+        for (i, (_, n)) in colors.iter().enumerate() {
+            seen += *n as u64;
+            if seen * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let upper = colors.split_off(split.clamp(1, colors.len() - 1));
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let mut palette: Vec<Rgb> = boxes
+        .iter()
+        .map(|colors| {
+            let mut sum = [0u64; 3];
+            let mut count = 0u64;
+            for (color, n) in colors {
+                for c in 0..3 {
+                    sum[c] += color[c] as u64 * *n as u64;
+                }
+                count += *n as u64;
+            }
+            sum.map(|s| ((s + count / 2) / count.max(1)) as u8)
+        })
+        .collect();
+    palette.sort_unstable();
+    palette.dedup();
+    palette
+}
+
+fn widest_channel(colors: &[(Rgb, u32)]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = colors.iter().map(|(color, _)| color[c]).min().unwrap_or(0);
+            let max = colors.iter().map(|(color, _)| color[c]).max().unwrap_or(0);
+            (c, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn neuquant(samples: &[Rgb], max_colors: usize) -> Vec<Rgb> {
+    // NeuQuant wants RGBA input and at least a few hundred pixels.
+    let rgba: Vec<u8> = samples
+        .iter()
+        .cycle()
+        .take(samples.len().max(512))
+        .flat_map(|&[r, g, b]| [r, g, b, 255])
+        .collect();
+    let quantizer = color_quant::NeuQuant::new(10, max_colors, &rgba);
+    let mut palette: Vec<Rgb> = quantizer
+        .color_map_rgb()
+        .chunks_exact(3)
+        .map(|c| [c[0], c[1], c[2]])
+        .collect();
+    palette.sort_unstable();
+    palette.dedup();
+    palette
+}
+
+/// Maps colors to their nearest palette entry. Palette colors map to
+/// themselves; other lookups go through a cache of 6-bit-per-channel cells,
+/// each resolved once for its center color.
+struct Mapper<'a> {
+    palette: &'a [Rgb],
+    exact: HashMap<Rgb, u8>,
+    cache: Vec<u16>,
+}
+
+impl<'a> Mapper<'a> {
+    const EMPTY: u16 = u16::MAX;
+
+    fn new(palette: &'a [Rgb]) -> Self {
+        let exact = palette
+            .iter()
+            .enumerate()
+            .map(|(index, color)| (*color, index as u8))
+            .collect();
+        Self {
+            palette,
+            exact,
+            cache: vec![Self::EMPTY; 1 << 18],
+        }
+    }
+
+    fn nearest(&mut self, color: Rgb) -> u8 {
+        if let Some(&index) = self.exact.get(&color) {
+            return index;
+        }
+        let [r, g, b] = color.map(|c| (c >> 2) as usize);
+        let key = (r << 12) | (g << 6) | b;
+        if self.cache[key] == Self::EMPTY {
+            let center = [r, g, b].map(|c| (c << 2 | 2) as u8);
+            self.cache[key] = self.search(center) as u16;
+        }
+        self.cache[key] as u8
+    }
+
+    fn search(&self, color: Rgb) -> usize {
+        self.palette
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| distance(color, **entry))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+fn distance(a: Rgb, b: Rgb) -> u32 {
+    // Weighted towards green, where the eye is most sensitive.
+    let d = |i: usize, weight: i32| {
+        let delta = a[i] as i32 - b[i] as i32;
+        (delta * delta * weight) as u32
+    };
+    d(0, 2) + d(1, 4) + d(2, 3)
+}
+
+/// Converts an RGBA image to palette indices. Pixels below the alpha
+/// threshold get `transparent`, or the nearest color if there is none.
+pub fn index_image(
+    rgba: &[u8],
+    width: usize,
+    palette: &[Rgb],
+    transparent: Option<u8>,
+    dither: bool,
+) -> Vec<u8> {
+    let mut mapper = Mapper::new(palette);
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+    // Floyd-Steinberg error for the current and the next row.
+    let mut error = vec![[0i16; 3]; width + 2];
+    let mut next_error = vec![[0i16; 3]; width + 2];
+
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            if pixel[3] < ALPHA_THRESHOLD {
+                if let Some(index) = transparent {
+                    indices.push(index);
+                    continue;
+                }
+            }
+            if !dither {
+                indices.push(mapper.nearest([pixel[0], pixel[1], pixel[2]]));
+                continue;
+            }
+            let wanted: [i16; 3] =
+                std::array::from_fn(|c| (pixel[c] as i16 + error[x + 1][c] / 16).clamp(0, 255));
+            let index = mapper.nearest(wanted.map(|c| c as u8));
+            indices.push(index);
+            let got = palette[index as usize];
+            for c in 0..3 {
+                let e = wanted[c] - got[c] as i16;
+                error[x + 2][c] += e * 7;
+                next_error[x][c] += e * 3;
+                next_error[x + 1][c] += e * 5;
+                next_error[x + 2][c] += e;
+            }
+        }
+        std::mem::swap(&mut error, &mut next_error);
+        next_error.fill([0; 3]);
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Vec<Rgb> {
+        (0..64u8).map(|i| [i * 4, 255 - i * 4, i * 2]).collect()
+    }
+
+    #[test]
+    fn colors_that_fit_are_kept_exactly() {
+        let samples = [[9, 9, 9], [200, 10, 30], [9, 9, 9], [0, 128, 255]];
+        for quantizer in Quantizer::ALL {
+            let palette = palette(&samples, 3, quantizer);
+            assert_eq!(palette, [[0, 128, 255], [9, 9, 9], [200, 10, 30]]);
+        }
+        assert_eq!(palette(&[], 16, Quantizer::MedianCut), [[0, 0, 0]]);
+    }
+
+    #[test]
+    fn median_cut_fills_the_palette() {
+        let samples = gradient();
+        for colors in [1, 2, 7, 16] {
+            assert_eq!(
+                palette(&samples, colors, Quantizer::MedianCut).len(),
+                colors
+            );
+        }
+        assert!(palette(&samples, 16, Quantizer::NeuQuant).len() <= 16);
+    }
+
+    #[test]
+    fn opaque_samples_skip_transparent_pixels() {
+        let rgba = [1, 2, 3, 255, 4, 5, 6, 0, 7, 8, 9, 128, 10, 11, 12, 255];
+        let all: Vec<Rgb> = opaque_samples(&rgba, 1).collect();
+        assert_eq!(all, [[1, 2, 3], [7, 8, 9], [10, 11, 12]]);
+        let every_other: Vec<Rgb> = opaque_samples(&rgba, 2).collect();
+        assert_eq!(every_other, [[1, 2, 3], [7, 8, 9]]);
+    }
+
+    #[test]
+    fn dithering_keeps_flat_colors_flat() {
+        let palette = [[0, 0, 0], [90, 160, 30], [255, 255, 255]];
+        let rgba: Vec<u8> = [90, 160, 30, 255].repeat(16 * 16);
+        let indices = index_image(&rgba, 16, &palette, None, true);
+        assert!(indices.iter().all(|&index| index == 1));
+    }
+
+    #[test]
+    fn dithering_preserves_the_average() {
+        let palette = [[0, 0, 0], [255, 255, 255]];
+        let rgba: Vec<u8> = [64, 64, 64, 255].repeat(32 * 32);
+        let plain = index_image(&rgba, 32, &palette, None, false);
+        assert!(plain.iter().all(|&index| index == 0));
+
+        let dithered = index_image(&rgba, 32, &palette, None, true);
+        let white = dithered.iter().filter(|&&index| index == 1).count();
+        let average = white as f32 * 255.0 / dithered.len() as f32;
+        assert!((average - 64.0).abs() < 4.0, "average {average}");
+    }
+
+    #[test]
+    fn transparent_pixels_get_the_transparent_index() {
+        let palette = [[255, 0, 0], [0, 0, 255]];
+        let rgba = [250, 0, 0, 255, 0, 0, 250, 100, 0, 0, 250, 255];
+        assert_eq!(index_image(&rgba, 3, &palette, Some(2), true), [0, 2, 1]);
+        assert_eq!(index_image(&rgba, 3, &palette, None, false), [0, 1, 1]);
+    }
+}
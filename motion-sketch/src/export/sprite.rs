@@ -0,0 +1,587 @@
+// PNG sequences and sprite sheets.
+//
+// A sprite sheet packs the frames of an animation into one PNG with a JSON
+// descriptor next to it in the TexturePacker "JSON (Hash)" or "JSON
+// (Array)" layout, which Phaser, PixiJS and most engine importers read.
+// Sprites are drawn over a game's own scene, so frames are rendered
+// without the artboard background and can be trimmed to their visible
+// pixels; the descriptor keeps the trimmed offset and the untrimmed source
+// size so engines still draw every frame at the same place, and the pivot
+// is given relative to the untrimmed frame for the same reason.
+//
+// A grid gives every frame a cell of the same size in playback order, so
+// when trimming it crops all frames to their combined bounds rather than
+// each to its own; the sheet stays usable by engines that slice by cell
+// size alone. A packed atlas trims frames individually, places them with a
+// skyline packer and stores identical frames, such as drawings held over
+// several frames on cel layers, only once.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value as Json};
+
+use super::{frame_delays, output_size, render_frames};
+use crate::render::{Background, Frame};
+use crate::scene::Document;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SheetLayout {
+    /// Equal cells in rows and columns, one per frame.
+    #[default]
+    Grid,
+    /// Frames at their own trimmed sizes, packed tightly.
+    Packed,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DescriptorFormat {
+    /// `frames` is an object keyed by frame name.
+    #[default]
+    Hash,
+    /// `frames` is an array whose entries carry a `filename`.
+    Array,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SheetOptions {
+    pub layout: SheetLayout,
+    /// Grid columns; `None` picks a near-square grid.
+    pub columns: Option<u32>,
+    /// Crop fully transparent borders.
+    pub trim: bool,
+    /// Transparent pixels between frames.
+    pub padding: u32,
+    /// Pivot as a fraction of the untrimmed frame size.
+    pub pivot: [f32; 2],
+    pub format: DescriptorFormat,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            layout: SheetLayout::default(),
+            columns: None,
+            trim: true,
+            padding: 2,
+            pivot: [0.5, 0.5],
+            format: DescriptorFormat::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+impl Rect {
+    fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            w: (self.x + self.w).max(other.x + other.w) - x,
+            h: (self.y + self.h).max(other.y + other.h) - y,
+        }
+    }
+
+    fn to_json(self) -> Json {
+        json!({ "x": self.x, "y": self.y, "w": self.w, "h": self.h })
+    }
+}
+
+/// A rendered frame cropped to `bounds` within the untrimmed frame.
+struct Sprite {
+    frame: u32,
+    bounds: Rect,
+    pixels: Vec<u8>,
+    /// Earlier sprite with the same content, whose place this one shares.
+    same_as: Option<usize>,
+}
+
+/// Writes each frame of `frames` as `{stem}_{frame:04}.png` in `dir`.
+pub fn save_sequence(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    dir: &Path,
+    stem: &str,
+) -> Result<()> {
+    let images = render_frames(doc, frames.clone(), scale, Background::Transparent);
+    for (frame, image) in frames.zip(images) {
+        image?.save_png(&dir.join(format!("{stem}_{frame:04}.png")))?;
+    }
+    Ok(())
+}
+
+/// Writes `frames` of `doc` at `scale` as a sprite sheet PNG at `path` and
+/// its descriptor beside it with a `.json` extension. Returns the
+/// descriptor's path.
+pub fn save(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    options: &SheetOptions,
+    path: &Path,
+) -> Result<PathBuf> {
+    if frames.is_empty() {
+        bail!("the frame range is empty");
+    }
+    let (width, height) = output_size(doc, scale);
+    let full = Rect {
+        x: 0,
+        y: 0,
+        w: width,
+        h: height,
+    };
+
+    // Only a packed atlas can reuse a place; a grid keeps one cell per
+    // frame so it can be sliced without the descriptor.
+    let share = options.layout == SheetLayout::Packed;
+    let mut sprites: Vec<Sprite> = Vec::new();
+    let mut by_hash: HashMap<u64, usize> = HashMap::new();
+    let images = render_frames(doc, frames.clone(), scale, Background::Transparent);
+    for (frame, image) in frames.clone().zip(images) {
+        let image = image?;
+        let bounds = if options.trim {
+            visible_bounds(&image)
+        } else {
+            full
+        };
+        let mut pixels = crop(&image, bounds);
+        let mut same_as = None;
+        if share {
+            let mut hasher = DefaultHasher::new();
+            (bounds, &pixels).hash(&mut hasher);
+            let hash = hasher.finish();
+            match by_hash.get(&hash) {
+                Some(&index)
+                    if sprites[index].bounds == bounds && sprites[index].pixels == pixels =>
+                {
+                    same_as = Some(index);
+                    pixels = Vec::new();
+                }
+                Some(_) => {}
+                None => {
+                    by_hash.insert(hash, sprites.len());
+                }
+            }
+        }
+        sprites.push(Sprite {
+            frame,
+            bounds,
+            pixels,
+            same_as,
+        });
+    }
+
+    // Where each sprite's frame rect goes in the sheet, and the part of the
+    // untrimmed frame that rect shows.
+    let padding = options.padding;
+    let mut slots = vec![Rect::default(); sprites.len()];
+    let mut sources = vec![full; sprites.len()];
+    match options.layout {
+        SheetLayout::Grid => {
+            let cell = sprites
+                .iter()
+                .map(|s| s.bounds)
+                .reduce(Rect::union)
+                .unwrap_or(full);
+            let count = sprites.len() as u32;
+            let columns = options
+                .columns
+                .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+                .clamp(1, count);
+            for (i, (slot, source)) in slots.iter_mut().zip(&mut sources).enumerate() {
+                let i = i as u32;
+                *slot = Rect {
+                    x: (i % columns) * (cell.w + padding),
+                    y: (i / columns) * (cell.h + padding),
+                    w: cell.w,
+                    h: cell.h,
+                };
+                *source = cell;
+            }
+        }
+        SheetLayout::Packed => {
+            let unique: Vec<usize> = (0..sprites.len())
+                .filter(|&i| sprites[i].same_as.is_none())
+                .collect();
+            let sizes: Vec<(u32, u32)> = unique
+                .iter()
+                .map(|&i| (sprites[i].bounds.w + padding, sprites[i].bounds.h + padding))
+                .collect();
+            for (&i, (x, y)) in unique.iter().zip(pack(&sizes)) {
+                let bounds = sprites[i].bounds;
+                slots[i] = Rect {
+                    x,
+                    y,
+                    w: bounds.w,
+                    h: bounds.h,
+                };
+                sources[i] = bounds;
+            }
+            for i in 0..sprites.len() {
+                if let Some(original) = sprites[i].same_as {
+                    slots[i] = slots[original];
+                    sources[i] = sources[original];
+                }
+            }
+        }
+    }
+
+    let sheet_width = slots.iter().map(|r| r.x + r.w).max().unwrap_or(1);
+    let sheet_height = slots.iter().map(|r| r.y + r.h).max().unwrap_or(1);
+    let mut sheet = Frame {
+        width: sheet_width,
+        height: sheet_height,
+        pixels: vec![0; sheet_width as usize * sheet_height as usize * 4],
+    };
+    for ((sprite, slot), source) in sprites.iter().zip(&slots).zip(&sources) {
+        if sprite.same_as.is_none() {
+            let x = slot.x + sprite.bounds.x - source.x;
+            let y = slot.y + sprite.bounds.y - source.y;
+            blit(&mut sheet, &sprite.pixels, sprite.bounds.w, x, y);
+        }
+    }
+    sheet.save_png(path)?;
+
+    let stem = path
+        .file_stem()
+        .map_or("sheet".into(), |stem| stem.to_string_lossy());
+    let image = path
+        .file_name()
+        .map_or("sheet.png".into(), |name| name.to_string_lossy());
+    let durations = frame_delays(doc.frame_rate, sprites.len(), 1000);
+    let pivot = options.pivot.map(|v| (v as f64 * 1000.0).round() / 1000.0);
+    let entries = sprites
+        .iter()
+        .zip(slots.iter().zip(&sources))
+        .zip(durations)
+        .map(|((sprite, (slot, source)), duration)| {
+            let name = format!("{stem}_{:04}.png", sprite.frame);
+            let entry = json!({
+                "frame": slot.to_json(),
+                "rotated": false,
+                "trimmed": *source != full,
+                "spriteSourceSize": source.to_json(),
+                "sourceSize": { "w": width, "h": height },
+                "pivot": { "x": pivot[0], "y": pivot[1] },
+                "duration": duration,
+            });
+            (name, entry)
+        });
+    let frames_json = match options.format {
+        DescriptorFormat::Hash => Json::Object(entries.collect::<Map<_, _>>()),
+        DescriptorFormat::Array => Json::Array(
+            entries
+                .map(|(name, mut entry)| {
+                    entry["filename"] = json!(name);
+                    entry
+                })
+                .collect(),
+        ),
+    };
+    let descriptor = json!({
+        "frames": frames_json,
+        "meta": {
+            "app": "motion-sketch",
+            "version": env!("CARGO_PKG_VERSION"),
+            "image": image,
+            "format": "RGBA8888",
+            "size": { "w": sheet_width, "h": sheet_height },
+            "scale": scale.to_string(),
+            "frameTags": [{
+                "name": stem,
+                "from": 0,
+                "to": sprites.len() - 1,
+                "direction": "forward",
+            }],
+        },
+    });
+
+    let descriptor_path = path.with_extension("json");
+    let text = serde_json::to_string_pretty(&descriptor)?;
+    fs::write(&descriptor_path, text)
+        .with_context(|| format!("cannot write {}", descriptor_path.display()))?;
+    Ok(descriptor_path)
+}
+
+/// Smallest rect holding every pixel that is not fully transparent. An
+/// empty frame keeps a single pixel so every frame has a rect.
+fn visible_bounds(image: &Frame) -> Rect {
+    let width = image.width as usize;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+    for (y, row) in image.pixels.chunks_exact(width * 4).enumerate() {
+        let mut visible = row.chunks_exact(4).enumerate().filter(|(_, p)| p[3] > 0);
+        let Some((first, _)) = visible.next() else {
+            continue;
+        };
+        let last = visible.next_back().map_or(first, |(x, _)| x);
+        min_x = min_x.min(first);
+        max_x = max_x.max(last);
+        min_y = min_y.min(y);
+        max_y = y;
+    }
+    if min_x == usize::MAX {
+        return Rect {
+            x: 0,
+            y: 0,
+            w: 1,
+            h: 1,
+        };
+    }
+    Rect {
+        x: min_x as u32,
+        y: min_y as u32,
+        w: (max_x - min_x + 1) as u32,
+        h: (max_y - min_y + 1) as u32,
+    }
+}
+
+fn crop(image: &Frame, rect: Rect) -> Vec<u8> {
+    let stride = image.width as usize * 4;
+    let (x, w) = (rect.x as usize * 4, rect.w as usize * 4);
+    image
+        .pixels
+        .chunks_exact(stride)
+        .skip(rect.y as usize)
+        .take(rect.h as usize)
+        .flat_map(|row| &row[x..x + w])
+        .copied()
+        .collect()
+}
+
+fn blit(sheet: &mut Frame, pixels: &[u8], width: u32, x: u32, y: u32) {
+    let stride = sheet.width as usize * 4;
+    let row_len = width as usize * 4;
+    for (row, src) in pixels.chunks_exact(row_len).enumerate() {
+        let start = (y as usize + row) * stride + x as usize * 4;
+        sheet.pixels[start..start + row_len].copy_from_slice(src);
+    }
+}
+
+/// Positions for rects of `sizes`. Tries a range of sheet widths around
+/// the square root of the total area and keeps the one that wastes least.
+fn pack(sizes: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| Reverse((sizes[i].1, sizes[i].0)));
+    let widest = sizes.iter().map(|s| s.0).max().unwrap_or(1);
+    let area: u64 = sizes.iter().map(|&(w, h)| w as u64 * h as u64).sum();
+    let side = (area as f64).sqrt().ceil() as u32;
+
+    let mut best: Option<(u64, Vec<(u32, u32)>)> = None;
+    for step in 0..=12 {
+        let width = widest.max(side * (8 + step * 2) / 16);
+        let positions = skyline(sizes, &order, width);
+        let (used_width, used_height) = positions
+            .iter()
+            .zip(sizes)
+            .fold((0, 0), |(w, h), (&(x, y), &(sw, sh))| {
+                (w.max(x + sw), h.max(y + sh))
+            });
+        let waste = used_width as u64 * used_height as u64;
+        if best.as_ref().is_none_or(|(best, _)| waste < *best) {
+            best = Some((waste, positions));
+        }
+    }
+    best.map(|(_, positions)| positions).unwrap_or_default()
+}
+
+/// Bottom-left skyline packing of `sizes`, visited in `order`, into a strip
+/// `width` wide: each rect goes where its top edge ends up lowest.
+fn skyline(sizes: &[(u32, u32)], order: &[usize], width: u32) -> Vec<(u32, u32)> {
+    // Segments of the skyline as (x, top, width), left to right.
+    let mut segments = vec![(0u32, 0u32, width)];
+    let mut positions = vec![(0, 0); sizes.len()];
+    for &i in order {
+        let (w, h) = sizes[i];
+        let mut best: Option<(u32, u32)> = None;
+        for start in 0..segments.len() {
+            let x = segments[start].0;
+            if x + w > width {
+                break;
+            }
+            let y = segments[start..]
+                .iter()
+                .take_while(|s| s.0 < x + w)
+                .map(|s| s.1)
+                .max()
+                .unwrap_or(0);
+            if best.is_none_or(|(bx, by)| (y + h, x) < (by + h, bx)) {
+                best = Some((x, y));
+            }
+        }
+        // Only a rect wider than the strip gets here; it goes on top.
+        let (x, y) = best.unwrap_or_else(|| (0, segments.iter().map(|s| s.1).max().unwrap_or(0)));
+        positions[i] = (x, y);
+
+        let end = x + w;
+        let mut next = Vec::with_capacity(segments.len() + 2);
+        for &(sx, top, sw) in &segments {
+            if sx + sw <= x || sx >= end {
+                next.push((sx, top, sw));
+                continue;
+            }
+            if sx < x {
+                next.push((sx, top, x - sx));
+            }
+            if sx + sw > end {
+                next.push((end, top, sx + sw - end));
+            }
+        }
+        next.push((x, y + h, w));
+        next.sort_unstable_by_key(|s| s.0);
+        next.dedup_by(|right, left| {
+            let merge = left.1 == right.1;
+            if merge {
+                left.2 += right.2;
+            }
+            merge
+        });
+        segments = next;
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Property, Value};
+    use crate::scene::{Color, Fill, Geometry, Point, Shape};
+
+    /// A 4x3 box on a 16x12 artboard, moving 4 pixels right and 3 down per
+    /// frame over frames 0 to 2 and holding still on frame 3.
+    fn moving_box() -> Document {
+        let mut doc = Document::new(16, 12);
+        let layer = doc.add_layer("Layer");
+        let mut shape = Shape::new(Geometry::Rect {
+            size: Point::new(4.0, 3.0),
+            corner_radius: 0.0,
+        });
+        shape.fill = Some(Fill::Solid(Color::rgb(1.0, 0.0, 0.0)));
+        let id = doc.add_shape(layer, "Box", shape).unwrap();
+        let animation = &mut doc.get_mut(id).unwrap().animation;
+        animation.set_keyframe(Property::Position, 0.0, Value::Point(Point::new(2.0, 1.0)));
+        animation.set_keyframe(Property::Position, 2.0, Value::Point(Point::new(10.0, 7.0)));
+        doc
+    }
+
+    /// Exports `moving_box` as a sheet and returns the descriptor with the
+    /// sheet's pixels.
+    fn export(name: &str, options: &SheetOptions) -> (Json, Frame) {
+        let dir = std::env::temp_dir().join(format!("sprite-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sheet.png");
+        let descriptor = save(&moving_box(), 0..4, 1.0, options, &path).unwrap();
+        let json = serde_json::from_str(&fs::read_to_string(descriptor).unwrap()).unwrap();
+
+        let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let sheet = Frame {
+            width: info.width,
+            height: info.height,
+            pixels,
+        };
+        (json, sheet)
+    }
+
+    fn rect(x: u32, y: u32, w: u32, h: u32) -> Json {
+        Rect { x, y, w, h }.to_json()
+    }
+
+    /// True if every pixel of `area` in `sheet` is the box's opaque red.
+    fn filled(sheet: &Frame, area: &Json) -> bool {
+        let value = |key: &str| area[key].as_u64().unwrap() as u32;
+        (value("y")..value("y") + value("h")).all(|y| {
+            (value("x")..value("x") + value("w")).all(|x| {
+                let start = (y * sheet.width + x) as usize * 4;
+                sheet.pixels[start..start + 4] == [255, 0, 0, 255]
+            })
+        })
+    }
+
+    #[test]
+    fn packed_frames_are_trimmed_to_the_box() {
+        let options = SheetOptions {
+            layout: SheetLayout::Packed,
+            ..SheetOptions::default()
+        };
+        let (json, sheet) = export("packed", &options);
+        let frames = &json["frames"];
+        let offsets = [(2, 1), (6, 4), (10, 7), (10, 7)];
+        for (frame, (x, y)) in offsets.into_iter().enumerate() {
+            let entry = &frames[format!("sheet_{frame:04}.png")];
+            assert_eq!(entry["trimmed"], true);
+            assert_eq!(entry["spriteSourceSize"], rect(x, y, 4, 3));
+            assert_eq!(entry["sourceSize"], json!({ "w": 16, "h": 12 }));
+            assert_eq!(entry["frame"]["w"], 4);
+            assert_eq!(entry["frame"]["h"], 3);
+            assert!(filled(&sheet, &entry["frame"]), "frame {frame}");
+        }
+        // The held frame shares the place of the one before it.
+        assert_eq!(
+            frames["sheet_0003.png"]["frame"],
+            frames["sheet_0002.png"]["frame"]
+        );
+        assert_ne!(
+            frames["sheet_0001.png"]["frame"],
+            frames["sheet_0002.png"]["frame"]
+        );
+    }
+
+    #[test]
+    fn grid_cells_are_trimmed_to_the_combined_bounds() {
+        let options = SheetOptions {
+            columns: Some(2),
+            padding: 1,
+            format: DescriptorFormat::Array,
+            ..SheetOptions::default()
+        };
+        let (json, sheet) = export("grid", &options);
+        let frames = json["frames"].as_array().unwrap();
+        assert_eq!(frames.len(), 4);
+        for (index, entry) in frames.iter().enumerate() {
+            let (column, row) = (index as u32 % 2, index as u32 / 2);
+            assert_eq!(entry["filename"], format!("sheet_{index:04}.png"));
+            assert_eq!(entry["trimmed"], true);
+            assert_eq!(entry["spriteSourceSize"], rect(2, 1, 12, 9));
+            assert_eq!(entry["frame"], rect(column * 13, row * 10, 12, 9));
+        }
+        assert_eq!(json["meta"]["size"], json!({ "w": 25, "h": 19 }));
+        // Frame 1's box sits 4 right and 3 down from the cell's corner.
+        assert!(filled(&sheet, &rect(13 + 4, 3, 4, 3)));
+        assert_eq!(
+            sheet.pixels[13 * 4 + 3],
+            0,
+            "the cell corner is transparent"
+        );
+    }
+
+    #[test]
+    fn untrimmed_frames_keep_the_whole_artboard() {
+        let options = SheetOptions {
+            layout: SheetLayout::Packed,
+            trim: false,
+            ..SheetOptions::default()
+        };
+        let (json, _) = export("untrimmed", &options);
+        let entry = &json["frames"]["sheet_0000.png"];
+        assert_eq!(entry["trimmed"], false);
+        assert_eq!(entry["spriteSourceSize"], rect(0, 0, 16, 12));
+    }
+}
@@ -0,0 +1,164 @@
+// Uncompressed video for external encoders.
+//
+// Frames are written as YUV4MPEG2 or as bare RGBA, either to a file, to
+// stdout or to the stdin of a command, so `motion-sketch render` can feed
+// ffmpeg or any other encoder without linking one in. Y4M carries the
+// size, frame rate and chroma layout in its header, and the color range as
+// ffmpeg's `XCOLORRANGE` extension; it has no field for the matrix, so
+// the encoder has to be told which of BT.601 and BT.709 was used. Raw RGBA
+// has no header at all and keeps straight alpha.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use super::yuv::{self, Chroma, ColorRange, YuvOptions};
+use super::{output_size, render_frames};
+use crate::render::Background;
+use crate::scene::Document;
+
+/// What goes down a stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Width x height x 4 bytes per frame, no header.
+    #[default]
+    Rgba,
+    Y4m,
+}
+
+/// Writes `frames` of `doc` at `scale` as a Y4M file.
+pub fn save_y4m(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    options: &YuvOptions,
+    path: &Path,
+) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    write_stream(doc, frames, scale, StreamFormat::Y4m, options, &mut out)
+        .and_then(|()| Ok(out.flush()?))
+        .with_context(|| format!("cannot write {}", path.display()))
+}
+
+/// Writes `frames` of `doc` at `scale` to `out` in `format`.
+pub fn write_stream(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    format: StreamFormat,
+    options: &YuvOptions,
+    out: &mut impl Write,
+) -> Result<()> {
+    if frames.is_empty() {
+        bail!("the frame range is empty");
+    }
+    if format == StreamFormat::Y4m {
+        out.write_all(y4m_header(doc, scale, options).as_bytes())?;
+    }
+    for frame in render_frames(doc, frames, scale, Background::Document) {
+        let frame = frame?;
+        match format {
+            StreamFormat::Rgba => out.write_all(&frame.pixels)?,
+            StreamFormat::Y4m => {
+                let planes = yuv::convert(&frame, options);
+                out.write_all(b"FRAME\n")?;
+                out.write_all(&planes.y)?;
+                out.write_all(&planes.cb)?;
+                out.write_all(&planes.cr)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs `command` through the platform shell and streams the frames to
+/// its stdin. `{width}`, `{height}` and `{fps}` in the command are
+/// replaced by the output size and the frame rate as a fraction.
+pub fn pipe(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    format: StreamFormat,
+    options: &YuvOptions,
+    command: &str,
+) -> Result<()> {
+    let (width, height) = output_size(doc, scale);
+    let (numerator, denominator) = frame_rate_ratio(doc.frame_rate);
+    let command = command
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+        .replace("{fps}", &format!("{numerator}/{denominator}"));
+
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(&command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("cannot run `{command}`"))?;
+
+    let mut stdin = BufWriter::new(child.stdin.take().context("no stdin for the command")?);
+    let written = write_stream(doc, frames, scale, format, options, &mut stdin)
+        .and_then(|()| Ok(stdin.flush()?));
+    // Closing stdin tells the command the stream has ended.
+    drop(stdin);
+    let status = child.wait()?;
+    if let Err(err) = written {
+        let broken = err
+            .downcast_ref::<io::Error>()
+            .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe);
+        if broken {
+            bail!("`{command}` stopped reading frames ({status})");
+        }
+        return Err(err);
+    }
+    if !status.success() {
+        bail!("`{command}` failed ({status})");
+    }
+    Ok(())
+}
+
+fn y4m_header(doc: &Document, scale: f32, options: &YuvOptions) -> String {
+    let (width, height) = output_size(doc, scale);
+    let (numerator, denominator) = frame_rate_ratio(doc.frame_rate);
+    let chroma = match options.chroma {
+        Chroma::C420 => "420jpeg",
+        Chroma::C444 => "444",
+    };
+    let range = match options.range {
+        ColorRange::Limited => "LIMITED",
+        ColorRange::Full => "FULL",
+    };
+    format!(
+        "YUV4MPEG2 W{width} H{height} F{numerator}:{denominator} Ip A1:1 C{chroma} XCOLORRANGE={range}\n"
+    )
+}
+
+/// Frame rate as a fraction, recognizing the NTSC rates (23.976, 29.97,
+/// 59.94, ...) as multiples of 1000/1001.
+pub fn frame_rate_ratio(frame_rate: f32) -> (u32, u32) {
+    let rate = frame_rate.max(0.001) as f64;
+    let ntsc = (rate * 1.001).round();
+    if rate.fract() != 0.0 && (ntsc * 1000.0 / 1001.0 - rate).abs() < 0.005 {
+        return (ntsc as u32 * 1000, 1001);
+    }
+    let mut numerator = (rate * 1000.0).round() as u32;
+    let mut denominator = 1000;
+    while numerator.is_multiple_of(10) && denominator > 1 {
+        numerator /= 10;
+        denominator /= 10;
+    }
+    (numerator, denominator)
+}
@@ -0,0 +1,133 @@
+// Animated WebP export.
+//
+// Frames go to libwebp's animation encoder, lossless or lossy. The alpha
+// plane is always kept at full precision, so lossy files still have
+// smooth edges over any background. Unlike the other raster exporters the
+// whole range is rendered before encoding: the encoder compares frames
+// with each other to store only what changed, and the `webp` crate hands
+// it the frames all at once.
+
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use ::webp::{AnimEncoder, AnimFrame, WebPConfig};
+use anyhow::{anyhow, bail, Context, Result};
+
+use super::{frame_delays, output_size, render_frames};
+use crate::render::{Background, Frame};
+use crate::scene::Document;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WebpOptions {
+    pub lossless: bool,
+    /// 0 to 100. Image quality when lossy; when lossless, how hard the
+    /// encoder works on a smaller file.
+    pub quality: f32,
+    /// 0 (fast) to 6 (smallest file).
+    pub effort: u8,
+    /// How many times the animation plays; 0 loops forever.
+    pub loop_count: u16,
+    pub background: Background,
+}
+
+impl Default for WebpOptions {
+    fn default() -> Self {
+        Self {
+            lossless: true,
+            quality: 75.0,
+            effort: 4,
+            loop_count: 0,
+            background: Background::Document,
+        }
+    }
+}
+
+/// Writes `frames` of `doc` at `scale` as an animated WebP.
+pub fn save(
+    doc: &Document,
+    frames: Range<u32>,
+    scale: f32,
+    options: &WebpOptions,
+    path: &Path,
+) -> Result<()> {
+    if frames.is_empty() {
+        bail!("the frame range is empty");
+    }
+    let (width, height) = output_size(doc, scale);
+    // WebP stores dimensions in 14 bits.
+    if width > 16383 || height > 16383 {
+        bail!("WebP images are limited to 16383x16383 pixels, not {width}x{height}");
+    }
+
+    let mut config = WebPConfig::new().map_err(|_| anyhow!("cannot set up the WebP encoder"))?;
+    config.lossless = options.lossless as i32;
+    config.quality = options.quality.clamp(0.0, 100.0);
+    config.method = options.effort.min(6) as i32;
+    config.alpha_quality = 100;
+
+    let delays = frame_delays(doc.frame_rate, frames.len(), 1000);
+    let images: Vec<Frame> =
+        render_frames(doc, frames, scale, options.background).collect::<Result<_>>()?;
+    let mut encoder = AnimEncoder::new(width, height, &config);
+    encoder.set_bgcolor([0, 0, 0, 0]);
+    encoder.set_loop_count(options.loop_count as i32);
+    // Timestamps are when each frame starts, in milliseconds.
+    let mut timestamp = 0;
+    for (image, delay) in images.iter().zip(delays) {
+        encoder.add_frame(AnimFrame::from_rgba(
+            &image.pixels,
+            width,
+            height,
+            timestamp,
+        ));
+        timestamp += delay as i32;
+    }
+    let data = encoder
+        .try_encode()
+        .map_err(|err| anyhow!("WebP encoding failed: {err:?}"))?;
+    fs::write(path, &*data).with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{assert_half_covered, half_covered};
+    use ::webp::AnimDecoder;
+
+    fn round_trip(options: WebpOptions) {
+        let path = std::env::temp_dir().join(format!(
+            "webp-alpha-{}-{}.webp",
+            std::process::id(),
+            options.lossless
+        ));
+        let options = WebpOptions {
+            background: Background::Transparent,
+            ..options
+        };
+        save(&half_covered(), 0..2, 1.0, &options, &path).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let image = AnimDecoder::new(&data).decode().unwrap();
+        assert!(image.len() > 0);
+        for frame in &image {
+            assert_half_covered(frame.get_image());
+        }
+    }
+
+    #[test]
+    fn lossless_keeps_partial_alpha() {
+        round_trip(WebpOptions::default());
+    }
+
+    #[test]
+    fn lossy_keeps_partial_alpha() {
+        round_trip(WebpOptions {
+            lossless: false,
+            quality: 100.0,
+            ..WebpOptions::default()
+        });
+    }
+}
@@ -0,0 +1,287 @@
+// RGB to Y'CbCr conversion for video output.
+//
+// Rendered pixels are gamma-encoded sRGB, which BT.601 and BT.709 both
+// take as R'G'B' directly, so conversion is the matrix of the chosen
+// standard applied to normalized values, followed by quantization to full
+// (0-255) or limited "studio" range (16-235 luma, 16-240 chroma).
+// Transparent pixels are composited over black first since the video
+// formats have no alpha. Chroma subsampled to 4:2:0 averages each 2x2
+// block, i.e. chroma samples sit at the block centre as in JPEG and the
+// Y4M `420jpeg` layout.
+
+use crate::render::Frame;
+
+/// Which standard's luma coefficients to use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Matrix {
+    /// Standard definition.
+    Bt601,
+    /// High definition; what most players assume for HD and larger.
+    #[default]
+    Bt709,
+}
+
+impl Matrix {
+    /// Red and blue luma weights (Kr, Kb).
+    fn weights(self) -> (f32, f32) {
+        match self {
+            Matrix::Bt601 => (0.299, 0.114),
+            Matrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorRange {
+    /// 16-235 luma and 16-240 chroma, the broadcast convention.
+    #[default]
+    Limited,
+    /// All of 0-255, as in JPEG.
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chroma {
+    /// Chroma at half resolution in both directions.
+    #[default]
+    C420,
+    /// Chroma at full resolution.
+    C444,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct YuvOptions {
+    pub matrix: Matrix,
+    pub range: ColorRange,
+    pub chroma: Chroma,
+}
+
+/// A frame as separate Y', Cb and Cr planes.
+pub struct Planes {
+    pub y: Vec<u8>,
+    pub cb: Vec<u8>,
+    pub cr: Vec<u8>,
+}
+
+/// Converts an RGBA frame to planes in the layout of `options.chroma`.
+/// Chroma planes of 4:2:0 frames with odd sizes round up.
+pub fn convert(frame: &Frame, options: &YuvOptions) -> Planes {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    // Y', Pb and Pr of every pixel, before quantization.
+    let values: Vec<[f32; 3]> = frame
+        .pixels
+        .chunks_exact(4)
+        .map(|p| {
+            let alpha = p[3] as f32 / 255.0;
+            analog(
+                [p[0], p[1], p[2]].map(|c| c as f32 / 255.0 * alpha),
+                options.matrix,
+            )
+        })
+        .collect();
+    let y = values.iter().map(|v| luma(v[0], options.range)).collect();
+
+    let (cb, cr) = match options.chroma {
+        Chroma::C444 => values
+            .iter()
+            .map(|v| (chroma(v[1], options.range), chroma(v[2], options.range)))
+            .unzip(),
+        Chroma::C420 => {
+            let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+            let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+            let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+            for cy in 0..chroma_height {
+                for cx in 0..chroma_width {
+                    let (mut pb, mut pr, mut count) = (0.0, 0.0, 0.0);
+                    for y in cy * 2..(cy * 2 + 2).min(height) {
+                        for x in cx * 2..(cx * 2 + 2).min(width) {
+                            let v = values[y * width + x];
+                            pb += v[1];
+                            pr += v[2];
+                            count += 1.0;
+                        }
+                    }
+                    cb.push(chroma(pb / count, options.range));
+                    cr.push(chroma(pr / count, options.range));
+                }
+            }
+            (cb, cr)
+        }
+    };
+    Planes { y, cb, cr }
+}
+
+/// Normalized R'G'B' to Y' in 0..1 and Pb, Pr in -0.5..0.5.
+fn analog([r, g, b]: [f32; 3], matrix: Matrix) -> [f32; 3] {
+    let (kr, kb) = matrix.weights();
+    let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+    [
+        y,
+        (b - y) / (2.0 * (1.0 - kb)),
+        (r - y) / (2.0 * (1.0 - kr)),
+    ]
+}
+
+fn luma(y: f32, range: ColorRange) -> u8 {
+    let value = match range {
+        ColorRange::Limited => 16.0 + 219.0 * y,
+        ColorRange::Full => 255.0 * y,
+    };
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+fn chroma(c: f32, range: ColorRange) -> u8 {
+    let value = match range {
+        ColorRange::Limited => 128.0 + 224.0 * c,
+        ColorRange::Full => 128.0 + 255.0 * c,
+    };
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn frame(width: u32, height: u32, pixels: &[[u8; 4]]) -> Frame {
+        Frame {
+            width,
+            height,
+            pixels: pixels.concat(),
+        }
+    }
+
+    /// Y'CbCr of a single pixel.
+    fn pixel(rgba: [u8; 4], matrix: Matrix, range: ColorRange) -> [u8; 3] {
+        let options = YuvOptions {
+            matrix,
+            range,
+            chroma: Chroma::C444,
+        };
+        let planes = convert(&frame(1, 1, &[rgba]), &options);
+        [planes.y[0], planes.cb[0], planes.cr[0]]
+    }
+
+    /// 75% color bars: white, yellow, cyan, green, magenta, red, blue.
+    fn bars() -> [[u8; 4]; 7] {
+        let on = 191;
+        [
+            [on, on, on, 255],
+            [on, on, 0, 255],
+            [0, on, on, 255],
+            [0, on, 0, 255],
+            [on, 0, on, 255],
+            [on, 0, 0, 255],
+            [0, 0, on, 255],
+        ]
+    }
+
+    /// Within one code value of `expected`: the published bar tables assume
+    /// exactly 75%, while 8-bit bars are 191/255.
+    fn assert_bars(matrix: Matrix, expected: [[u8; 3]; 7]) {
+        for (rgba, expected) in bars().into_iter().zip(expected) {
+            let actual = pixel(rgba, matrix, ColorRange::Limited);
+            let close = actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 1);
+            assert!(close, "{rgba:?}: {actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn black_and_white_span_each_range() {
+        for matrix in [Matrix::Bt601, Matrix::Bt709] {
+            assert_eq!(pixel(BLACK, matrix, ColorRange::Limited), [16, 128, 128]);
+            assert_eq!(pixel(WHITE, matrix, ColorRange::Limited), [235, 128, 128]);
+            assert_eq!(pixel(BLACK, matrix, ColorRange::Full), [0, 128, 128]);
+            assert_eq!(pixel(WHITE, matrix, ColorRange::Full), [255, 128, 128]);
+        }
+    }
+
+    #[test]
+    fn bt601_limited_color_bars() {
+        assert_bars(
+            Matrix::Bt601,
+            [
+                [180, 128, 128],
+                [162, 44, 142],
+                [131, 156, 44],
+                [112, 72, 58],
+                [84, 184, 198],
+                [65, 100, 212],
+                [35, 212, 114],
+            ],
+        );
+    }
+
+    #[test]
+    fn bt709_limited_color_bars() {
+        assert_bars(
+            Matrix::Bt709,
+            [
+                [180, 128, 128],
+                [168, 44, 136],
+                [145, 147, 44],
+                [133, 63, 52],
+                [63, 193, 204],
+                [51, 109, 212],
+                [28, 212, 120],
+            ],
+        );
+    }
+
+    #[test]
+    fn full_range_primaries() {
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        // The JPEG (JFIF) values.
+        assert_eq!(pixel(red, Matrix::Bt601, ColorRange::Full), [76, 85, 255]);
+        assert_eq!(pixel(green, Matrix::Bt601, ColorRange::Full), [150, 44, 21]);
+        assert_eq!(pixel(BLUE, Matrix::Bt601, ColorRange::Full), [29, 255, 107]);
+        assert_eq!(pixel(red, Matrix::Bt709, ColorRange::Full), [54, 99, 255]);
+        assert_eq!(pixel(green, Matrix::Bt709, ColorRange::Full), [182, 30, 12]);
+        assert_eq!(pixel(BLUE, Matrix::Bt709, ColorRange::Full), [18, 255, 116]);
+    }
+
+    #[test]
+    fn transparency_is_composited_over_black() {
+        let clear = [255, 255, 255, 0];
+        assert_eq!(
+            pixel(clear, Matrix::Bt709, ColorRange::Limited),
+            [16, 128, 128]
+        );
+        let half = pixel([255, 255, 255, 128], Matrix::Bt709, ColorRange::Full);
+        assert_eq!(half, [128, 128, 128]);
+    }
+
+    #[test]
+    fn chroma_420_averages_partial_blocks_at_odd_edges() {
+        // Blue in one pixel of each 2x2 block of a 3x3 frame, so the blocks
+        // cut off by the right and bottom edges average fewer pixels.
+        let pixels = [
+            BLUE, BLACK, BLUE, //
+            BLACK, BLACK, BLACK, //
+            BLUE, BLACK, BLUE, //
+        ];
+        let options = YuvOptions {
+            matrix: Matrix::Bt601,
+            range: ColorRange::Full,
+            chroma: Chroma::C420,
+        };
+        let planes = convert(&frame(3, 3, &pixels), &options);
+        assert_eq!(planes.y.len(), 9);
+        // Blue has Pb 0.5 and Pr -0.0813: a block a quarter blue has Cb
+        // 128 + 255 / 8 and Cr 128 - 20.7 / 4, half blue 128 + 255 / 4 and
+        // 128 - 20.7 / 2.
+        assert_eq!(planes.cb, [160, 192, 192, 255]);
+        assert_eq!(planes.cr, [123, 118, 118, 107]);
+
+        let options = YuvOptions {
+            chroma: Chroma::C444,
+            ..options
+        };
+        let planes = convert(&frame(3, 3, &pixels), &options);
+        assert_eq!((planes.cb.len(), planes.cr.len()), (9, 9));
+    }
+}
@@ -0,0 +1,278 @@
+// Undo/redo history.
+//
+// Edits are recorded as snapshots of the state taken before the edit: undo
+// swaps the current state with the top snapshot and moves the swapped-out
+// state onto the redo stack. A group collects everything between `begin`
+// and `commit` into one step, which is how a pointer drag that touches the
+// document on every move still undoes in one go. The history knows nothing
+// about the UI; the editor wraps every mutation in a group.
+
+/// Steps kept when no depth is configured.
+pub const DEFAULT_DEPTH: usize = 100;
+
+struct Step<T> {
+    label: String,
+    state: T,
+}
+
+/// An open group: the state before its first edit.
+struct Pending<T> {
+    label: String,
+    before: T,
+    /// Nested `begin` calls; the group closes when this drops to zero.
+    depth: usize,
+}
+
+pub struct History<T> {
+    undo: Vec<Step<T>>,
+    redo: Vec<Step<T>>,
+    pending: Option<Pending<T>>,
+    depth: usize,
+}
+
+impl<T: Clone + PartialEq> Default for History<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH)
+    }
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    /// A history keeping at most `depth` undo steps.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            pending: None,
+            depth,
+        }
+    }
+
+    /// Opens a group named `label` with `state` as its starting point.
+    /// Nested calls join the outermost group.
+    pub fn begin(&mut self, label: &str, state: &T) {
+        match &mut self.pending {
+            Some(pending) => pending.depth += 1,
+            None => {
+                self.pending = Some(Pending {
+                    label: label.to_owned(),
+                    before: state.clone(),
+                    depth: 1,
+                })
+            }
+        }
+    }
+
+    /// Closes the innermost group. When the outermost group closes and
+    /// `state` differs from where it started, a step is recorded and the
+    /// redo stack is cleared. Returns whether a step was recorded.
+    pub fn commit(&mut self, state: &T) -> bool {
+        let Some(pending) = &mut self.pending else {
+            return false;
+        };
+        pending.depth -= 1;
+        if pending.depth > 0 {
+            return false;
+        }
+        let Some(pending) = self.pending.take() else {
+            return false;
+        };
+        if pending.before == *state {
+            return false;
+        }
+        self.undo.push(Step {
+            label: pending.label,
+            state: pending.before,
+        });
+        self.redo.clear();
+        self.trim();
+        true
+    }
+
+    /// Abandons the open group and restores `state` to where it started.
+    pub fn cancel(&mut self, state: &mut T) {
+        if let Some(pending) = self.pending.take() {
+            *state = pending.before;
+        }
+    }
+
+    pub fn in_group(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Runs `edit` on `state` as a single step.
+    pub fn edit<R>(&mut self, label: &str, state: &mut T, edit: impl FnOnce(&mut T) -> R) -> R {
+        self.begin(label, state);
+        let result = edit(state);
+        self.commit(state);
+        result
+    }
+
+    /// Reverts the last step. An open group is closed first so an undo in
+    /// the middle of a drag undoes the drag. Returns the step's label.
+    pub fn undo(&mut self, state: &mut T) -> Option<String> {
+        self.close_all(state);
+        let step = self.undo.pop()?;
+        let after = std::mem::replace(state, step.state);
+        self.redo.push(Step {
+            label: step.label.clone(),
+            state: after,
+        });
+        Some(step.label)
+    }
+
+    /// Re-applies the last undone step. Returns the step's label.
+    pub fn redo(&mut self, state: &mut T) -> Option<String> {
+        self.close_all(state);
+        let step = self.redo.pop()?;
+        let before = std::mem::replace(state, step.state);
+        self.undo.push(Step {
+            label: step.label.clone(),
+            state: before,
+        });
+        Some(step.label)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|step| step.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|step| step.label.as_str())
+    }
+
+    /// Forgets every step, e.g. after a different document is opened.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending = None;
+    }
+
+    fn close_all(&mut self, state: &T) {
+        if let Some(pending) = &mut self.pending {
+            pending.depth = 1;
+            self.commit(state);
+        }
+    }
+
+    fn trim(&mut self) {
+        if self.undo.len() > self.depth {
+            let excess = self.undo.len() - self.depth;
+            self.undo.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(history: &mut History<i32>, state: &mut i32, value: i32) {
+        history.edit(&format!("Set {value}"), state, |state| *state = value);
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_steps() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        set(&mut history, &mut state, 1);
+        set(&mut history, &mut state, 2);
+
+        assert_eq!(history.undo(&mut state).as_deref(), Some("Set 2"));
+        assert_eq!(state, 1);
+        assert_eq!(history.undo(&mut state).as_deref(), Some("Set 1"));
+        assert_eq!(state, 0);
+        assert_eq!(history.undo(&mut state), None);
+        assert!(!history.can_undo());
+
+        assert_eq!(history.redo(&mut state).as_deref(), Some("Set 1"));
+        assert_eq!(history.redo(&mut state).as_deref(), Some("Set 2"));
+        assert_eq!(state, 2);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        set(&mut history, &mut state, 1);
+        history.undo(&mut state);
+        assert!(history.can_redo());
+        set(&mut history, &mut state, 5);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(&mut state), None);
+        assert_eq!(state, 5);
+    }
+
+    #[test]
+    fn a_group_is_one_step() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        history.begin("Drag", &state);
+        for value in 1..=5 {
+            // Nested edits join the open group.
+            set(&mut history, &mut state, value);
+        }
+        assert!(history.commit(&state));
+        assert_eq!(history.undo_label(), Some("Drag"));
+        history.undo(&mut state);
+        assert_eq!(state, 0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn a_group_without_changes_is_dropped() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        history.begin("Click", &state);
+        state = 3;
+        state -= 3;
+        assert!(!history.commit(&state));
+        assert!(!history.can_undo());
+        assert!(!history.in_group());
+    }
+
+    #[test]
+    fn undo_inside_a_group_undoes_the_group() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        history.begin("Drag", &state);
+        state = 7;
+        history.undo(&mut state);
+        assert_eq!(state, 0);
+        assert!(!history.in_group());
+    }
+
+    #[test]
+    fn the_oldest_steps_are_evicted_past_the_depth() {
+        let mut history = History::new(3);
+        let mut state = 0;
+        for value in 1..=5 {
+            set(&mut history, &mut state, value);
+        }
+        while history.undo(&mut state).is_some() {}
+        assert_eq!(state, 2);
+    }
+
+    #[test]
+    fn cancel_restores_the_state_and_records_nothing() {
+        let mut history = History::new(10);
+        let mut state = 0;
+        set(&mut history, &mut state, 1);
+        history.begin("Drag", &state);
+        history.begin("Nested", &state);
+        state = 7;
+        history.cancel(&mut state);
+        assert_eq!(state, 1);
+        assert!(!history.in_group());
+        assert!(!history.can_redo());
+        assert_eq!(history.undo_label(), Some("Set 1"));
+    }
+}
@@ -0,0 +1,576 @@
+// Properties inspector model.
+//
+// `fields` lists the editable properties of the current selection at the
+// playhead and `apply` writes an edit back to every selected node. The UI
+// only renders the fields and forwards edits, so everything here works on
+// plain document data.
+
+use crate::animation::{self, Property, PropertyValues, Value};
+use crate::easing::Easing;
+use crate::scene::{BlendMode, Color, Document, Node, NodeId, Point};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldId {
+    PositionX,
+    PositionY,
+    Rotation,
+    ScaleX,
+    ScaleY,
+    Opacity,
+    BlendMode,
+    FillColor,
+    StrokeColor,
+    StrokeWidth,
+    /// Easing of one track's keyframe at the playhead, which shapes the
+    /// segment that starts there.
+    Easing,
+}
+
+impl FieldId {
+    pub const ALL: [FieldId; 11] = [
+        FieldId::PositionX,
+        FieldId::PositionY,
+        FieldId::Rotation,
+        FieldId::ScaleX,
+        FieldId::ScaleY,
+        FieldId::Opacity,
+        FieldId::BlendMode,
+        FieldId::FillColor,
+        FieldId::StrokeColor,
+        FieldId::StrokeWidth,
+        FieldId::Easing,
+    ];
+
+    /// Stable name the UI uses to refer to the field.
+    pub fn key(self) -> &'static str {
+        match self {
+            FieldId::PositionX => "position.x",
+            FieldId::PositionY => "position.y",
+            FieldId::Rotation => "rotation",
+            FieldId::ScaleX => "scale.x",
+            FieldId::ScaleY => "scale.y",
+            FieldId::Opacity => "opacity",
+            FieldId::BlendMode => "blend-mode",
+            FieldId::FillColor => "fill",
+            FieldId::StrokeColor => "stroke",
+            FieldId::StrokeWidth => "stroke-width",
+            FieldId::Easing => "easing",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<FieldId> {
+        FieldId::ALL.into_iter().find(|id| id.key() == key)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FieldId::PositionX => "X",
+            FieldId::PositionY => "Y",
+            FieldId::Rotation => "Rotation",
+            FieldId::ScaleX => "Scale X",
+            FieldId::ScaleY => "Scale Y",
+            FieldId::Opacity => "Opacity",
+            FieldId::BlendMode => "Blend",
+            FieldId::FillColor => "Fill",
+            FieldId::StrokeColor => "Stroke",
+            FieldId::StrokeWidth => "Stroke width",
+            FieldId::Easing => "Easing",
+        }
+    }
+
+    /// The animation track behind the field, if it can be keyframed.
+    pub fn property(self) -> Option<Property> {
+        match self {
+            FieldId::PositionX | FieldId::PositionY => Some(Property::Position),
+            FieldId::Rotation => Some(Property::Rotation),
+            FieldId::ScaleX | FieldId::ScaleY => Some(Property::Scale),
+            FieldId::Opacity => Some(Property::Opacity),
+            FieldId::FillColor => Some(Property::FillColor),
+            FieldId::StrokeWidth => Some(Property::StrokeWidth),
+            FieldId::BlendMode | FieldId::StrokeColor | FieldId::Easing => None,
+        }
+    }
+
+    pub fn kind(self) -> FieldKind {
+        let number = |min, max, step| FieldKind::Number { min, max, step };
+        match self {
+            FieldId::PositionX | FieldId::PositionY => number(-100_000.0, 100_000.0, 1.0),
+            FieldId::Rotation => number(-36_000.0, 36_000.0, 1.0),
+            FieldId::ScaleX | FieldId::ScaleY => number(-100.0, 100.0, 0.01),
+            FieldId::Opacity => number(0.0, 100.0, 1.0),
+            FieldId::StrokeWidth => number(0.0, 1000.0, 0.5),
+            FieldId::FillColor | FieldId::StrokeColor => FieldKind::Color,
+            FieldId::BlendMode => {
+                FieldKind::Choice(BlendMode::ALL.iter().map(|m| m.label().into()).collect())
+            }
+            FieldId::Easing => FieldKind::Easing,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldKind {
+    Number {
+        min: f32,
+        max: f32,
+        step: f32,
+    },
+    Color,
+    Choice(Vec<String>),
+    /// A preset list plus free text parsed with `Easing::from_str`.
+    Easing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldValue {
+    Number(f32),
+    Color(Color),
+    /// Index into the field's choices.
+    Choice(usize),
+    Easing(Easing),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub id: FieldId,
+    pub kind: FieldKind,
+    /// Value of the first selected node that has the property.
+    pub value: FieldValue,
+    /// The selected nodes disagree on the value.
+    pub mixed: bool,
+    /// Some selected node animates the property.
+    pub animated: bool,
+    /// Some selected node has a keyframe for the property at the playhead.
+    pub keyed: bool,
+}
+
+/// Fields shown for `selection` at `frame`. A field is listed when at least
+/// one selected node has the property. The easing field shows the keyframe
+/// of `track`, or of the first track keyed at `frame` when that is `None`.
+pub fn fields(
+    doc: &Document,
+    selection: &[NodeId],
+    frame: f32,
+    track: Option<Property>,
+) -> Vec<Field> {
+    let sampled: Vec<(&Node, PropertyValues)> = selection
+        .iter()
+        .filter_map(|id| doc.get(*id))
+        .map(|node| (node, animation::sample(node, frame)))
+        .collect();
+
+    FieldId::ALL
+        .into_iter()
+        .filter_map(|id| {
+            let mut values = sampled
+                .iter()
+                .filter_map(|(node, values)| read(id, node, values, frame, track));
+            let value = values.next()?;
+            let mixed = values.any(|other| other != value);
+            let has_track = |keyed: bool| {
+                id.property().is_some_and(|property| {
+                    sampled
+                        .iter()
+                        .any(|(node, _)| match node.animation.track(property) {
+                            Some(track) if keyed => track.keyframe_at(frame).is_some(),
+                            Some(track) => !track.keyframes.is_empty(),
+                            None => false,
+                        })
+                })
+            };
+            Some(Field {
+                id,
+                kind: id.kind(),
+                value,
+                mixed,
+                animated: has_track(false),
+                keyed: has_track(true),
+            })
+        })
+        .collect()
+}
+
+fn read(
+    id: FieldId,
+    node: &Node,
+    values: &PropertyValues,
+    frame: f32,
+    track: Option<Property>,
+) -> Option<FieldValue> {
+    let number = |v: f32| Some(FieldValue::Number(v));
+    match id {
+        FieldId::PositionX => number(values.position.x),
+        FieldId::PositionY => number(values.position.y),
+        FieldId::Rotation => number(values.rotation),
+        FieldId::ScaleX => number(values.scale.x),
+        FieldId::ScaleY => number(values.scale.y),
+        FieldId::Opacity => number(values.opacity * 100.0),
+        FieldId::BlendMode => BlendMode::ALL
+            .iter()
+            .position(|mode| *mode == node.blend_mode)
+            .map(FieldValue::Choice),
+        FieldId::FillColor => values.fill_color.map(FieldValue::Color),
+        FieldId::StrokeColor => node
+            .shape()?
+            .stroke
+            .as_ref()
+            .map(|s| FieldValue::Color(s.color)),
+        FieldId::StrokeWidth => values.stroke_width.and_then(number),
+        FieldId::Easing => {
+            let property = easing_track(node, frame, track)?;
+            let key = node.animation.track(property)?.keyframe_at(frame)?;
+            Some(FieldValue::Easing(key.easing))
+        }
+    }
+}
+
+/// The track whose keyframe at `frame` the easing field edits on `node`.
+fn easing_track(node: &Node, frame: f32, track: Option<Property>) -> Option<Property> {
+    track.or_else(|| {
+        node.animation
+            .tracks
+            .iter()
+            .find(|t| t.keyframe_at(frame).is_some())
+            .map(|t| t.property)
+    })
+}
+
+/// Sets field `id` to `value` on every selected node that has it. Animated
+/// properties get a keyframe at `frame`, as does every keyframeable property
+/// when `auto_key` is on; otherwise the static value changes. An easing
+/// only changes the keyframe of the track `fields` shows for `track`.
+pub fn apply(
+    doc: &mut Document,
+    selection: &[NodeId],
+    frame: f32,
+    track: Option<Property>,
+    id: FieldId,
+    value: FieldValue,
+    auto_key: bool,
+) {
+    for &node_id in selection {
+        let Some(node) = doc.get_mut(node_id) else {
+            continue;
+        };
+        match (id, value) {
+            (FieldId::BlendMode, FieldValue::Choice(index)) => {
+                if let Some(mode) = BlendMode::ALL.get(index) {
+                    node.blend_mode = *mode;
+                }
+            }
+            (FieldId::StrokeColor, FieldValue::Color(color)) => {
+                if let Some(stroke) = node.shape_mut().and_then(|s| s.stroke.as_mut()) {
+                    stroke.color = color;
+                }
+            }
+            (FieldId::Easing, FieldValue::Easing(easing)) => {
+                if let Some(property) = easing_track(node, frame, track) {
+                    node.animation.set_easing(property, frame, easing);
+                }
+            }
+            _ => {
+                let values = animation::sample(node, frame);
+                if let (Some(property), Some(new)) = (id.property(), updated(id, &values, value)) {
+                    animation::write(node, property, new, frame, auto_key);
+                }
+            }
+        }
+    }
+}
+
+/// The property value after changing the part of it that `id` shows.
+fn updated(id: FieldId, values: &PropertyValues, value: FieldValue) -> Option<Value> {
+    let FieldKind::Number { min, max, .. } = id.kind() else {
+        return match (id, value) {
+            (FieldId::FillColor, FieldValue::Color(color)) if values.fill_color.is_some() => {
+                Some(Value::Color(color))
+            }
+            _ => None,
+        };
+    };
+    let FieldValue::Number(v) = value else {
+        return None;
+    };
+    let v = v.clamp(min, max);
+    let (position, scale) = (values.position, values.scale);
+    match id {
+        FieldId::PositionX => Some(Value::Point(Point::new(v, position.y))),
+        FieldId::PositionY => Some(Value::Point(Point::new(position.x, v))),
+        FieldId::Rotation => Some(Value::Scalar(v)),
+        FieldId::ScaleX => Some(Value::Point(Point::new(v, scale.y))),
+        FieldId::ScaleY => Some(Value::Point(Point::new(scale.x, v))),
+        FieldId::Opacity => Some(Value::Scalar(v / 100.0)),
+        FieldId::StrokeWidth => values.stroke_width.map(|_| Value::Scalar(v)),
+        _ => None,
+    }
+}
+
+/// Adds a keyframe holding the current value at `frame` to every selected
+/// node, or removes the keyframes there if any selected node has one.
+/// Removing a track's last keyframe keeps its value as the static value.
+pub fn toggle_keyframe(doc: &mut Document, selection: &[NodeId], frame: f32, id: FieldId) {
+    let Some(property) = id.property() else {
+        return;
+    };
+    let keyed = selection.iter().filter_map(|id| doc.get(*id)).any(|node| {
+        node.animation
+            .track(property)
+            .is_some_and(|track| track.keyframe_at(frame).is_some())
+    });
+    for &node_id in selection {
+        let Some(node) = doc.get_mut(node_id) else {
+            continue;
+        };
+        let values = animation::sample(node, frame);
+        let Some(value) = values.get(property) else {
+            continue;
+        };
+        if !keyed {
+            node.animation.set_keyframe(property, frame, value);
+            continue;
+        }
+        let tracks = &mut node.animation.tracks;
+        let Some(track) = tracks.iter_mut().find(|t| t.property == property) else {
+            continue;
+        };
+        if track.remove(frame).is_some() && track.keyframes.is_empty() {
+            animation::write(node, property, value, frame, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::easing::Curve;
+    use crate::scene::{Geometry, Shape, Stroke};
+
+    /// A rectangle with position and rotation keyed at frames 0 and 10.
+    fn keyed_rect(doc: &mut Document) -> NodeId {
+        let layer = doc.add_layer("Layer");
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(10.0, 10.0),
+            corner_radius: 0.0,
+        });
+        let id = doc.add_shape(layer, "Rect", shape).unwrap();
+        let animation = &mut doc.get_mut(id).unwrap().animation;
+        for frame in [0.0, 10.0] {
+            animation.set_keyframe(Property::Position, frame, Value::Point(Point::ZERO));
+            animation.set_keyframe(Property::Rotation, frame, Value::Scalar(frame));
+        }
+        id
+    }
+
+    fn easing(doc: &Document, id: NodeId, property: Property) -> Easing {
+        let track = doc.get(id).unwrap().animation.track(property).unwrap();
+        track.keyframe_at(0.0).unwrap().easing
+    }
+
+    fn easing_field(doc: &Document, id: NodeId, track: Option<Property>) -> FieldValue {
+        let fields = fields(doc, &[id], 0.0, track);
+        fields
+            .into_iter()
+            .find(|f| f.id == FieldId::Easing)
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn easing_edits_only_the_chosen_track() {
+        let mut doc = Document::new(100, 100);
+        let id = keyed_rect(&mut doc);
+        let custom = Easing::CubicBezier {
+            x1: 0.1,
+            y1: 0.7,
+            x2: 0.2,
+            y2: 1.0,
+        };
+        let value = FieldValue::Easing(custom);
+        apply(
+            &mut doc,
+            &[id],
+            0.0,
+            Some(Property::Rotation),
+            FieldId::Easing,
+            value,
+            false,
+        );
+        assert_eq!(easing(&doc, id, Property::Rotation), custom);
+        assert_eq!(easing(&doc, id, Property::Position), Easing::Linear);
+
+        // Without a chosen track the first track keyed at the playhead is used.
+        let value = FieldValue::Easing(Easing::In(Curve::Quad));
+        apply(&mut doc, &[id], 0.0, None, FieldId::Easing, value, false);
+        assert_eq!(
+            easing(&doc, id, Property::Position),
+            Easing::In(Curve::Quad)
+        );
+        assert_eq!(easing(&doc, id, Property::Rotation), custom);
+    }
+
+    #[test]
+    fn easing_field_shows_custom_curves() {
+        let mut doc = Document::new(100, 100);
+        let id = keyed_rect(&mut doc);
+        let custom: Easing = "cubic-bezier(0.1, 0.7, 0.2, 1)".parse().unwrap();
+        assert!(!Easing::presets().contains(&custom));
+        let node = doc.get_mut(id).unwrap();
+        node.animation.set_easing(Property::Rotation, 0.0, custom);
+
+        let shown = easing_field(&doc, id, Some(Property::Rotation));
+        assert_eq!(shown, FieldValue::Easing(custom));
+        let shown = easing_field(&doc, id, None);
+        assert_eq!(shown, FieldValue::Easing(Easing::Linear));
+    }
+
+    fn rect(doc: &mut Document, layer: NodeId) -> NodeId {
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(10.0, 10.0),
+            corner_radius: 0.0,
+        });
+        doc.add_shape(layer, "Rect", shape).unwrap()
+    }
+
+    fn field(fields: &[Field], id: FieldId) -> Option<&Field> {
+        fields.iter().find(|f| f.id == id)
+    }
+
+    #[test]
+    fn fields_report_disagreement_across_the_selection() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let (a, b) = (rect(&mut doc, layer), rect(&mut doc, layer));
+        doc.get_mut(a).unwrap().opacity = 0.5;
+        let stroke = Stroke::new(Color::BLACK, 3.0);
+        doc.get_mut(b).unwrap().shape_mut().unwrap().stroke = Some(stroke);
+
+        let both = fields(&doc, &[a, b], 0.0, None);
+        let opacity = field(&both, FieldId::Opacity).unwrap();
+        assert_eq!(opacity.value, FieldValue::Number(50.0));
+        assert!(opacity.mixed);
+        let rotation = field(&both, FieldId::Rotation).unwrap();
+        assert!(!rotation.mixed);
+        // Only `b` has a stroke, so the field shows its value unmixed.
+        let width = field(&both, FieldId::StrokeWidth).unwrap();
+        assert_eq!(width.value, FieldValue::Number(3.0));
+        assert!(!width.mixed);
+        // Nothing is keyed, so there is no easing to show.
+        assert!(field(&both, FieldId::Easing).is_none());
+
+        // A layer has no fill or stroke.
+        let of_layer = fields(&doc, &[layer], 0.0, None);
+        assert!(field(&of_layer, FieldId::FillColor).is_none());
+        assert!(field(&of_layer, FieldId::StrokeColor).is_none());
+        assert!(field(&of_layer, FieldId::PositionX).is_some());
+    }
+
+    #[test]
+    fn fields_flag_animated_and_keyed_properties() {
+        let mut doc = Document::new(100, 100);
+        let animated = keyed_rect(&mut doc);
+        let layer = doc.layers[0].id;
+        let still = rect(&mut doc, layer);
+
+        let between = fields(&doc, &[still, animated], 5.0, None);
+        let x = field(&between, FieldId::PositionX).unwrap();
+        assert!(x.animated && !x.keyed);
+        assert!(!field(&between, FieldId::Opacity).unwrap().animated);
+
+        let on_key = fields(&doc, &[still, animated], 10.0, None);
+        let rotation = field(&on_key, FieldId::Rotation).unwrap();
+        assert!(rotation.animated && rotation.keyed);
+        // The first selected node without a key decides the value.
+        assert_eq!(rotation.value, FieldValue::Number(0.0));
+        assert!(rotation.mixed);
+    }
+
+    #[test]
+    fn apply_edits_static_values_and_keys_animated_ones() {
+        let mut doc = Document::new(100, 100);
+        let animated = keyed_rect(&mut doc);
+        let layer = doc.layers[0].id;
+        let still = rect(&mut doc, layer);
+        let selection = [animated, still];
+        let edit = |doc: &mut Document, id, value, auto_key| {
+            apply(doc, &selection, 5.0, None, id, value, auto_key)
+        };
+
+        edit(
+            &mut doc,
+            FieldId::PositionY,
+            FieldValue::Number(40.0),
+            false,
+        );
+        let node = doc.get(still).unwrap();
+        assert_eq!(node.transform.position, Point::new(0.0, 40.0));
+        assert!(!node.animation.is_animated(Property::Position));
+        let track = doc
+            .get(animated)
+            .unwrap()
+            .animation
+            .track(Property::Position);
+        let key = track.unwrap().keyframe_at(5.0).unwrap();
+        assert_eq!(key.value, Value::Point(Point::new(0.0, 40.0)));
+
+        // Opacity is shown in percent and clamped to its range.
+        edit(&mut doc, FieldId::Opacity, FieldValue::Number(150.0), true);
+        let node = doc.get(still).unwrap();
+        assert_eq!(node.opacity, 1.0);
+        let key = node
+            .animation
+            .track(Property::Opacity)
+            .unwrap()
+            .keyframe_at(5.0);
+        assert_eq!(key.unwrap().value, Value::Scalar(1.0));
+
+        edit(&mut doc, FieldId::BlendMode, FieldValue::Choice(1), false);
+        assert!(selection
+            .iter()
+            .all(|id| doc.get(*id).unwrap().blend_mode == BlendMode::ALL[1]));
+
+        // Values of the wrong kind are ignored.
+        let before = doc.clone();
+        edit(&mut doc, FieldId::Rotation, FieldValue::Choice(2), false);
+        edit(
+            &mut doc,
+            FieldId::StrokeColor,
+            FieldValue::Color(Color::BLACK),
+            false,
+        );
+        assert!(doc == before);
+    }
+
+    #[test]
+    fn toggle_keyframe_adds_to_all_or_removes_from_all() {
+        let mut doc = Document::new(100, 100);
+        let animated = keyed_rect(&mut doc);
+        let layer = doc.layers[0].id;
+        let still = rect(&mut doc, layer);
+        doc.get_mut(still).unwrap().transform.rotation = 30.0;
+        let selection = [animated, still];
+        let rotation_key = |doc: &Document, id: NodeId, frame: f32| {
+            let track = doc.get(id).unwrap().animation.track(Property::Rotation);
+            track
+                .and_then(|t| t.keyframe_at(frame))
+                .map(|k| k.value.clone())
+        };
+
+        // Nobody is keyed at 5: both get a key holding their current value.
+        toggle_keyframe(&mut doc, &selection, 5.0, FieldId::Rotation);
+        assert_eq!(rotation_key(&doc, animated, 5.0), Some(Value::Scalar(5.0)));
+        assert_eq!(rotation_key(&doc, still, 5.0), Some(Value::Scalar(30.0)));
+
+        // Keyed now: the keys go again, and `still` loses its only key but
+        // keeps the value.
+        toggle_keyframe(&mut doc, &selection, 5.0, FieldId::Rotation);
+        assert_eq!(rotation_key(&doc, animated, 5.0), None);
+        let node = doc.get(still).unwrap();
+        assert!(!node.animation.is_animated(Property::Rotation));
+        assert_eq!(node.transform.rotation, 30.0);
+
+        // Fields without a track do nothing.
+        let before = doc.clone();
+        toggle_keyframe(&mut doc, &selection, 5.0, FieldId::BlendMode);
+        assert!(doc == before);
+    }
+}
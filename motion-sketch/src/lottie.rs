@@ -0,0 +1,767 @@
+// Lottie (Bodymovin JSON) export.
+//
+// Writes the document as a Lottie animation for mobile and web players.
+// Every top-level layer becomes a shape layer whose children are nested
+// shape groups, so the node tree survives the trip. A cel layer becomes a
+// null layer carrying its transform, parenting one shape layer per drawing
+// that is only alive during that drawing's exposure.
+//
+// Keyframes keep their timing and easing: linear, hold and cubic-bezier
+// easings map to Lottie's bezier tangents directly and the Penner curves
+// use their usual cubic-bezier approximations. Elastic and bounce easings
+// have no bezier form and are baked into one keyframe per frame. Anything
+// else Lottie cannot express is dropped and reported as a warning.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value as Json};
+
+use crate::animation::{Keyframe, Property, Track, Value};
+use crate::cel;
+use crate::easing::{Curve, Easing};
+use crate::scene::{
+    BlendMode, Color, Document, Fill, Geometry, GradientStop, LineCap, LineJoin, Node, NodeKind,
+    PathPoint, Point, Shape, Stroke,
+};
+use crate::stroke;
+
+/// Bodymovin schema version the output follows.
+const VERSION: &str = "5.7.4";
+
+/// Layer type codes.
+const SOLID_LAYER: u32 = 1;
+const NULL_LAYER: u32 = 3;
+const SHAPE_LAYER: u32 = 4;
+
+/// An exported animation and what could not be represented in it.
+pub struct Lottie {
+    pub json: Json,
+    /// One line per kind of dropped or approximated feature.
+    pub warnings: Vec<String>,
+}
+
+/// Lottie animation of `doc` over `frames`.
+pub fn export(doc: &Document, frames: Range<u32>) -> Lottie {
+    let mut exporter = Exporter {
+        frames: frames.start as f32..frames.end as f32,
+        next_index: 1,
+        warnings: BTreeMap::new(),
+    };
+
+    // Lottie lists layers top-most first.
+    let mut layers = Vec::new();
+    for layer in doc.layers.iter().rev() {
+        if layer.is_cel_layer() {
+            exporter.cel_layer(layer, &mut layers);
+        } else {
+            let json = exporter.shape_layer(layer, None, exporter.frames.clone(), 1.0);
+            layers.push(json);
+        }
+    }
+    layers.push(exporter.background(doc));
+
+    let json = json!({
+        "v": VERSION,
+        "fr": doc.frame_rate,
+        "ip": exporter.frames.start,
+        "op": exporter.frames.end,
+        "w": doc.width,
+        "h": doc.height,
+        "nm": "Motion Sketch",
+        "ddd": 0,
+        "assets": [],
+        "layers": layers,
+    });
+    let warnings = exporter
+        .warnings
+        .into_iter()
+        .map(|(message, count)| match count {
+            1 => message,
+            n => format!("{message} ({n} times)"),
+        })
+        .collect();
+    Lottie { json, warnings }
+}
+
+/// Writes the animation of `doc` over `frames` to `path`. Returns the
+/// export warnings.
+pub fn save(doc: &Document, frames: Range<u32>, path: &Path) -> Result<Vec<String>> {
+    let lottie = export(doc, frames);
+    let text = serde_json::to_string(&lottie.json)?;
+    fs::write(path, text).with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(lottie.warnings)
+}
+
+struct Exporter {
+    frames: Range<f32>,
+    /// `ind` of the next layer; parenting refers to layers by it.
+    next_index: u32,
+    /// Warning messages with how often they occurred.
+    warnings: BTreeMap<String, usize>,
+}
+
+impl Exporter {
+    fn warn(&mut self, message: impl Into<String>) {
+        *self.warnings.entry(message.into()).or_default() += 1;
+    }
+
+    fn index(&mut self) -> u32 {
+        let index = self.next_index;
+        self.next_index += 1;
+        index
+    }
+
+    /// Solid layer under everything, matching the artboard background.
+    fn background(&mut self, doc: &Document) -> Json {
+        let [r, g, b, _] = doc.background.to_rgba8();
+        json!({
+            "ddd": 0,
+            "ind": self.index(),
+            "ty": SOLID_LAYER,
+            "nm": "Background",
+            "sr": 1,
+            "ks": static_transform(doc.background.a),
+            "ao": 0,
+            "sc": format!("#{r:02x}{g:02x}{b:02x}"),
+            "sw": doc.width,
+            "sh": doc.height,
+            "ip": self.frames.start,
+            "op": self.frames.end,
+            "st": 0,
+            "bm": 0,
+        })
+    }
+
+    /// A shape layer for a layer or drawing node, alive during `range`.
+    /// `opacity` multiplies the node's own opacity.
+    fn shape_layer(
+        &mut self,
+        node: &Node,
+        parent: Option<u32>,
+        range: Range<f32>,
+        opacity: f32,
+    ) -> Json {
+        let mut layer = json!({
+            "ddd": 0,
+            "ind": self.index(),
+            "ty": SHAPE_LAYER,
+            "nm": node.name,
+            "sr": 1,
+            "ks": self.transform(node, opacity, false),
+            "ao": 0,
+            "shapes": self.children(node),
+            "ip": range.start,
+            "op": range.end,
+            "st": 0,
+            "bm": blend_mode(node.blend_mode),
+        });
+        if let Some(parent) = parent {
+            layer["parent"] = json!(parent);
+        }
+        if !node.visible {
+            layer["hd"] = json!(true);
+        }
+        layer
+    }
+
+    /// A null layer with the cel layer's transform and one shape layer per
+    /// drawing exposed in the exported range, parented to it.
+    fn cel_layer(&mut self, node: &Node, layers: &mut Vec<Json>) {
+        let parent = self.index();
+        // Parenting passes transforms on but not opacity, so the cel
+        // layer's opacity goes into every drawing instead.
+        if node.animation.is_animated(Property::Opacity) {
+            self.warn("animated opacity of cel layers is not exported");
+        }
+        let mut drawings = Vec::new();
+        for exposure in cel::exposures(node) {
+            let start = (exposure.start as f32).max(self.frames.start);
+            let end = (exposure.end() as f32).min(self.frames.end);
+            let Some(drawing) = node.children().iter().find(|c| c.id == exposure.drawing) else {
+                continue;
+            };
+            if start >= end {
+                continue;
+            }
+            let mut json = self.shape_layer(drawing, Some(parent), start..end, node.opacity);
+            if !node.visible {
+                json["hd"] = json!(true);
+            }
+            if drawing.blend_mode.is_normal() {
+                json["bm"] = json!(blend_mode(node.blend_mode));
+            }
+            drawings.push(json);
+        }
+        let null = json!({
+            "ddd": 0,
+            "ind": parent,
+            "ty": NULL_LAYER,
+            "nm": node.name,
+            "sr": 1,
+            "ks": self.transform(node, 1.0, false),
+            "ao": 0,
+            "ip": self.frames.start,
+            "op": self.frames.end,
+            "st": 0,
+            "bm": 0,
+        });
+        // Drawings follow one another in time, so their stacking order does
+        // not matter; the null only needs to come before its children.
+        layers.push(null);
+        layers.extend(drawings.into_iter().rev());
+    }
+
+    /// Shape items for a container's children, top-most first.
+    fn children(&mut self, node: &Node) -> Vec<Json> {
+        node.children()
+            .iter()
+            .rev()
+            .filter_map(|child| self.item(child))
+            .collect()
+    }
+
+    /// A shape group for a group or shape node.
+    fn item(&mut self, node: &Node) -> Option<Json> {
+        if !node.blend_mode.is_normal() {
+            self.warn("blend modes below layer level are not supported");
+        }
+        let mut items = match &node.kind {
+            NodeKind::Group { children } if children.is_empty() => return None,
+            NodeKind::Group { .. } => self.children(node),
+            NodeKind::Shape(shape) => self.shape(node, shape),
+            NodeKind::Layer { .. } | NodeKind::CelLayer { .. } | NodeKind::Drawing { .. } => {
+                self.warn("layers nested inside other nodes are not supported");
+                return None;
+            }
+        };
+        items.push(self.transform(node, 1.0, true));
+        let mut group = json!({
+            "ty": "gr",
+            "nm": node.name,
+            "it": items,
+        });
+        if !node.visible {
+            group["hd"] = json!(true);
+        }
+        Some(group)
+    }
+
+    /// Geometry and paint items of a shape; stroke before fill so it
+    /// paints on top.
+    fn shape(&mut self, node: &Node, shape: &Shape) -> Vec<Json> {
+        let mut items = vec![self.geometry(node, &shape.geometry)];
+        if let Some(stroke) = &shape.stroke {
+            items.push(self.stroke(node, stroke));
+        }
+        match &shape.fill {
+            Some(Fill::Solid(color)) => items.push(self.solid_fill(node, *color)),
+            Some(fill) => items.push(gradient_fill(fill)),
+            None => {}
+        }
+        items
+    }
+
+    fn geometry(&mut self, node: &Node, geometry: &Geometry) -> Json {
+        match geometry {
+            Geometry::Rect {
+                size,
+                corner_radius,
+            } => json!({
+                "ty": "rc",
+                "d": 1,
+                "p": constant(&[size.x / 2.0, size.y / 2.0]),
+                "s": constant(&[size.x, size.y]),
+                "r": constant(&[*corner_radius]),
+            }),
+            Geometry::Ellipse { radii } => json!({
+                "ty": "el",
+                "d": 1,
+                "p": constant(&[0.0, 0.0]),
+                "s": constant(&[radii.x * 2.0, radii.y * 2.0]),
+            }),
+            Geometry::Path(data) => {
+                let closed = data.closed;
+                let path = |value: &Value| match value {
+                    Value::Path(points) => vec![bezier_shape(points, closed)],
+                    _ => vec![bezier_shape(&data.points, closed)],
+                };
+                let static_value = Value::Path(data.points.clone());
+                json!({
+                    "ty": "sh",
+                    "d": 1,
+                    "ks": self.property(node, Property::PathPoints, &static_value, path),
+                })
+            }
+            Geometry::Brush(points) => {
+                let outline: Vec<PathPoint> = stroke::outline(points)
+                    .into_iter()
+                    .map(PathPoint::corner)
+                    .collect();
+                json!({
+                    "ty": "sh",
+                    "d": 1,
+                    "ks": fixed(vec![bezier_shape(&outline, true)]),
+                })
+            }
+        }
+    }
+
+    fn solid_fill(&mut self, node: &Node, color: Color) -> Json {
+        let value = Value::Color(color);
+        json!({
+            "ty": "fl",
+            "c": self.property(node, Property::FillColor, &value, rgb),
+            "o": self.property(node, Property::FillColor, &value, alpha),
+            "r": 1,
+        })
+    }
+
+    fn stroke(&mut self, node: &Node, stroke: &Stroke) -> Json {
+        let color = Value::Color(stroke.color);
+        let width = Value::Scalar(stroke.width);
+        json!({
+            "ty": "st",
+            "c": fixed(rgb(&color)),
+            "o": fixed(alpha(&color)),
+            "w": self.property(node, Property::StrokeWidth, &width, scalar),
+            "lc": match stroke.cap {
+                LineCap::Butt => 1,
+                LineCap::Round => 2,
+                LineCap::Square => 3,
+            },
+            "lj": match stroke.join {
+                LineJoin::Miter => 1,
+                LineJoin::Round => 2,
+                LineJoin::Bevel => 3,
+            },
+            "ml": 4,
+        })
+    }
+
+    /// Layer `ks` or group `tr` transform. `opacity` multiplies the node's
+    /// own opacity.
+    fn transform(&mut self, node: &Node, opacity: f32, group: bool) -> Json {
+        let t = &node.transform;
+        let mut json = json!({
+            "a": constant(&[t.anchor.x, t.anchor.y]),
+            "p": self.property(node, Property::Position, &Value::Point(t.position), point),
+            "s": self.property(node, Property::Scale, &Value::Point(t.scale), percent_point),
+            "r": self.property(node, Property::Rotation, &Value::Scalar(t.rotation), scalar),
+        });
+        let convert = |value: &Value| match value {
+            Value::Scalar(v) => vec![num(v.clamp(0.0, 1.0) * opacity * 100.0)],
+            _ => vec![num(node.opacity * opacity * 100.0)],
+        };
+        let static_value = Value::Scalar(node.opacity);
+        json["o"] = self.property(node, Property::Opacity, &static_value, convert);
+        if group {
+            json["ty"] = json!("tr");
+        }
+        json
+    }
+
+    /// A property that is animated when the node has keyframes for it.
+    /// `convert` maps a value to Lottie's number array.
+    fn property(
+        &mut self,
+        node: &Node,
+        property: Property,
+        static_value: &Value,
+        convert: impl Fn(&Value) -> Vec<Json>,
+    ) -> Json {
+        match node.animation.track(property) {
+            Some(track) if !track.keyframes.is_empty() => self.animated(track, convert),
+            _ => fixed(convert(static_value)),
+        }
+    }
+
+    fn animated(&mut self, track: &Track, convert: impl Fn(&Value) -> Vec<Json>) -> Json {
+        let mut keyframes: Vec<Json> = Vec::new();
+        for pair in track.keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            let mut keyframe = json!({ "t": num(from.frame), "s": convert(&from.value) });
+            if !interpolates(from, to) {
+                keyframe["h"] = json!(1);
+                keyframes.push(keyframe);
+                continue;
+            }
+            match bezier(from.easing) {
+                Some([x1, y1, x2, y2]) => {
+                    keyframe["o"] = json!({ "x": [num(x1)], "y": [num(y1)] });
+                    keyframe["i"] = json!({ "x": [num(x2)], "y": [num(y2)] });
+                    keyframes.push(keyframe);
+                }
+                None => {
+                    self.warn(format!(
+                        "{} easing was baked into per-frame keyframes",
+                        from.easing
+                    ));
+                    // One linear keyframe per whole frame of the segment.
+                    let mut frame = from.frame;
+                    while frame < to.frame {
+                        let value = track.sample(frame).unwrap_or(from.value.clone());
+                        keyframes.push(json!({
+                            "t": num(frame),
+                            "s": convert(&value),
+                            "o": { "x": [0.0], "y": [0.0] },
+                            "i": { "x": [1.0], "y": [1.0] },
+                        }));
+                        frame = frame.floor() + 1.0;
+                    }
+                }
+            }
+        }
+        if let Some(last) = track.keyframes.last() {
+            keyframes.push(json!({ "t": num(last.frame), "s": convert(&last.value) }));
+        }
+        json!({ "a": 1, "k": keyframes })
+    }
+}
+
+/// False when the renderer holds `from`'s value over the segment: hold
+/// easing, or paths whose point counts differ.
+fn interpolates(from: &Keyframe, to: &Keyframe) -> bool {
+    match (&from.value, &to.value) {
+        _ if from.easing == Easing::Hold => false,
+        (Value::Path(a), Value::Path(b)) => a.len() == b.len(),
+        _ => true,
+    }
+}
+
+/// Cubic-bezier control points `[x1, y1, x2, y2]` of an easing, or `None`
+/// if it has no bezier form.
+fn bezier(easing: Easing) -> Option<[f32; 4]> {
+    // The curves are the usual CSS approximations of Penner's equations.
+    let (ease_in, ease_out, ease_in_out) = match easing {
+        Easing::Linear => return Some([0.0, 0.0, 1.0, 1.0]),
+        Easing::Hold => return None,
+        Easing::CubicBezier { x1, y1, x2, y2 } => return Some([x1, y1, x2, y2]),
+        Easing::In(curve) | Easing::Out(curve) | Easing::InOut(curve) => match curve {
+            Curve::Quad => (
+                [0.11, 0.0, 0.5, 0.0],
+                [0.5, 1.0, 0.89, 1.0],
+                [0.45, 0.0, 0.55, 1.0],
+            ),
+            Curve::Cubic => (
+                [0.32, 0.0, 0.67, 0.0],
+                [0.33, 1.0, 0.68, 1.0],
+                [0.65, 0.0, 0.35, 1.0],
+            ),
+            Curve::Expo => (
+                [0.7, 0.0, 0.84, 0.0],
+                [0.16, 1.0, 0.3, 1.0],
+                [0.87, 0.0, 0.13, 1.0],
+            ),
+            Curve::Back => (
+                [0.36, 0.0, 0.66, -0.56],
+                [0.34, 1.56, 0.64, 1.0],
+                [0.68, -0.6, 0.32, 1.6],
+            ),
+            Curve::Elastic | Curve::Bounce => return None,
+        },
+    };
+    Some(match easing {
+        Easing::In(_) => ease_in,
+        Easing::Out(_) => ease_out,
+        _ => ease_in_out,
+    })
+}
+
+/// An unanimated property. Single values (scalars and shapes) are written
+/// bare, vectors as arrays.
+fn fixed(mut values: Vec<Json>) -> Json {
+    match values.len() {
+        1 => json!({ "a": 0, "k": values.remove(0) }),
+        _ => json!({ "a": 0, "k": values }),
+    }
+}
+
+fn constant(values: &[f32]) -> Json {
+    fixed(values.iter().copied().map(num).collect())
+}
+
+fn static_transform(opacity: f32) -> Json {
+    json!({
+        "a": constant(&[0.0, 0.0]),
+        "p": constant(&[0.0, 0.0]),
+        "s": constant(&[100.0, 100.0]),
+        "r": constant(&[0.0]),
+        "o": constant(&[opacity * 100.0]),
+    })
+}
+
+fn scalar(value: &Value) -> Vec<Json> {
+    match value {
+        Value::Scalar(v) => vec![num(*v)],
+        _ => vec![num(0.0)],
+    }
+}
+
+fn point(value: &Value) -> Vec<Json> {
+    match value {
+        Value::Point(p) => vec![num(p.x), num(p.y)],
+        _ => vec![num(0.0), num(0.0)],
+    }
+}
+
+fn percent_point(value: &Value) -> Vec<Json> {
+    match value {
+        Value::Point(p) => vec![num(p.x * 100.0), num(p.y * 100.0)],
+        _ => vec![num(100.0), num(100.0)],
+    }
+}
+
+/// Color channels; Lottie keeps the alpha in a separate opacity.
+fn rgb(value: &Value) -> Vec<Json> {
+    match value {
+        Value::Color(c) => vec![num(c.r), num(c.g), num(c.b), num(1.0)],
+        _ => vec![num(0.0), num(0.0), num(0.0), num(1.0)],
+    }
+}
+
+fn alpha(value: &Value) -> Vec<Json> {
+    match value {
+        Value::Color(c) => vec![num(c.a * 100.0)],
+        _ => vec![num(100.0)],
+    }
+}
+
+/// Lottie bezier shape; tangents are relative to their vertex.
+fn bezier_shape(points: &[PathPoint], closed: bool) -> Json {
+    let relative = |p: Point, anchor: Point| json!([num(p.x - anchor.x), num(p.y - anchor.y)]);
+    json!({
+        "c": closed,
+        "v": points.iter().map(|p| json!([num(p.anchor.x), num(p.anchor.y)])).collect::<Vec<_>>(),
+        "i": points.iter().map(|p| relative(p.handle_in, p.anchor)).collect::<Vec<_>>(),
+        "o": points.iter().map(|p| relative(p.handle_out, p.anchor)).collect::<Vec<_>>(),
+    })
+}
+
+fn gradient_fill(fill: &Fill) -> Json {
+    let (kind, start, end, stops) = match fill {
+        Fill::LinearGradient { start, end, stops } => (1, *start, *end, stops),
+        Fill::RadialGradient {
+            center,
+            radius,
+            stops,
+        } => (2, *center, Point::new(center.x + radius, center.y), stops),
+        Fill::Solid(_) => unreachable!("solid fills are not gradients"),
+    };
+    json!({
+        "ty": "gf",
+        "t": kind,
+        "s": fixed(vec![num(start.x), num(start.y)]),
+        "e": fixed(vec![num(end.x), num(end.y)]),
+        "g": { "p": stops.len(), "k": fixed(vec![json!(gradient_colors(stops))]) },
+        "o": fixed(vec![num(100.0)]),
+        "r": 1,
+    })
+}
+
+/// Stops as `offset, r, g, b` quadruples, followed by `offset, alpha`
+/// pairs when any stop is translucent.
+fn gradient_colors(stops: &[GradientStop]) -> Vec<Json> {
+    let mut values: Vec<f32> = stops
+        .iter()
+        .flat_map(|s| [s.offset, s.color.r, s.color.g, s.color.b])
+        .collect();
+    if stops.iter().any(|s| s.color.a < 1.0) {
+        values.extend(stops.iter().flat_map(|s| [s.offset, s.color.a]));
+    }
+    values.into_iter().map(num).collect()
+}
+
+/// A number rounded to three decimals, which keeps the file small and free
+/// of float noise.
+fn num(value: f32) -> Json {
+    json!((value as f64 * 1000.0).round() / 1000.0)
+}
+
+fn blend_mode(mode: BlendMode) -> u32 {
+    match mode {
+        BlendMode::Normal => 0,
+        BlendMode::Multiply => 1,
+        BlendMode::Screen => 2,
+        BlendMode::Overlay => 3,
+        BlendMode::Darken => 4,
+        BlendMode::Lighten => 5,
+        BlendMode::ColorDodge => 6,
+        BlendMode::ColorBurn => 7,
+        BlendMode::Difference => 10,
+        BlendMode::Plus => 16,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A layer rotating with a Penner easing, holding a box whose position
+    /// is keyed with every kind of easing the exporter handles.
+    fn document() -> Document {
+        let mut doc = Document::new(200, 100);
+        let layer = doc.add_layer("Layer");
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(20.0, 10.0),
+            corner_radius: 0.0,
+        });
+        let rect = doc.add_shape(layer, "Box", shape).unwrap();
+
+        let animation = &mut doc.get_mut(layer).unwrap().animation;
+        animation.set_keyframe(Property::Rotation, 0.0, Value::Scalar(0.0));
+        animation.set_keyframe(Property::Rotation, 12.0, Value::Scalar(90.0));
+        animation.set_easing(Property::Rotation, 0.0, Easing::InOut(Curve::Quad));
+
+        let animation = &mut doc.get_mut(rect).unwrap().animation;
+        let keys = [
+            (0.0, Point::new(0.0, 0.0), Easing::EASE),
+            (6.0, Point::new(50.0, 20.0), Easing::Linear),
+            (12.0, Point::new(80.0, 40.0), Easing::Hold),
+            (18.0, Point::new(10.0, 10.0), Easing::Out(Curve::Elastic)),
+            (24.0, Point::new(100.0, 50.0), Easing::Linear),
+        ];
+        for (frame, position, easing) in keys {
+            animation.set_keyframe(Property::Position, frame, Value::Point(position));
+            animation.set_easing(Property::Position, frame, easing);
+        }
+        doc
+    }
+
+    /// Reads an animated Lottie property back into a track, turning hold
+    /// flags and bezier tangents into easings.
+    fn reimport(json: &Json, property: Property) -> Track {
+        assert_eq!(json["a"], 1);
+        let mut track = Track::new(property);
+        for key in json["k"].as_array().unwrap() {
+            let number = |value: &Json| value.as_f64().unwrap() as f32;
+            let s: Vec<f32> = key["s"].as_array().unwrap().iter().map(number).collect();
+            let value = match s[..] {
+                [v] => Value::Scalar(v),
+                [x, y] => Value::Point(Point::new(x, y)),
+                _ => panic!("unexpected value {s:?}"),
+            };
+            let tangents = [
+                &key["o"]["x"],
+                &key["o"]["y"],
+                &key["i"]["x"],
+                &key["i"]["y"],
+            ];
+            let easing = match tangents.map(|t| t[0].as_f64().map(|v| v as f32)) {
+                _ if key["h"] == 1 => Easing::Hold,
+                [Some(x1), Some(y1), Some(x2), Some(y2)]
+                    if [x1, y1, x2, y2] != [0.0, 0.0, 1.0, 1.0] =>
+                {
+                    Easing::CubicBezier { x1, y1, x2, y2 }
+                }
+                _ => Easing::Linear,
+            };
+            track.insert(Keyframe {
+                frame: number(&key["t"]),
+                value,
+                easing,
+            });
+        }
+        track
+    }
+
+    #[test]
+    fn document_structure() {
+        let doc = document();
+        let lottie = export(&doc, 0..25);
+        let json = &lottie.json;
+        assert_eq!(json["v"], VERSION);
+        assert_eq!(json["fr"], json!(24.0));
+        assert_eq!(json["ip"], json!(0.0));
+        assert_eq!(json["op"], json!(25.0));
+        assert_eq!((&json["w"], &json["h"]), (&json!(200), &json!(100)));
+
+        let layers = json["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0]["ty"], SHAPE_LAYER);
+        assert_eq!(layers[0]["nm"], "Layer");
+        assert_eq!(layers[1]["ty"], SOLID_LAYER);
+        assert_eq!(layers[1]["sc"], "#ffffff");
+
+        let ks = &layers[0]["ks"];
+        for key in ["a", "p", "s", "r", "o"] {
+            assert!(ks[key].is_object(), "ks.{key} missing");
+        }
+        assert_eq!(ks["p"], json!({ "a": 0, "k": [0.0, 0.0] }));
+        assert_eq!(ks["s"], json!({ "a": 0, "k": [100.0, 100.0] }));
+        assert_eq!(ks["o"], json!({ "a": 0, "k": 100.0 }));
+        let rotation = json!({
+            "a": 1,
+            "k": [
+                {
+                    "t": 0.0,
+                    "s": [0.0],
+                    "o": { "x": [0.45], "y": [0.0] },
+                    "i": { "x": [0.55], "y": [1.0] },
+                },
+                { "t": 12.0, "s": [90.0] },
+            ],
+        });
+        assert_eq!(ks["r"], rotation);
+
+        let group = &layers[0]["shapes"][0];
+        assert_eq!(group["ty"], "gr");
+        assert_eq!(group["nm"], "Box");
+        let items = group["it"].as_array().unwrap();
+        let types: Vec<&str> = items.iter().map(|i| i["ty"].as_str().unwrap()).collect();
+        assert_eq!(types, ["rc", "fl", "tr"]);
+
+        let keys = items[2]["p"]["k"].as_array().unwrap();
+        assert_eq!(keys[0]["o"], json!({ "x": [0.25], "y": [0.1] }));
+        assert_eq!(keys[0]["i"], json!({ "x": [0.25], "y": [1.0] }));
+        assert_eq!(keys[1]["o"], json!({ "x": [0.0], "y": [0.0] }));
+        assert_eq!(keys[1]["i"], json!({ "x": [1.0], "y": [1.0] }));
+        assert_eq!(keys[2]["t"], json!(12.0));
+        assert_eq!(keys[2]["h"], 1);
+        assert!(keys[2].get("o").is_none());
+        assert_eq!(
+            lottie.warnings,
+            ["ease-out-elastic easing was baked into per-frame keyframes"]
+        );
+    }
+
+    #[test]
+    fn keyframes_survive_reimport() {
+        let doc = document();
+        let json = export(&doc, 0..25).json;
+        let group = &json["layers"][0]["shapes"][0];
+        let transform = group["it"].as_array().unwrap().last().unwrap();
+        let track = reimport(&transform["p"], Property::Position);
+
+        let frames: Vec<f32> = track.keyframes.iter().map(|k| k.frame).collect();
+        let baked = [18.0, 19.0, 20.0, 21.0, 22.0, 23.0];
+        assert_eq!(frames, [&[0.0, 6.0, 12.0][..], &baked, &[24.0]].concat());
+        let easings: Vec<Easing> = track.keyframes.iter().map(|k| k.easing).collect();
+        assert_eq!(&easings[..3], [Easing::EASE, Easing::Linear, Easing::Hold]);
+
+        let rect = doc.layers[0].children()[0]
+            .animation
+            .track(Property::Position);
+        let original = rect.unwrap();
+        for frame in 0..=24 {
+            let frame = frame as f32;
+            let (Some(Value::Point(expected)), Some(Value::Point(actual))) =
+                (original.sample(frame), track.sample(frame))
+            else {
+                panic!("no position at frame {frame}");
+            };
+            assert!(
+                expected.distance(actual) < 1e-3,
+                "frame {frame}: {expected:?} != {actual:?}"
+            );
+        }
+
+        let layer = &json["layers"][0]["ks"];
+        let rotation = reimport(&layer["r"], Property::Rotation);
+        let values: Vec<&Value> = rotation.keyframes.iter().map(|k| &k.value).collect();
+        assert_eq!(values, [&Value::Scalar(0.0), &Value::Scalar(90.0)]);
+    }
+}
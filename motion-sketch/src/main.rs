@@ -1,20 +1,33 @@
-use skia_safe::{gpu, Color, Paint, PaintStyle, Surface, Canvas};
-use raw_window_handle::{HasWindowHandle};
-use slint::platform::WindowAdapter;
+mod animation;
+mod app;
+mod bezier;
+mod cel;
+mod cli;
+mod easing;
+mod export;
+mod history;
+mod inspector;
+mod lottie;
+mod onion;
+mod playback;
+mod project;
+mod render;
+mod scene;
+mod snap;
+mod stroke;
+mod svg;
+mod timeline;
+mod tools;
+mod viewport;
 
 slint::include_modules!();
 
 fn main() -> anyhow::Result<()> {
-    // Initialize your Slint UI
-    let ui = AppWindow::new()?;
-    let window = ui.window();
-    let window_handle = window.window_handle();
+    // Headless subcommands never touch the windowing system
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = cli::Command::parse(&args)? {
+        return command.run();
+    }
 
-    // Access the raw window handle
-    let raw_window_handle = window_handle.window_handle().unwrap();
-
-    // Set up Vulkan context for Skia
-
-    ui.run()?;
-    Ok(())
+    app::run()
 }
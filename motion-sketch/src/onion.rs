@@ -0,0 +1,174 @@
+// Onion skinning: faded, tinted copies of neighbouring frames drawn beneath
+// the current frame in the editor viewport. The settings are part of the
+// document so they are saved with the project; exports never show them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::cel;
+use crate::scene::{Color, Document};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnionSkin {
+    pub enabled: bool,
+    /// Number of earlier frames shown.
+    pub before: u32,
+    /// Number of later frames shown.
+    pub after: u32,
+    /// Frames between two ghosts.
+    pub spacing: u32,
+    /// Opacity of the ghosts nearest to the current frame; farther ones
+    /// fade out linearly.
+    pub opacity: f32,
+    pub before_tint: Color,
+    pub after_tint: Color,
+    /// Show the nearest keyframes instead of evenly spaced frames.
+    pub keyframes_only: bool,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            before: 2,
+            after: 2,
+            spacing: 1,
+            opacity: 0.4,
+            before_tint: Color::rgb(0.9, 0.2, 0.2),
+            after_tint: Color::rgb(0.2, 0.7, 0.3),
+            keyframes_only: false,
+        }
+    }
+}
+
+/// One ghost frame to draw.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ghost {
+    pub frame: f32,
+    pub tint: Color,
+    pub opacity: f32,
+}
+
+impl OnionSkin {
+    /// Ghosts around `frame`, farthest first so nearer ones draw on top.
+    pub fn ghosts(&self, doc: &Document, frame: f32) -> Vec<Ghost> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let (previous, next) = if self.keyframes_only {
+            let keys = key_frames(doc);
+            let previous: Vec<f32> = keys.iter().rev().copied().filter(|k| *k < frame).collect();
+            let next: Vec<f32> = keys.iter().copied().filter(|k| *k > frame).collect();
+            (previous, next)
+        } else {
+            let spacing = self.spacing.max(1) as f32;
+            let last = doc.duration.saturating_sub(1) as f32;
+            let step = |k: u32| k as f32 * spacing;
+            let previous = (1..=self.before)
+                .map(|k| frame - step(k))
+                .take_while(|f| *f >= 0.0)
+                .collect();
+            let next = (1..=self.after)
+                .map(|k| frame + step(k))
+                .take_while(|f| *f <= last)
+                .collect();
+            (previous, next)
+        };
+
+        let mut ghosts = Vec::new();
+        for (frames, count, tint) in [
+            (previous, self.before, self.before_tint),
+            (next, self.after, self.after_tint),
+        ] {
+            for (index, frame) in frames.into_iter().take(count as usize).enumerate() {
+                let falloff = 1.0 - index as f32 / count as f32;
+                ghosts.push(Ghost {
+                    frame,
+                    tint,
+                    opacity: self.opacity * falloff,
+                });
+            }
+        }
+        ghosts.sort_by(|a, b| (b.frame - frame).abs().total_cmp(&(a.frame - frame).abs()));
+        ghosts
+    }
+}
+
+/// Every frame holding a keyframe or starting a cel drawing anywhere in the
+/// document, ascending.
+fn key_frames(doc: &Document) -> Vec<f32> {
+    let mut frames: Vec<f32> = doc
+        .walk()
+        .into_iter()
+        .flat_map(|(_, node)| node.animation.keyframe_frames())
+        .chain(cel::drawing_starts(doc))
+        .collect();
+    frames.sort_by(f32::total_cmp);
+    frames.dedup();
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Property, Value};
+
+    fn onion(before: u32, after: u32, spacing: u32) -> OnionSkin {
+        OnionSkin {
+            enabled: true,
+            before,
+            after,
+            spacing,
+            ..OnionSkin::default()
+        }
+    }
+
+    fn frames(ghosts: &[Ghost]) -> Vec<f32> {
+        ghosts.iter().map(|g| g.frame).collect()
+    }
+
+    #[test]
+    fn ghosts_are_spaced_and_fade_out_farthest_first() {
+        let doc = Document::new(100, 100);
+        let skin = onion(2, 2, 3);
+        let ghosts = skin.ghosts(&doc, 10.0);
+        assert_eq!(frames(&ghosts), [4.0, 16.0, 7.0, 13.0]);
+
+        let before = ghosts.iter().find(|g| g.frame == 7.0).unwrap();
+        assert_eq!(before.tint, skin.before_tint);
+        assert_eq!(before.opacity, skin.opacity);
+        let after = ghosts.iter().find(|g| g.frame == 16.0).unwrap();
+        assert_eq!(after.tint, skin.after_tint);
+        assert_eq!(after.opacity, skin.opacity / 2.0);
+
+        let disabled = OnionSkin {
+            enabled: false,
+            ..skin
+        };
+        assert!(disabled.ghosts(&doc, 10.0).is_empty());
+    }
+
+    #[test]
+    fn ghosts_stay_inside_the_animation() {
+        let doc = Document::new(100, 100);
+        let last = doc.duration as f32 - 1.0;
+        assert_eq!(frames(&onion(3, 0, 1).ghosts(&doc, 1.0)), [0.0]);
+        assert_eq!(frames(&onion(0, 3, 1).ghosts(&doc, last - 1.0)), [last]);
+    }
+
+    #[test]
+    fn keyframes_only_shows_the_nearest_keys() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let animation = &mut doc.get_mut(layer).unwrap().animation;
+        for frame in [0.0, 4.0, 9.0, 10.0, 30.0, 50.0] {
+            animation.set_keyframe(Property::Opacity, frame, Value::Scalar(1.0));
+        }
+        let skin = OnionSkin {
+            keyframes_only: true,
+            // Spacing does not apply to keyframes.
+            ..onion(2, 1, 5)
+        };
+        assert_eq!(frames(&skin.ghosts(&doc, 10.0)), [30.0, 4.0, 9.0]);
+    }
+}
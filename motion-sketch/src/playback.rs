@@ -0,0 +1,262 @@
+// Playback clock.
+//
+// Turns wall-clock time into document frames. The clock never reads the
+// time itself: callers pass the current `Instant`, so the app drives it from
+// a `slint::Timer` while a simulated clock can feed it any sequence of
+// instants. The frame is computed from the time elapsed since playback
+// started instead of being incremented per tick, so a slow render skips
+// frames rather than letting playback drift behind real time.
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Play to the end of the range and stop.
+    Once,
+    #[default]
+    Loop,
+    /// Bounce back and forth between the ends of the range.
+    PingPong,
+}
+
+impl LoopMode {
+    pub const ALL: [LoopMode; 3] = [LoopMode::Loop, LoopMode::Once, LoopMode::PingPong];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LoopMode::Once => "Once",
+            LoopMode::Loop => "Loop",
+            LoopMode::PingPong => "Ping-pong",
+        }
+    }
+}
+
+/// Playback speeds offered in the UI.
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 1.5, 2.0, 4.0];
+
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    /// When the current run started or was last rebased.
+    origin: Instant,
+    /// Frames played before `origin`, counted from the range start and
+    /// without folding for loops.
+    played: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Playback {
+    pub mode: LoopMode,
+    /// Inclusive frame range to play; the whole document when `None`.
+    pub range: Option<(u32, u32)>,
+    speed: f32,
+    run: Option<Run>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            mode: LoopMode::default(),
+            range: None,
+            speed: 1.0,
+            run: None,
+        }
+    }
+}
+
+impl Playback {
+    pub fn is_playing(&self) -> bool {
+        self.run.is_some()
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// First and last frame played for a document `duration` frames long.
+    /// Both are inclusive and clamped to the document's last frame.
+    pub fn bounds(&self, duration: u32) -> (u32, u32) {
+        let last = duration.saturating_sub(1);
+        match self.range {
+            Some((start, end)) => (start.min(end).min(last), start.max(end).min(last)),
+            None => (0, last),
+        }
+    }
+
+    /// Starts playing from `frame`, or from the start of the range if
+    /// `frame` lies outside it.
+    pub fn play(&mut self, now: Instant, frame: f32, duration: u32) {
+        let (start, end) = self.bounds(duration);
+        let frame = frame.floor();
+        let played = if frame >= start as f32 && frame < end as f32 {
+            (frame - start as f32) as f64
+        } else {
+            0.0
+        };
+        self.run = Some(Run {
+            origin: now,
+            played,
+        });
+    }
+
+    /// Stops advancing; the playhead stays where it is.
+    pub fn pause(&mut self) {
+        self.run = None;
+    }
+
+    /// Stops and returns the frame to rewind to.
+    pub fn stop(&mut self, duration: u32) -> f32 {
+        self.run = None;
+        self.bounds(duration).0 as f32
+    }
+
+    /// Changes the speed without jumping: the frames played so far are
+    /// kept and only time from `now` on runs at the new speed.
+    pub fn set_speed(&mut self, speed: f32, now: Instant, frame_rate: f32) {
+        if let Some(run) = &mut self.run {
+            run.played += elapsed_frames(run.origin, now, frame_rate, self.speed);
+            run.origin = now;
+        }
+        self.speed = speed.max(0.01);
+    }
+
+    /// Frame to show at `now`, or `None` when not playing. A `Once` run
+    /// that reaches the end of the range stops itself there.
+    pub fn tick(&mut self, now: Instant, frame_rate: f32, duration: u32) -> Option<f32> {
+        let run = self.run?;
+        let (start, end) = self.bounds(duration);
+        let length = (end - start) as u64;
+        let played = (run.played + elapsed_frames(run.origin, now, frame_rate, self.speed)) as u64;
+        let offset = match self.mode {
+            LoopMode::Once => {
+                if played >= length {
+                    self.run = None;
+                }
+                played.min(length)
+            }
+            LoopMode::Loop => played % (length + 1),
+            LoopMode::PingPong if length == 0 => 0,
+            LoopMode::PingPong => {
+                let phase = played % (2 * length);
+                if phase <= length {
+                    phase
+                } else {
+                    2 * length - phase
+                }
+            }
+        };
+        Some((start as u64 + offset) as f32)
+    }
+
+    /// How often the driving timer should call `tick`: once per frame at
+    /// the current speed.
+    pub fn tick_interval(&self, frame_rate: f32) -> Duration {
+        let fps = (frame_rate * self.speed).max(1.0);
+        Duration::from_secs_f32(1.0 / fps).max(Duration::from_millis(4))
+    }
+}
+
+fn elapsed_frames(origin: Instant, now: Instant, frame_rate: f32, speed: f32) -> f64 {
+    now.saturating_duration_since(origin).as_secs_f64() * frame_rate as f64 * speed as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FPS: f32 = 10.0;
+    const DURATION: u32 = 5;
+
+    /// Plays from frame 0 and samples the clock halfway through each of
+    /// the first `ticks` frames.
+    fn frames(playback: &mut Playback, ticks: u32) -> Vec<Option<f32>> {
+        let origin = Instant::now();
+        playback.play(origin, 0.0, DURATION);
+        (0..ticks)
+            .map(|n| {
+                let now = origin + Duration::from_secs_f32((n as f32 + 0.5) / FPS);
+                playback.tick(now, FPS, DURATION)
+            })
+            .collect()
+    }
+
+    fn playing(frames: &[f32]) -> Vec<Option<f32>> {
+        frames.iter().copied().map(Some).collect()
+    }
+
+    #[test]
+    fn bounds_stop_at_the_last_frame() {
+        let mut playback = Playback::default();
+        assert_eq!(playback.bounds(DURATION), (0, 4));
+        assert_eq!(playback.bounds(0), (0, 0));
+        playback.range = Some((3, 1));
+        assert_eq!(playback.bounds(DURATION), (1, 3));
+        playback.range = Some((2, 99));
+        assert_eq!(playback.bounds(DURATION), (2, 4));
+        playback.range = Some((50, 99));
+        assert_eq!(playback.bounds(DURATION), (4, 4));
+    }
+
+    #[test]
+    fn loop_wraps_after_the_last_frame() {
+        let mut playback = Playback::default();
+        let expected = playing(&[0., 1., 2., 3., 4., 0., 1., 2., 3., 4., 0., 1.]);
+        assert_eq!(frames(&mut playback, 12), expected);
+        assert!(playback.is_playing());
+    }
+
+    #[test]
+    fn loop_stays_inside_the_range() {
+        let mut playback = Playback {
+            range: Some((1, 2)),
+            ..Playback::default()
+        };
+        let expected = playing(&[1., 2., 1., 2., 1., 2.]);
+        assert_eq!(frames(&mut playback, 6), expected);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let mut playback = Playback {
+            mode: LoopMode::PingPong,
+            ..Playback::default()
+        };
+        let expected = playing(&[0., 1., 2., 3., 4., 3., 2., 1., 0., 1., 2., 3.]);
+        assert_eq!(frames(&mut playback, 12), expected);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut playback = Playback {
+            mode: LoopMode::Once,
+            ..Playback::default()
+        };
+        let mut expected = playing(&[0., 1., 2., 3., 4.]);
+        expected.extend([None, None]);
+        assert_eq!(frames(&mut playback, 7), expected);
+        assert!(!playback.is_playing());
+    }
+
+    #[test]
+    fn play_resumes_from_a_frame_inside_the_range() {
+        let mut playback = Playback::default();
+        let origin = Instant::now();
+        playback.play(origin, 2.7, DURATION);
+        assert_eq!(playback.tick(origin, FPS, DURATION), Some(2.0));
+
+        playback.range = Some((3, 4));
+        playback.play(origin, 2.0, DURATION);
+        assert_eq!(playback.tick(origin, FPS, DURATION), Some(3.0));
+    }
+
+    #[test]
+    fn speed_changes_keep_the_frames_played() {
+        let mut playback = Playback::default();
+        let origin = Instant::now();
+        let at = |secs: f32| origin + Duration::from_secs_f32(secs);
+        playback.play(origin, 0.0, DURATION);
+        playback.set_speed(2.0, at(0.25), FPS);
+        assert_eq!(playback.tick(at(0.25), FPS, DURATION), Some(2.0));
+        assert_eq!(playback.tick(at(0.3), FPS, DURATION), Some(3.0));
+        assert!((playback.tick_interval(FPS).as_secs_f32() - 0.05).abs() < 1e-6);
+    }
+}
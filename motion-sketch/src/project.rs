@@ -0,0 +1,208 @@
+// Project files (`.msk`).
+//
+// A project is a JSON envelope around the serialized `Document`:
+//
+//     { "format": "motion-sketch", "version": 1, "document": { ... } }
+//
+// `version` is bumped whenever the document schema changes incompatibly.
+// Older files are upgraded on load by running every migration between the
+// file's version and `CURRENT_VERSION` on the raw JSON, before the typed
+// deserialization, so the Rust types only ever need to match the latest
+// schema.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::scene::Document;
+
+pub const EXTENSION: &str = "msk";
+pub const FORMAT: &str = "motion-sketch";
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Migration `MIGRATIONS[n]` upgrades a version `n` file to version `n + 1`.
+const MIGRATIONS: [fn(Value) -> Result<Value>; CURRENT_VERSION as usize] = [migrate_v0_to_v1];
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    format: String,
+    version: u32,
+    document: T,
+}
+
+/// Serializes a document to the current project schema.
+pub fn to_string(doc: &Document) -> Result<String> {
+    let envelope = Envelope {
+        format: FORMAT.into(),
+        version: CURRENT_VERSION,
+        document: doc,
+    };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// Parses a project of any supported version, migrating it as needed.
+pub fn from_str(text: &str) -> Result<Document> {
+    let mut value: Value = serde_json::from_str(text)?;
+    let mut version = version_of(&value)?;
+    if version > CURRENT_VERSION {
+        bail!(
+            "project was saved by a newer Motion Sketch (schema version {version}, \
+             this build supports up to {CURRENT_VERSION})"
+        );
+    }
+    while version < CURRENT_VERSION {
+        value = MIGRATIONS[version as usize](value)
+            .with_context(|| format!("cannot upgrade project from schema version {version}"))?;
+        version += 1;
+    }
+    let envelope: Envelope<Document> = serde_json::from_value(value)?;
+    Ok(envelope.document)
+}
+
+pub fn load(path: &Path) -> Result<Document> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("cannot read project {}", path.display()))?;
+    from_str(&text).with_context(|| format!("invalid project {}", path.display()))
+}
+
+/// Writes the project next to its destination first and then renames it
+/// into place, so a failed save never truncates an existing file.
+pub fn save(doc: &Document, path: &Path) -> Result<()> {
+    let text = to_string(doc)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, text).with_context(|| format!("cannot write project {}", path.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("cannot write project {}", path.display()))
+}
+
+fn version_of(value: &Value) -> Result<u32> {
+    let Some(object) = value.as_object() else {
+        bail!("project root must be a JSON object");
+    };
+    // Files written before the envelope existed are a bare document.
+    if !object.contains_key("format") {
+        return Ok(0);
+    }
+    if object["format"] != FORMAT {
+        bail!("not a Motion Sketch project");
+    }
+    let version = object
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("project has no schema version"))?;
+    u32::try_from(version).map_err(|_| anyhow!("invalid schema version {version}"))
+}
+
+fn migrate_v0_to_v1(document: Value) -> Result<Value> {
+    Ok(json!({ "format": FORMAT, "version": 1, "document": document }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Property, Value};
+    use crate::easing::{Curve, Easing};
+    use crate::scene::{
+        Color, Fill, Geometry, NodeKind, PathData, PathPoint, Point, Shape, Stroke, Transform,
+    };
+
+    fn sample_document() -> Document {
+        let mut doc = Document::new(640, 360);
+        doc.frame_rate = 30.0;
+        doc.duration = 90;
+        let background = doc.add_layer("Background");
+        let characters = doc.add_layer("Characters");
+
+        let mut rect = Shape::new(Geometry::Rect {
+            size: Point::new(640.0, 360.0),
+            corner_radius: 12.0,
+        });
+        rect.fill = Some(Fill::Solid(Color::rgb(0.1, 0.2, 0.3)));
+        doc.add_shape(background, "Sky", rect).unwrap();
+
+        let group = doc.make_node(
+            "Body",
+            NodeKind::Group {
+                children: Vec::new(),
+            },
+        );
+        let group_id = group.id;
+        doc.insert(Some(characters), 0, group).unwrap();
+        let mut path = Shape::new(Geometry::Path(PathData {
+            points: vec![
+                PathPoint::corner(Point::new(0.0, 0.0)),
+                PathPoint::corner(Point::new(40.0, 10.0)),
+                PathPoint::corner(Point::new(20.0, 50.0)),
+            ],
+            closed: true,
+        }));
+        path.stroke = Some(Stroke::new(Color::rgb(0.9, 0.5, 0.1), 3.5));
+        let head = doc.add_shape(group_id, "Head", path).unwrap();
+
+        let body = doc.get_mut(group_id).unwrap();
+        body.transform = Transform {
+            position: Point::new(100.0, 80.0),
+            rotation: 15.0,
+            scale: Point::new(1.5, 0.75),
+            anchor: Point::new(20.0, 25.0),
+        };
+        body.animation.set_keyframe(
+            Property::Position,
+            0.0,
+            Value::Point(Point::new(100.0, 80.0)),
+        );
+        body.animation.set_keyframe(
+            Property::Position,
+            45.0,
+            Value::Point(Point::new(300.0, 80.0)),
+        );
+        body.animation
+            .set_easing(Property::Position, 0.0, Easing::InOut(Curve::Back));
+        let head = doc.get_mut(head).unwrap();
+        head.animation
+            .set_keyframe(Property::Rotation, 10.0, Value::Scalar(-30.0));
+        head.animation
+            .set_keyframe(Property::Rotation, 20.0, Value::Scalar(30.0));
+        head.animation
+            .set_easing(Property::Rotation, 10.0, Easing::EASE);
+        head.opacity = 0.5;
+        doc
+    }
+
+    #[test]
+    fn save_then_load_gives_back_the_document() {
+        let doc = sample_document();
+        let path = std::env::temp_dir().join(format!(
+            "motion-sketch-round-trip-{}.{EXTENSION}",
+            std::process::id()
+        ));
+        save(&doc, &path).unwrap();
+        let loaded = load(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.unwrap(), doc);
+        assert_eq!(from_str(&to_string(&doc).unwrap()).unwrap(), doc);
+    }
+
+    #[test]
+    fn v0_bare_document_is_migrated() {
+        let doc = sample_document();
+        let bare = serde_json::to_value(&doc).unwrap();
+        assert_eq!(version_of(&bare).unwrap(), 0);
+
+        let upgraded = MIGRATIONS[0](bare.clone()).unwrap();
+        assert_eq!(upgraded["format"], FORMAT);
+        assert_eq!(upgraded["version"], 1);
+        assert_eq!(upgraded["document"], bare);
+
+        assert_eq!(from_str(&bare.to_string()).unwrap(), doc);
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let text = json!({ "format": FORMAT, "version": CURRENT_VERSION + 1, "document": {} });
+        assert!(from_str(&text.to_string()).is_err());
+    }
+}
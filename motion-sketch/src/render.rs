@@ -0,0 +1,753 @@
+// CPU raster renderer.
+//
+// The scene is drawn into a raster-backed `skia_safe::Surface`, so rendering
+// works the same on machines without a GPU, in CI and in the headless CLI.
+// The resulting pixels are handed to Slint as a plain RGBA image.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path as FsPath;
+
+use anyhow::{anyhow, Context, Result};
+use skia_safe::{
+    canvas::SaveLayerRec, color_filters, path_utils, surfaces, AlphaType, Canvas, Color4f,
+    ColorType, Font, FontMgr, FontStyle, ImageInfo, Matrix, Paint, PaintCap, PaintJoin, PaintStyle,
+    Path, PathFillType, RRect, Rect, Shader, TileMode,
+};
+
+use crate::animation::{self, PropertyValues};
+use crate::cel;
+use crate::onion::Ghost;
+use crate::scene::{
+    self, BlendMode, Document, Fill, Geometry, GradientStop, LineCap, LineJoin, Node, NodeKind,
+    Shape,
+};
+use crate::snap::{Axis, Grid, GridKind, Guide, Indicator};
+use crate::stroke;
+use crate::viewport::Viewport;
+
+/// Surroundings of the artboard in the editor viewport, the same as the
+/// panel background.
+const PASTEBOARD: Color4f = Color4f::new(0.21, 0.21, 0.21, 1.0);
+
+/// Thickness of the rulers along the top and left of the editor viewport,
+/// in pixels.
+pub const RULER_SIZE: f32 = 20.0;
+/// Labelled ruler ticks are at least this many pixels apart.
+const RULER_TICK_PIXELS: f32 = 50.0;
+/// Grid lines closer than this many pixels are thinned out.
+const MIN_GRID_PIXELS: f32 = 6.0;
+const GUIDE_COLOR: Color4f = Color4f::new(0.0, 0.75, 0.9, 0.9);
+const SNAP_COLOR: Color4f = Color4f::new(1.0, 0.2, 0.7, 1.0);
+
+/// What the artboard is cleared to before the layers are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Background {
+    /// The document's background color.
+    #[default]
+    Document,
+    /// Nothing, so exported pixels keep the alpha of what covers them.
+    Transparent,
+}
+
+/// Rendered pixels in RGBA8 with straight (non-premultiplied) alpha.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn to_slint_image(&self) -> slint::Image {
+        let buffer = slint::SharedPixelBuffer::<slint::Rgba8Pixel>::clone_from_slice(
+            &self.pixels,
+            self.width,
+            self.height,
+        );
+        slint::Image::from_rgba8(buffer)
+    }
+
+    pub fn save_png(&self, path: &FsPath) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("cannot create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Renders the whole artboard at `frame`, scaled to `width` x `height` pixels.
+pub fn render(
+    doc: &Document,
+    frame: f32,
+    width: u32,
+    height: u32,
+    background: Background,
+) -> Result<Frame> {
+    render_with_overlay(doc, frame, width, height, background, |_| {})
+}
+
+/// Like [`render`], then lets `overlay` draw on top in document space
+/// (tool previews, handles and other editor-only decorations).
+pub fn render_with_overlay(
+    doc: &Document,
+    frame: f32,
+    width: u32,
+    height: u32,
+    background: Background,
+    overlay: impl FnOnce(&Canvas),
+) -> Result<Frame> {
+    rasterize(width, height, |canvas| {
+        canvas.scale((
+            width as f32 / doc.width as f32,
+            height as f32 / doc.height as f32,
+        ));
+        draw_document(canvas, doc, frame, background);
+        overlay(canvas);
+    })
+}
+
+/// The editor viewport: the document seen through `viewport`, with the
+/// document's onion skins drawn beneath the current frame, content outside
+/// the artboard dimmed, the artboard edge outlined and the grid and guides
+/// on top. `overlay` draws in document space above that, and the rulers
+/// cover the top and left edges of the panel.
+pub fn render_editor_view(
+    doc: &Document,
+    frame: f32,
+    viewport: &Viewport,
+    overlay: impl FnOnce(&Canvas),
+) -> Result<Frame> {
+    let width = viewport.width.ceil().max(1.0) as u32;
+    let height = viewport.height.ceil().max(1.0) as u32;
+    rasterize(width, height, |canvas| {
+        canvas.clear(PASTEBOARD);
+        canvas.translate((viewport.width / 2.0, viewport.height / 2.0));
+        canvas.rotate(viewport.rotation, None);
+        canvas.scale((viewport.zoom, viewport.zoom));
+        canvas.translate((-viewport.center.x, -viewport.center.y));
+
+        let artboard = Rect::from_wh(doc.width as f32, doc.height as f32);
+        canvas.draw_rect(artboard, &Paint::new(color4f(doc.background), None));
+        for ghost in doc.onion.ghosts(doc, frame) {
+            draw_ghost(canvas, doc, &ghost);
+        }
+        for layer in &doc.layers {
+            draw_node(canvas, layer, frame);
+        }
+
+        // Dim whatever shows outside the artboard. The veil reaches past the
+        // panel's corners at any view rotation.
+        let reach = viewport.width.hypot(viewport.height) / 2.0 * viewport.pixel_size() + 1.0;
+        let center = viewport.center;
+        let mut veil = Path::rect(
+            Rect::from_ltrb(
+                center.x - reach,
+                center.y - reach,
+                center.x + reach,
+                center.y + reach,
+            ),
+            None,
+        );
+        veil.add_rect(artboard, None);
+        veil.set_fill_type(PathFillType::EvenOdd);
+        let veil_color = Color4f {
+            a: 0.7,
+            ..PASTEBOARD
+        };
+        let mut dim = Paint::new(veil_color, None);
+        dim.set_anti_alias(true);
+        canvas.draw_path(&veil, &dim);
+
+        let mut edge = Paint::new(Color4f::new(0.0, 0.0, 0.0, 0.5), None);
+        edge.set_anti_alias(true);
+        edge.set_style(PaintStyle::Stroke);
+        edge.set_stroke_width(viewport.pixel_size());
+        canvas.draw_rect(artboard, &edge);
+
+        let snap = &doc.snap;
+        if snap.grid.visible {
+            draw_grid(canvas, &snap.grid, viewport.zoom);
+        }
+        draw_guides(canvas, &snap.guides, viewport.pixel_size());
+
+        overlay(canvas);
+
+        if snap.rulers {
+            canvas.reset_matrix();
+            draw_rulers(canvas, viewport, &snap.guides);
+        }
+    })
+}
+
+/// Draws the grid over the visible part of the document plane, skipping
+/// lines where they would crowd together at low zoom.
+fn draw_grid(canvas: &Canvas, grid: &Grid, zoom: f32) {
+    let Some(visible) = canvas.local_clip_bounds() else {
+        return;
+    };
+    if grid.spacing <= 0.0 {
+        return;
+    }
+    let mut every = 1.0;
+    while grid.spacing * every * zoom < MIN_GRID_PIXELS {
+        every *= 2.0;
+    }
+    let spacing = grid.spacing * every;
+    let column = grid.column_width() * every;
+
+    let mut path = Path::new();
+    let steps = |from: f32, to: f32, step: f32| {
+        ((from / step).floor() as i64..=(to / step).ceil() as i64).map(move |k| k as f32 * step)
+    };
+    for x in steps(visible.left, visible.right, column) {
+        path.move_to((x, visible.top));
+        path.line_to((x, visible.bottom));
+    }
+    match grid.kind {
+        GridKind::Square => {
+            for y in steps(visible.top, visible.bottom, spacing) {
+                path.move_to((visible.left, y));
+                path.line_to((visible.right, y));
+            }
+        }
+        GridKind::Isometric => {
+            // Lines at +-30 degrees through the lattice points: one column
+            // across is half a spacing up or down.
+            for slope in [0.5 * spacing / column, -0.5 * spacing / column] {
+                let offsets = [visible.left, visible.right]
+                    .map(|x| [visible.top, visible.bottom].map(|y| y - slope * x));
+                let offsets = offsets.iter().flatten();
+                let low = offsets.clone().copied().fold(f32::INFINITY, f32::min);
+                let high = offsets.copied().fold(f32::NEG_INFINITY, f32::max);
+                for offset in steps(low, high, spacing) {
+                    path.move_to((visible.left, slope * visible.left + offset));
+                    path.line_to((visible.right, slope * visible.right + offset));
+                }
+            }
+        }
+    }
+    let mut paint = Paint::new(Color4f::new(0.5, 0.5, 0.5, 0.35), None);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(1.0 / zoom);
+    canvas.draw_path(&path, &paint);
+}
+
+fn draw_guides(canvas: &Canvas, guides: &[Guide], pixel_size: f32) {
+    let Some(visible) = canvas.local_clip_bounds() else {
+        return;
+    };
+    let mut paint = Paint::new(GUIDE_COLOR, None);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(pixel_size);
+    for guide in guides {
+        let p = guide.position;
+        match guide.axis {
+            Axis::Vertical => canvas.draw_line((p, visible.top), (p, visible.bottom), &paint),
+            Axis::Horizontal => canvas.draw_line((visible.left, p), (visible.right, p), &paint),
+        };
+    }
+}
+
+/// Draws what a drag snapped to, in document space: a line across the
+/// view for a snapped x or y and a cross on a snapped point.
+pub fn draw_snap_indicators(canvas: &Canvas, indicators: &[Indicator], pixel_size: f32) {
+    let Some(visible) = canvas.local_clip_bounds() else {
+        return;
+    };
+    let mut paint = Paint::new(SNAP_COLOR, None);
+    paint.set_anti_alias(true);
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(pixel_size);
+    let size = 5.0 * pixel_size;
+    for indicator in indicators {
+        match *indicator {
+            Indicator::Vertical(x) => {
+                canvas.draw_line((x, visible.top), (x, visible.bottom), &paint);
+            }
+            Indicator::Horizontal(y) => {
+                canvas.draw_line((visible.left, y), (visible.right, y), &paint);
+            }
+            Indicator::Point(p) => {
+                canvas.draw_line((p.x - size, p.y - size), (p.x + size, p.y + size), &paint);
+                canvas.draw_line((p.x - size, p.y + size), (p.x + size, p.y - size), &paint);
+            }
+        }
+    }
+}
+
+/// Draws the rulers in panel pixels. Ticks, labels and guide markers need
+/// the document axes to line up with the panel, so a rotated view only
+/// gets the empty bands, which still take guide drags.
+fn draw_rulers(canvas: &Canvas, viewport: &Viewport, guides: &[Guide]) {
+    let (width, height) = (viewport.width, viewport.height);
+    let band = Paint::new(Color4f::new(0.17, 0.17, 0.17, 1.0), None);
+    canvas.draw_rect(Rect::from_wh(width, RULER_SIZE), &band);
+    canvas.draw_rect(Rect::from_wh(RULER_SIZE, height), &band);
+    let mut line = Paint::new(Color4f::new(0.55, 0.55, 0.55, 1.0), None);
+    line.set_style(PaintStyle::Stroke);
+    line.set_stroke_width(1.0);
+    canvas.draw_line((RULER_SIZE, RULER_SIZE), (width, RULER_SIZE), &line);
+    canvas.draw_line((RULER_SIZE, RULER_SIZE), (RULER_SIZE, height), &line);
+    if viewport.rotation != 0.0 {
+        return;
+    }
+
+    let step = ruler_step(viewport.zoom);
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let font = FontMgr::new()
+        .legacy_make_typeface(None, FontStyle::normal())
+        .map(|typeface| Font::new(typeface, 10.0));
+    let text = Paint::new(Color4f::new(0.75, 0.75, 0.75, 1.0), None);
+    let first = viewport.to_document(scene::Point::new(RULER_SIZE, RULER_SIZE));
+    let last = viewport.to_document(scene::Point::new(width, height));
+    let ticks = |from: f32, to: f32| {
+        let start = (from / step).floor() as i64;
+        let end = (to / step).ceil() as i64;
+        (start..=end).flat_map(|k| (0..5).map(move |minor| (k as f32 + minor as f32 / 5.0, minor)))
+    };
+
+    for (k, minor) in ticks(first.x, last.x) {
+        let x = viewport.to_screen(scene::Point::new(k * step, 0.0)).x;
+        if x < RULER_SIZE || x > width {
+            continue;
+        }
+        let length = if minor == 0 { 8.0 } else { 4.0 };
+        canvas.draw_line((x, RULER_SIZE - length), (x, RULER_SIZE), &line);
+        if let (0, Some(font)) = (minor, &font) {
+            let label = format!("{:.*}", decimals, k * step);
+            canvas.draw_str(&label, (x + 3.0, RULER_SIZE - 9.0), font, &text);
+        }
+    }
+    for (k, minor) in ticks(first.y, last.y) {
+        let y = viewport.to_screen(scene::Point::new(0.0, k * step)).y;
+        if y < RULER_SIZE || y > height {
+            continue;
+        }
+        let length = if minor == 0 { 8.0 } else { 4.0 };
+        canvas.draw_line((RULER_SIZE - length, y), (RULER_SIZE, y), &line);
+        if let (0, Some(font)) = (minor, &font) {
+            // Labels run up the left ruler.
+            let label = format!("{:.*}", decimals, k * step);
+            canvas.save();
+            canvas.translate((RULER_SIZE - 9.0, y - 3.0));
+            canvas.rotate(-90.0, None);
+            canvas.draw_str(&label, (0.0, 0.0), font, &text);
+            canvas.restore();
+        }
+    }
+
+    let marker = Paint::new(GUIDE_COLOR, None);
+    for guide in guides {
+        let at = viewport.to_screen(scene::Point::new(guide.position, guide.position));
+        let triangle = match guide.axis {
+            Axis::Vertical if at.x >= RULER_SIZE => {
+                [(at.x - 4.0, 0.0), (at.x + 4.0, 0.0), (at.x, 6.0)]
+            }
+            Axis::Horizontal if at.y >= RULER_SIZE => {
+                [(0.0, at.y - 4.0), (0.0, at.y + 4.0), (6.0, at.y)]
+            }
+            _ => continue,
+        };
+        let mut path = Path::new();
+        path.add_poly(&triangle.map(skia_safe::Point::from), true);
+        canvas.draw_path(&path, &marker);
+    }
+}
+
+/// Document distance between labelled ruler ticks: 1, 2 or 5 times a power
+/// of ten, at least `RULER_TICK_PIXELS` apart.
+fn ruler_step(zoom: f32) -> f32 {
+    let target = RULER_TICK_PIXELS / zoom;
+    let magnitude = 10f32.powf(target.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= target)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Draws the layers at the ghost's frame, tinted and faded as one layer.
+fn draw_ghost(canvas: &Canvas, doc: &Document, ghost: &Ghost) {
+    let mut paint = Paint::default();
+    paint.set_alpha_f(ghost.opacity);
+    // Paint the tint over the content's own colors, keeping its alpha.
+    let tint = Color4f::new(ghost.tint.r, ghost.tint.g, ghost.tint.b, 0.6).to_color();
+    paint.set_color_filter(color_filters::blend(tint, skia_safe::BlendMode::SrcATop));
+    canvas.save_layer(&SaveLayerRec::default().paint(&paint));
+    for layer in &doc.layers {
+        draw_node(canvas, layer, ghost.frame);
+    }
+    canvas.restore();
+}
+
+/// Runs `draw` on a fresh `width` x `height` raster surface and reads the
+/// result back. Also used by editor panels that are drawn with skia, such
+/// as the timeline.
+pub fn rasterize(width: u32, height: u32, draw: impl FnOnce(&Canvas)) -> Result<Frame> {
+    let mut surface = surfaces::raster_n32_premul((width as i32, height as i32))
+        .ok_or_else(|| anyhow!("cannot allocate a {width}x{height} raster surface"))?;
+    draw(surface.canvas());
+
+    let info = ImageInfo::new(
+        (width as i32, height as i32),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let row_bytes = width as usize * 4;
+    let mut pixels = vec![0u8; row_bytes * height as usize];
+    if !surface.read_pixels(&info, &mut pixels, row_bytes, (0, 0)) {
+        return Err(anyhow!("failed to read back rendered pixels"));
+    }
+    Ok(Frame {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Draws the artboard background and every visible layer in document space,
+/// with animated properties sampled at `frame`.
+pub fn draw_document(canvas: &Canvas, doc: &Document, frame: f32, background: Background) {
+    let clear = match background {
+        Background::Document => doc.background,
+        Background::Transparent => scene::Color::TRANSPARENT,
+    };
+    canvas.clear(color4f(clear));
+    for layer in &doc.layers {
+        draw_node(canvas, layer, frame);
+    }
+}
+
+fn draw_node(canvas: &Canvas, node: &Node, frame: f32) {
+    let values = animation::sample(node, frame);
+    if !node.visible || values.opacity <= 0.0 {
+        return;
+    }
+
+    let t = values.transform(&node.transform);
+    // A save layer both saves the matrix and composites the subtree with
+    // the node's opacity and blend mode as a whole.
+    if values.opacity < 1.0 || !node.blend_mode.is_normal() {
+        let mut paint = Paint::default();
+        paint.set_alpha_f(values.opacity);
+        paint.set_blend_mode(blend_mode(node.blend_mode));
+        canvas.save_layer(&SaveLayerRec::default().paint(&paint));
+    } else {
+        canvas.save();
+    }
+    canvas.translate((t.position.x, t.position.y));
+    canvas.rotate(t.rotation, None);
+    canvas.scale((t.scale.x, t.scale.y));
+    canvas.translate((-t.anchor.x, -t.anchor.y));
+
+    match &node.kind {
+        NodeKind::Layer { children }
+        | NodeKind::Group { children }
+        | NodeKind::Drawing { children, .. } => {
+            for child in children {
+                draw_node(canvas, child, frame);
+            }
+        }
+        NodeKind::CelLayer { children } => {
+            if let Some(index) = cel::exposed_index(node, frame) {
+                draw_node(canvas, &children[index], frame);
+            }
+        }
+        NodeKind::Shape(shape) => draw_shape(canvas, shape, &values),
+    }
+
+    canvas.restore();
+}
+
+fn draw_shape(canvas: &Canvas, shape: &Shape, values: &PropertyValues) {
+    let path = outline(&shape.geometry, values.path_points.as_deref());
+
+    if let Some(fill) = &shape.fill {
+        let mut paint = Paint::default();
+        match fill {
+            Fill::Solid(color) => {
+                paint.set_color4f(color4f(values.fill_color.unwrap_or(*color)), None);
+            }
+            Fill::LinearGradient { start, end, stops } => {
+                let (colors, offsets) = gradient_stops(stops);
+                paint.set_shader(Shader::linear_gradient(
+                    (pt(*start), pt(*end)),
+                    colors.as_slice(),
+                    offsets.as_slice(),
+                    TileMode::Clamp,
+                    None,
+                    None,
+                ));
+            }
+            Fill::RadialGradient {
+                center,
+                radius,
+                stops,
+            } => {
+                let (colors, offsets) = gradient_stops(stops);
+                paint.set_shader(Shader::radial_gradient(
+                    pt(*center),
+                    *radius,
+                    colors.as_slice(),
+                    offsets.as_slice(),
+                    TileMode::Clamp,
+                    None,
+                    None,
+                ));
+            }
+        }
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Fill);
+        canvas.draw_path(&path, &paint);
+    }
+
+    if let Some(stroke) = &shape.stroke {
+        let mut paint = Paint::new(color4f(stroke.color), None);
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(values.stroke_width.unwrap_or(stroke.width));
+        paint.set_stroke_cap(match stroke.cap {
+            LineCap::Round => PaintCap::Round,
+            LineCap::Butt => PaintCap::Butt,
+            LineCap::Square => PaintCap::Square,
+        });
+        paint.set_stroke_join(match stroke.join {
+            LineJoin::Round => PaintJoin::Round,
+            LineJoin::Miter => PaintJoin::Miter,
+            LineJoin::Bevel => PaintJoin::Bevel,
+        });
+        canvas.draw_path(&path, &paint);
+    }
+}
+
+fn gradient_stops(stops: &[GradientStop]) -> (Vec<skia_safe::Color>, Vec<f32>) {
+    stops
+        .iter()
+        .map(|stop| (color4f(stop.color).to_color(), stop.offset.clamp(0.0, 1.0)))
+        .unzip()
+}
+
+fn blend_mode(mode: BlendMode) -> skia_safe::BlendMode {
+    match mode {
+        BlendMode::Normal => skia_safe::BlendMode::SrcOver,
+        BlendMode::Multiply => skia_safe::BlendMode::Multiply,
+        BlendMode::Screen => skia_safe::BlendMode::Screen,
+        BlendMode::Overlay => skia_safe::BlendMode::Overlay,
+        BlendMode::Darken => skia_safe::BlendMode::Darken,
+        BlendMode::Lighten => skia_safe::BlendMode::Lighten,
+        BlendMode::ColorDodge => skia_safe::BlendMode::ColorDodge,
+        BlendMode::ColorBurn => skia_safe::BlendMode::ColorBurn,
+        BlendMode::Difference => skia_safe::BlendMode::Difference,
+        BlendMode::Plus => skia_safe::BlendMode::Plus,
+    }
+}
+
+/// Finds the top-most visible, unlocked shape under the document-space
+/// point `p` at `frame`, ignoring cel drawings not exposed there. Strokes,
+/// and the outlines of unfilled shapes, count as hit within `tolerance`
+/// document units.
+pub fn hit_test(
+    doc: &Document,
+    frame: f32,
+    p: scene::Point,
+    tolerance: f32,
+) -> Option<scene::NodeId> {
+    let walk = doc.walk();
+    walk.iter()
+        .rev()
+        .filter(|(_, node)| node.shape().is_some() && is_editable(doc, node.id))
+        .filter(|(_, node)| cel::is_exposed(doc, node.id, frame))
+        .find(|(_, node)| shape_contains(doc, node, frame, p, tolerance))
+        .map(|(_, node)| node.id)
+}
+
+fn shape_contains(
+    doc: &Document,
+    node: &Node,
+    frame: f32,
+    p: scene::Point,
+    tolerance: f32,
+) -> bool {
+    let Some(shape) = node.shape() else {
+        return false;
+    };
+    let local = pt(animation::to_local(doc, node.id, frame, p));
+    let path = shape_path(node, frame);
+    if shape.fill.is_some() && path.contains(local) {
+        return true;
+    }
+
+    // The tolerance is in document units; shrink it by the node's scale to
+    // widen the local stroke by the same on-screen amount.
+    let scale = animation::document_scale(doc, node.id, frame);
+    let stroke_width = shape.stroke.as_ref().map_or(0.0, |stroke| {
+        animation::sample(node, frame)
+            .stroke_width
+            .unwrap_or(stroke.width)
+    });
+    let width = stroke_width + 2.0 * tolerance / scale;
+    if width <= 0.0 {
+        return false;
+    }
+    let mut paint = Paint::default();
+    paint.set_style(PaintStyle::Stroke);
+    paint.set_stroke_width(width);
+    paint.set_stroke_cap(PaintCap::Round);
+    paint.set_stroke_join(PaintJoin::Round);
+    let mut outline = Path::new();
+    path_utils::fill_path_with_paint(&path, &paint, &mut outline, None::<&Rect>, None::<Matrix>)
+        && outline.contains(local)
+}
+
+/// Bounds of a node's content at `frame` in its local coordinates, or
+/// `None` if it has nothing to show. Cel layers count only the drawing
+/// exposed at `frame`.
+pub fn node_bounds(node: &Node, frame: f32) -> Option<Rect> {
+    let children = match &node.kind {
+        NodeKind::Shape(_) => {
+            let path = shape_path(node, frame);
+            return (!path.is_empty()).then(|| path.compute_tight_bounds());
+        }
+        NodeKind::CelLayer { children } => match cel::exposed_index(node, frame) {
+            Some(index) => &children[index..=index],
+            None => &[],
+        },
+        _ => node.children(),
+    };
+    let mut corners = Vec::new();
+    for child in children {
+        let Some(rect) = node_bounds(child, frame) else {
+            continue;
+        };
+        let t = animation::sample(child, frame).transform(&child.transform);
+        corners.extend(
+            [
+                (rect.left, rect.top),
+                (rect.right, rect.top),
+                (rect.right, rect.bottom),
+                (rect.left, rect.bottom),
+            ]
+            .map(|(x, y)| t.apply(scene::Point::new(x, y))),
+        );
+    }
+    let first = corners.first()?;
+    let mut bounds = Rect::from_ltrb(first.x, first.y, first.x, first.y);
+    for p in &corners {
+        bounds.left = bounds.left.min(p.x);
+        bounds.top = bounds.top.min(p.y);
+        bounds.right = bounds.right.max(p.x);
+        bounds.bottom = bounds.bottom.max(p.y);
+    }
+    Some(bounds)
+}
+
+/// Corners of a node's local bounds in document space, or an empty list if
+/// the node shows nothing at `frame`.
+pub fn document_corners(doc: &Document, id: scene::NodeId, frame: f32) -> Vec<scene::Point> {
+    let Some(rect) = doc.get(id).and_then(|node| node_bounds(node, frame)) else {
+        return Vec::new();
+    };
+    [
+        (rect.left, rect.top),
+        (rect.right, rect.top),
+        (rect.right, rect.bottom),
+        (rect.left, rect.bottom),
+    ]
+    .into_iter()
+    .map(|(x, y)| animation::to_document(doc, id, frame, scene::Point::new(x, y)))
+    .collect()
+}
+
+/// True if the node and all its ancestors are visible and unlocked.
+pub fn is_editable(doc: &Document, id: scene::NodeId) -> bool {
+    doc.ancestry(id)
+        .into_iter()
+        .filter_map(|id| doc.get(id))
+        .all(|node| node.visible && !node.locked)
+}
+
+/// Outline of a shape node at `frame`, in its local coordinates.
+pub fn shape_path(node: &Node, frame: f32) -> Path {
+    let Some(shape) = node.shape() else {
+        return Path::new();
+    };
+    let values = animation::sample(node, frame);
+    outline(&shape.geometry, values.path_points.as_deref())
+}
+
+// Geometry outline with animated path points, if any, replacing the static
+// ones.
+fn outline(geometry: &Geometry, points: Option<&[scene::PathPoint]>) -> Path {
+    match (geometry, points) {
+        (Geometry::Path(data), Some(points)) => {
+            let mut path = Path::new();
+            append_points(&mut path, points, data.closed);
+            path
+        }
+        (geometry, _) => geometry_path(geometry),
+    }
+}
+
+/// Builds the outline of a shape in its local coordinates.
+pub fn geometry_path(geometry: &Geometry) -> Path {
+    let mut path = Path::new();
+    match geometry {
+        Geometry::Rect {
+            size,
+            corner_radius,
+        } => {
+            let rect = Rect::from_xywh(0.0, 0.0, size.x, size.y);
+            path.add_rrect(
+                RRect::new_rect_xy(rect, *corner_radius, *corner_radius),
+                None,
+            );
+        }
+        Geometry::Ellipse { radii } => {
+            path.add_oval(
+                Rect::from_xywh(-radii.x, -radii.y, radii.x * 2.0, radii.y * 2.0),
+                None,
+            );
+        }
+        Geometry::Path(data) => append_points(&mut path, &data.points, data.closed),
+        Geometry::Brush(points) => {
+            let polygon: Vec<skia_safe::Point> =
+                stroke::outline(points).into_iter().map(pt).collect();
+            path.add_poly(&polygon, true);
+        }
+    }
+    path
+}
+
+fn append_points(path: &mut Path, points: &[scene::PathPoint], closed: bool) {
+    let Some(first) = points.first() else {
+        return;
+    };
+    path.move_to(pt(first.anchor));
+    for pair in points.windows(2) {
+        path.cubic_to(
+            pt(pair[0].handle_out),
+            pt(pair[1].handle_in),
+            pt(pair[1].anchor),
+        );
+    }
+    if closed && points.len() > 1 {
+        let last = points[points.len() - 1];
+        path.cubic_to(pt(last.handle_out), pt(first.handle_in), pt(first.anchor));
+        path.close();
+    }
+}
+
+fn pt(p: scene::Point) -> skia_safe::Point {
+    skia_safe::Point::new(p.x, p.y)
+}
+
+fn color4f(c: scene::Color) -> Color4f {
+    Color4f::new(c.r, c.g, c.b, c.a)
+}
@@ -0,0 +1,877 @@
+// Scene document model.
+//
+// Everything the panels show (instrument list, viewport, timeline and
+// properties) reads from and writes to a single `Document`. The model is
+// plain Rust data with no knowledge of Slint or Skia so it can be built and
+// inspected headlessly.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::animation::Animation;
+use crate::onion::OnionSkin;
+use crate::snap::SnapSettings;
+use crate::stroke::BrushPoint;
+
+/// Stable identifier of a node inside a document. IDs are never reused, so
+/// they stay valid across reordering and reparenting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub u64);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub const ZERO: Point = Point { x: 0.0, y: 0.0 };
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn distance(self, other: Point) -> f32 {
+        (self - other).length()
+    }
+
+    pub fn length(self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    pub fn lerp(self, other: Point, t: f32) -> Point {
+        Point::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+
+    /// Rotates the vector clockwise by `degrees`, as `Transform` does.
+    pub fn rotate(self, degrees: f32) -> Point {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Point::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+impl std::ops::Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl std::ops::Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl std::ops::Mul<f32> for Point {
+    type Output = Point;
+    fn mul(self, rhs: f32) -> Point {
+        Point::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+/// Straight (non-premultiplied) RGBA color with components in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::rgba(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [c(self.r), c(self.g), c(self.b), c(self.a)]
+    }
+
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let l = |a: f32, b: f32| a + (b - a) * t;
+        Color::rgba(
+            l(self.r, other.r),
+            l(self.g, other.g),
+            l(self.b, other.b),
+            l(self.a, other.a),
+        )
+    }
+}
+
+/// Placement of a node relative to its parent. Rotation is in degrees,
+/// clockwise, around `anchor` (in the node's local coordinates).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub position: Point,
+    pub rotation: f32,
+    pub scale: Point,
+    pub anchor: Point,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Point::ZERO,
+            rotation: 0.0,
+            scale: Point::new(1.0, 1.0),
+            anchor: Point::ZERO,
+        }
+    }
+}
+
+impl Transform {
+    /// Maps a point from the node's local space into its parent's space.
+    pub fn apply(&self, p: Point) -> Point {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let x = (p.x - self.anchor.x) * self.scale.x;
+        let y = (p.y - self.anchor.y) * self.scale.y;
+        Point::new(
+            x * cos - y * sin + self.position.x,
+            x * sin + y * cos + self.position.y,
+        )
+    }
+
+    /// Inverse of [`Transform::apply`]. Degenerate scales map to the anchor.
+    pub fn invert(&self, p: Point) -> Point {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let dx = p.x - self.position.x;
+        let dy = p.y - self.position.y;
+        let x = dx * cos + dy * sin;
+        let y = -dx * sin + dy * cos;
+        let sx = if self.scale.x == 0.0 {
+            0.0
+        } else {
+            x / self.scale.x
+        };
+        let sy = if self.scale.y == 0.0 {
+            0.0
+        } else {
+            y / self.scale.y
+        };
+        Point::new(sx + self.anchor.x, sy + self.anchor.y)
+    }
+}
+
+/// Color at one position of a gradient, from 0 at its start to 1 at its end.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: Color,
+}
+
+/// Shape fill. Gradient geometry is in the shape's local coordinates and
+/// colors are padded beyond the first and last stop.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Fill {
+    Solid(Color),
+    LinearGradient {
+        start: Point,
+        end: Point,
+        stops: Vec<GradientStop>,
+    },
+    RadialGradient {
+        center: Point,
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineCap {
+    #[default]
+    Round,
+    Butt,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineJoin {
+    #[default]
+    Round,
+    Miter,
+    Bevel,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+}
+
+impl Stroke {
+    pub fn new(color: Color, width: f32) -> Self {
+        Self {
+            color,
+            width,
+            cap: LineCap::default(),
+            join: LineJoin::default(),
+        }
+    }
+}
+
+/// Anchor of a cubic Bezier path with its incoming and outgoing control
+/// handles, both stored as absolute positions in the node's local space.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathPoint {
+    pub anchor: Point,
+    pub handle_in: Point,
+    pub handle_out: Point,
+}
+
+impl PathPoint {
+    /// A corner point whose handles sit on the anchor.
+    pub fn corner(anchor: Point) -> Self {
+        Self {
+            anchor,
+            handle_in: anchor,
+            handle_out: anchor,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathData {
+    pub points: Vec<PathPoint>,
+    pub closed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Geometry {
+    /// Axis-aligned rectangle with its top-left corner at the local origin.
+    Rect {
+        size: Point,
+        corner_radius: f32,
+    },
+    /// Ellipse centred on the local origin.
+    Ellipse {
+        radii: Point,
+    },
+    Path(PathData),
+    /// Freehand variable-width stroke, drawn as a filled outline.
+    Brush(Vec<BrushPoint>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub geometry: Geometry,
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+}
+
+impl Shape {
+    pub fn new(geometry: Geometry) -> Self {
+        Self {
+            geometry,
+            fill: Some(Fill::Solid(Color::WHITE)),
+            stroke: None,
+        }
+    }
+}
+
+/// How a node's rendered content is composited onto what lies below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    Difference,
+    /// Additive blending.
+    Plus,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 10] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::Difference,
+        BlendMode::Plus,
+    ];
+
+    pub fn is_normal(&self) -> bool {
+        *self == BlendMode::Normal
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Normal",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Screen => "Screen",
+            BlendMode::Overlay => "Overlay",
+            BlendMode::Darken => "Darken",
+            BlendMode::Lighten => "Lighten",
+            BlendMode::ColorDodge => "Color dodge",
+            BlendMode::ColorBurn => "Color burn",
+            BlendMode::Difference => "Difference",
+            BlendMode::Plus => "Add",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NodeKind {
+    /// Top-level container shown as a row in the timeline.
+    Layer {
+        children: Vec<Node>,
+    },
+    Group {
+        children: Vec<Node>,
+    },
+    /// Top-level layer of hand-drawn frames whose children are `Drawing`s
+    /// exposed one after another.
+    CelLayer {
+        children: Vec<Node>,
+    },
+    /// One drawing of a cel layer, held on screen for `hold` frames.
+    Drawing {
+        children: Vec<Node>,
+        hold: u32,
+    },
+    Shape(Shape),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub id: NodeId,
+    pub name: String,
+    pub visible: bool,
+    pub locked: bool,
+    pub opacity: f32,
+    #[serde(default, skip_serializing_if = "BlendMode::is_normal")]
+    pub blend_mode: BlendMode,
+    pub transform: Transform,
+    pub kind: NodeKind,
+    #[serde(default, skip_serializing_if = "Animation::is_empty")]
+    pub animation: Animation,
+}
+
+impl Node {
+    pub fn children(&self) -> &[Node] {
+        match &self.kind {
+            NodeKind::Layer { children }
+            | NodeKind::Group { children }
+            | NodeKind::CelLayer { children }
+            | NodeKind::Drawing { children, .. } => children,
+            NodeKind::Shape(_) => &[],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node>> {
+        match &mut self.kind {
+            NodeKind::Layer { children }
+            | NodeKind::Group { children }
+            | NodeKind::CelLayer { children }
+            | NodeKind::Drawing { children, .. } => Some(children),
+            NodeKind::Shape(_) => None,
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        !matches!(self.kind, NodeKind::Shape(_))
+    }
+
+    pub fn is_cel_layer(&self) -> bool {
+        matches!(self.kind, NodeKind::CelLayer { .. })
+    }
+
+    pub fn shape(&self) -> Option<&Shape> {
+        match &self.kind {
+            NodeKind::Shape(shape) => Some(shape),
+            _ => None,
+        }
+    }
+
+    pub fn shape_mut(&mut self) -> Option<&mut Shape> {
+        match &mut self.kind {
+            NodeKind::Shape(shape) => Some(shape),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SceneError {
+    UnknownNode(NodeId),
+    NotAContainer(NodeId),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::UnknownNode(id) => write!(f, "no node with id {id}"),
+            SceneError::NotAContainer(id) => write!(f, "node {id} cannot contain children"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// The whole project: artboard, timing and the layer tree.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub width: u32,
+    pub height: u32,
+    pub background: Color,
+    /// Frames per second.
+    pub frame_rate: f32,
+    /// Length of the animation in frames.
+    pub duration: u32,
+    /// Top-level nodes, bottom-most first.
+    pub layers: Vec<Node>,
+    /// Editor-only onion skin settings, saved with the project.
+    #[serde(default)]
+    pub onion: OnionSkin,
+    /// Editor-only grid, guides and snapping settings, saved with the
+    /// project.
+    #[serde(default)]
+    pub snap: SnapSettings,
+    next_id: u64,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new(1920, 1080)
+    }
+}
+
+impl Document {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            background: Color::WHITE,
+            frame_rate: 24.0,
+            duration: 120,
+            layers: Vec::new(),
+            onion: OnionSkin::default(),
+            snap: SnapSettings::default(),
+            next_id: 1,
+        }
+    }
+
+    /// Hands out a fresh, never-used node ID.
+    pub fn alloc_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Builds a node with default properties and a fresh ID. The node is not
+    /// inserted; pass it to [`Document::insert`].
+    pub fn make_node(&mut self, name: impl Into<String>, kind: NodeKind) -> Node {
+        Node {
+            id: self.alloc_id(),
+            name: name.into(),
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            transform: Transform::default(),
+            kind,
+            animation: Animation::default(),
+        }
+    }
+
+    /// Appends a new empty layer on top of the stack.
+    pub fn add_layer(&mut self, name: impl Into<String>) -> NodeId {
+        let layer = self.make_node(
+            name,
+            NodeKind::Layer {
+                children: Vec::new(),
+            },
+        );
+        let id = layer.id;
+        self.layers.push(layer);
+        id
+    }
+
+    /// Appends a new cel layer with one empty drawing on top of the stack.
+    pub fn add_cel_layer(&mut self, name: impl Into<String>) -> NodeId {
+        let drawing = self.make_node(
+            "Drawing 1",
+            NodeKind::Drawing {
+                children: Vec::new(),
+                hold: crate::cel::DEFAULT_HOLD,
+            },
+        );
+        let layer = self.make_node(
+            name,
+            NodeKind::CelLayer {
+                children: vec![drawing],
+            },
+        );
+        let id = layer.id;
+        self.layers.push(layer);
+        id
+    }
+
+    /// Deep copy of `node` with fresh IDs throughout, ready to insert.
+    pub fn duplicate(&mut self, node: &Node) -> Node {
+        let mut copy = node.clone();
+        self.renumber(&mut copy);
+        copy
+    }
+
+    /// Adds a shape to the end of `parent`'s children.
+    pub fn add_shape(
+        &mut self,
+        parent: NodeId,
+        name: impl Into<String>,
+        shape: Shape,
+    ) -> Result<NodeId, SceneError> {
+        let node = self.make_node(name, NodeKind::Shape(shape));
+        let id = node.id;
+        self.insert(Some(parent), usize::MAX, node)?;
+        Ok(id)
+    }
+
+    /// Inserts `node` at `index` (clamped) under `parent`, or at the top
+    /// level when `parent` is `None`.
+    pub fn insert(
+        &mut self,
+        parent: Option<NodeId>,
+        index: usize,
+        node: Node,
+    ) -> Result<(), SceneError> {
+        self.reserve_ids(&node);
+        let siblings = self.siblings_mut(parent)?;
+        let index = index.min(siblings.len());
+        siblings.insert(index, node);
+        Ok(())
+    }
+
+    /// Detaches a node (and its subtree) from the document.
+    pub fn remove(&mut self, id: NodeId) -> Result<Node, SceneError> {
+        let (parent, index) = self.locate(id).ok_or(SceneError::UnknownNode(id))?;
+        Ok(self.siblings_mut(parent)?.remove(index))
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        fn find(nodes: &[Node], id: NodeId) -> Option<&Node> {
+            nodes.iter().find_map(|n| {
+                if n.id == id {
+                    Some(n)
+                } else {
+                    find(n.children(), id)
+                }
+            })
+        }
+        find(&self.layers, id)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        fn find(nodes: &mut [Node], id: NodeId) -> Option<&mut Node> {
+            for node in nodes {
+                if node.id == id {
+                    return Some(node);
+                }
+                if let Some(children) = node.children_mut() {
+                    if let Some(found) = find(children, id) {
+                        return Some(found);
+                    }
+                }
+            }
+            None
+        }
+        find(&mut self.layers, id)
+    }
+
+    /// Returns the parent (or `None` for top-level) and index of a node.
+    pub fn locate(&self, id: NodeId) -> Option<(Option<NodeId>, usize)> {
+        fn search(
+            nodes: &[Node],
+            parent: Option<NodeId>,
+            id: NodeId,
+        ) -> Option<(Option<NodeId>, usize)> {
+            for (index, node) in nodes.iter().enumerate() {
+                if node.id == id {
+                    return Some((parent, index));
+                }
+                if let Some(found) = search(node.children(), Some(node.id), id) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+        search(&self.layers, None, id)
+    }
+
+    pub fn parent_of(&self, id: NodeId) -> Option<NodeId> {
+        self.locate(id).and_then(|(parent, _)| parent)
+    }
+
+    /// True if `ancestor` is a strict ancestor of `id`.
+    pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = self.parent_of(id);
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+            current = self.parent_of(parent);
+        }
+        false
+    }
+
+    /// IDs from the top-level layer down to `id` itself, or empty if `id` is
+    /// not in the document.
+    pub fn ancestry(&self, id: NodeId) -> Vec<NodeId> {
+        if self.get(id).is_none() {
+            return Vec::new();
+        }
+        let mut chain = vec![id];
+        while let Some(parent) = self.parent_of(chain[chain.len() - 1]) {
+            chain.push(parent);
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Top-level layer that contains `id` (a layer is its own layer).
+    pub fn layer_of(&self, id: NodeId) -> Option<NodeId> {
+        let mut current = id;
+        loop {
+            match self.parent_of(current) {
+                Some(parent) => current = parent,
+                None => return self.get(current).map(|n| n.id),
+            }
+        }
+    }
+
+    /// Depth-first, pre-order walk over every node with its depth.
+    pub fn walk(&self) -> Vec<(usize, &Node)> {
+        fn visit<'a>(nodes: &'a [Node], depth: usize, out: &mut Vec<(usize, &'a Node)>) {
+            for node in nodes {
+                out.push((depth, node));
+                visit(node.children(), depth + 1, out);
+            }
+        }
+        let mut out = Vec::new();
+        visit(&self.layers, 0, &mut out);
+        out
+    }
+
+    pub fn frame_to_secs(&self, frame: f32) -> f32 {
+        frame / self.frame_rate
+    }
+
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> Result<&mut Vec<Node>, SceneError> {
+        match parent {
+            None => Ok(&mut self.layers),
+            Some(id) => self
+                .get_mut(id)
+                .ok_or(SceneError::UnknownNode(id))?
+                .children_mut()
+                .ok_or(SceneError::NotAContainer(id)),
+        }
+    }
+
+    fn renumber(&mut self, node: &mut Node) {
+        node.id = self.alloc_id();
+        if let Some(children) = node.children_mut() {
+            for child in children {
+                self.renumber(child);
+            }
+        }
+    }
+
+    // Nodes built elsewhere (e.g. clipboard, undo) may carry IDs from this
+    // document; make sure the allocator never hands those out again.
+    fn reserve_ids(&mut self, node: &Node) {
+        self.next_id = self.next_id.max(node.id.0 + 1);
+        for child in node.children() {
+            self.reserve_ids(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect() -> Shape {
+        Shape::new(Geometry::Rect {
+            size: Point::new(10.0, 10.0),
+            corner_radius: 0.0,
+        })
+    }
+
+    fn group(doc: &mut Document, parent: NodeId, name: &str) -> NodeId {
+        let node = doc.make_node(
+            name,
+            NodeKind::Group {
+                children: Vec::new(),
+            },
+        );
+        let id = node.id;
+        doc.insert(Some(parent), usize::MAX, node).unwrap();
+        id
+    }
+
+    fn names(doc: &Document) -> Vec<(usize, &str)> {
+        doc.walk()
+            .into_iter()
+            .map(|(depth, node)| (depth, node.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn ids_survive_reordering_and_reparenting() {
+        let mut doc = Document::new(100, 100);
+        let a = doc.add_layer("A");
+        let b = doc.add_layer("B");
+        let shape = doc.add_shape(a, "Shape", rect()).unwrap();
+
+        let node = doc.remove(shape).unwrap();
+        doc.insert(Some(b), 0, node).unwrap();
+        let layer = doc.remove(a).unwrap();
+        doc.insert(None, usize::MAX, layer).unwrap();
+        assert_eq!(doc.get(shape).unwrap().name, "Shape");
+        assert_eq!(doc.parent_of(shape), Some(b));
+        assert_eq!(doc.get(a).unwrap().name, "A");
+        assert_eq!(doc.locate(a), Some((None, 1)));
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let first = doc.add_shape(layer, "First", rect()).unwrap();
+        doc.remove(first).unwrap();
+        let second = doc.add_shape(layer, "Second", rect()).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn insert_reserves_foreign_ids() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let mut node = doc.make_node("Pasted", NodeKind::Shape(rect()));
+        node.id = NodeId(50);
+        doc.insert(Some(layer), 0, node).unwrap();
+        assert!(doc.alloc_id() > NodeId(50));
+    }
+
+    #[test]
+    fn duplicate_renumbers_the_subtree() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let outer = group(&mut doc, layer, "Group");
+        let inner = doc.add_shape(outer, "Shape", rect()).unwrap();
+
+        let original = doc.get(outer).unwrap().clone();
+        let copy = doc.duplicate(&original);
+        assert_ne!(copy.id, outer);
+        assert_ne!(copy.children()[0].id, inner);
+        doc.insert(Some(layer), usize::MAX, copy).unwrap();
+        assert_eq!(doc.get(layer).unwrap().children().len(), 2);
+    }
+
+    #[test]
+    fn insert_clamps_the_index_and_remove_detaches() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let a = doc.add_shape(layer, "A", rect()).unwrap();
+        let b = doc.make_node("B", NodeKind::Shape(rect()));
+        let b_id = b.id;
+        doc.insert(Some(layer), 0, b).unwrap();
+        assert_eq!(doc.locate(b_id), Some((Some(layer), 0)));
+        assert_eq!(doc.locate(a), Some((Some(layer), 1)));
+
+        let removed = doc.remove(a).unwrap();
+        assert_eq!(removed.id, a);
+        assert!(doc.get(a).is_none());
+        assert_eq!(doc.remove(a), Err(SceneError::UnknownNode(a)));
+    }
+
+    #[test]
+    fn insert_into_a_shape_fails() {
+        let mut doc = Document::new(100, 100);
+        let layer = doc.add_layer("Layer");
+        let shape = doc.add_shape(layer, "Shape", rect()).unwrap();
+        let node = doc.make_node("Child", NodeKind::Shape(rect()));
+        assert_eq!(
+            doc.insert(Some(shape), 0, node),
+            Err(SceneError::NotAContainer(shape))
+        );
+    }
+
+    #[test]
+    fn walk_is_depth_first_pre_order() {
+        let mut doc = Document::new(100, 100);
+        let bottom = doc.add_layer("Bottom");
+        let top = doc.add_layer("Top");
+        let outer = group(&mut doc, bottom, "Group");
+        doc.add_shape(outer, "Inner", rect()).unwrap();
+        doc.add_shape(bottom, "Loose", rect()).unwrap();
+        doc.add_shape(top, "Upper", rect()).unwrap();
+
+        assert_eq!(
+            names(&doc),
+            [
+                (0, "Bottom"),
+                (1, "Group"),
+                (2, "Inner"),
+                (1, "Loose"),
+                (0, "Top"),
+                (1, "Upper"),
+            ]
+        );
+        let inner = doc.walk()[2].1.id;
+        assert_eq!(doc.ancestry(inner), [bottom, outer, inner]);
+    }
+
+    #[test]
+    fn point_rotation_matches_transforms() {
+        let v = Point::new(3.0, 1.0);
+        let turned = v.rotate(90.0);
+        assert!(turned.distance(Point::new(-1.0, 3.0)) < 1e-5);
+        let transform = Transform {
+            rotation: 37.0,
+            ..Transform::default()
+        };
+        assert!(v.rotate(37.0).distance(transform.apply(v)) < 1e-5);
+    }
+}
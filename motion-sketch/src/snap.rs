@@ -0,0 +1,454 @@
+// Grid, guides and snapping.
+//
+// The grid, the guides and the snapping switches are part of the document
+// so they are saved with the project, like the onion skin; exports never
+// show them. A tool builds a `Snapper` when a drag starts. It collects the
+// lines and points a dragged position may stick to, leaving out whatever is
+// being dragged, and pulls positions onto the nearest of them within a few
+// screen pixels. Every snap reports `Indicator`s for the tool to draw, so
+// the user sees what a position stuck to.
+
+use serde::{Deserialize, Serialize};
+
+use crate::animation;
+use crate::cel;
+use crate::render;
+use crate::scene::{Document, NodeId, Point};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridKind {
+    #[default]
+    Square,
+    /// Triangular lattice of vertical lines and lines at +-30 degrees.
+    Isometric,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    pub visible: bool,
+    pub kind: GridKind,
+    /// Square cell size, or the vertical distance between isometric
+    /// lattice points, in document units.
+    pub spacing: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            kind: GridKind::Square,
+            spacing: 20.0,
+        }
+    }
+}
+
+impl Grid {
+    /// Distance between vertical grid lines.
+    pub fn column_width(&self) -> f32 {
+        match self.kind {
+            GridKind::Square => self.spacing,
+            GridKind::Isometric => self.spacing * 3f32.sqrt() / 2.0,
+        }
+    }
+
+    /// Lattice point nearest to `p`. Odd isometric columns are offset by
+    /// half the spacing.
+    pub fn nearest_point(&self, p: Point) -> Point {
+        let s = self.spacing;
+        let w = self.column_width();
+        let round = |v: f32, step: f32, offset: f32| ((v - offset) / step).round() * step + offset;
+        match self.kind {
+            GridKind::Square => Point::new(round(p.x, s, 0.0), round(p.y, s, 0.0)),
+            GridKind::Isometric => {
+                let column = (p.x / w).round();
+                [column - 1.0, column, column + 1.0]
+                    .map(|k| {
+                        let offset = if k.rem_euclid(2.0) == 1.0 {
+                            s / 2.0
+                        } else {
+                            0.0
+                        };
+                        Point::new(k * w, round(p.y, s, offset))
+                    })
+                    .into_iter()
+                    .min_by(|a, b| a.distance(p).total_cmp(&b.distance(p)))
+                    .unwrap_or(p)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    /// A vertical line at an x position.
+    Vertical,
+    /// A horizontal line at a y position.
+    Horizontal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Guide {
+    pub axis: Axis,
+    /// x of a vertical guide or y of a horizontal one, in document units.
+    pub position: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapSettings {
+    /// Master switch for every kind of snapping below.
+    pub enabled: bool,
+    pub to_grid: bool,
+    pub to_guides: bool,
+    /// Edges and centres of other objects' bounds, and of the artboard.
+    pub to_objects: bool,
+    pub to_anchors: bool,
+    /// Snap distance in screen pixels.
+    pub tolerance: f32,
+    pub grid: Grid,
+    pub guides: Vec<Guide>,
+    pub rulers: bool,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            to_grid: false,
+            to_guides: true,
+            to_objects: true,
+            to_anchors: true,
+            tolerance: 8.0,
+            grid: Grid::default(),
+            guides: Vec::new(),
+            rulers: true,
+        }
+    }
+}
+
+/// What a position snapped to, in document coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indicator {
+    Vertical(f32),
+    Horizontal(f32),
+    Point(Point),
+}
+
+/// Snap targets gathered from a document for one drag. The default
+/// snapper leaves every position alone.
+#[derive(Clone, Debug, Default)]
+pub struct Snapper {
+    /// Snap distance in document units.
+    tolerance: f32,
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    points: Vec<Point>,
+    grid: Option<Grid>,
+}
+
+impl Snapper {
+    /// Targets in `doc` at `frame` for a view where one screen pixel is
+    /// `pixel_size` document units. Nodes in `exclude` and their
+    /// descendants are not targets.
+    pub fn new(doc: &Document, frame: f32, pixel_size: f32, exclude: &[NodeId]) -> Self {
+        let settings = &doc.snap;
+        if !settings.enabled {
+            return Snapper::default();
+        }
+        let mut snapper = Snapper {
+            tolerance: settings.tolerance * pixel_size,
+            ..Snapper::default()
+        };
+        if settings.to_grid {
+            snapper.grid = Some(settings.grid);
+        }
+        if settings.to_guides {
+            for guide in &settings.guides {
+                match guide.axis {
+                    Axis::Vertical => snapper.xs.push(guide.position),
+                    Axis::Horizontal => snapper.ys.push(guide.position),
+                }
+            }
+        }
+        if settings.to_objects {
+            let size = Point::new(doc.width as f32, doc.height as f32);
+            snapper.add_box(Point::ZERO, size);
+        }
+
+        let excluded = |id: NodeId| {
+            exclude
+                .iter()
+                .any(|&other| other == id || doc.is_ancestor(other, id))
+        };
+        for (_, node) in doc.walk() {
+            if node.shape().is_none()
+                || excluded(node.id)
+                || !is_shown(doc, node.id)
+                || !cel::is_exposed(doc, node.id, frame)
+            {
+                continue;
+            }
+            if settings.to_objects {
+                let corners = render::document_corners(doc, node.id, frame);
+                if let Some((min, max)) = bounds(&corners) {
+                    snapper.add_box(min, max);
+                }
+            }
+            if settings.to_anchors {
+                if let Some(points) = animation::sample(node, frame).path_points {
+                    snapper.points.extend(
+                        points
+                            .iter()
+                            .map(|p| animation::to_document(doc, node.id, frame, p.anchor)),
+                    );
+                }
+            }
+        }
+        snapper
+    }
+
+    /// Adds extra point targets, such as the handles of the box being
+    /// edited.
+    pub fn with_points(mut self, points: impl IntoIterator<Item = Point>) -> Self {
+        self.points.extend(points);
+        self
+    }
+
+    /// Edges, centre lines, corners and centre of an axis-aligned box.
+    fn add_box(&mut self, min: Point, max: Point) {
+        let center = (min + max) * 0.5;
+        self.xs.extend([min.x, center.x, max.x]);
+        self.ys.extend([min.y, center.y, max.y]);
+        self.points.extend([
+            min,
+            Point::new(max.x, min.y),
+            max,
+            Point::new(min.x, max.y),
+            center,
+        ]);
+    }
+
+    /// Pulls `p` onto the nearest point target, or else onto the nearest
+    /// line in x and in y independently.
+    pub fn snap_point(&self, p: Point) -> (Point, Vec<Indicator>) {
+        let lattice = self
+            .grid
+            .filter(|grid| grid.kind == GridKind::Isometric)
+            .map(|grid| grid.nearest_point(p));
+        let nearest = self
+            .points
+            .iter()
+            .copied()
+            .chain(lattice)
+            .map(|target| (target.distance(p), target))
+            .filter(|(distance, _)| *distance < self.tolerance)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((_, target)) = nearest {
+            return (target, vec![Indicator::Point(target)]);
+        }
+
+        let mut snapped = p;
+        let mut indicators = Vec::new();
+        if let Some(x) = self.nearest_x(&[p.x]).map(|(_, x)| x) {
+            snapped.x = x;
+            indicators.push(Indicator::Vertical(x));
+        }
+        if let Some(y) = self.nearest_y(&[p.y]).map(|(_, y)| y) {
+            snapped.y = y;
+            indicators.push(Indicator::Horizontal(y));
+        }
+        (snapped, indicators)
+    }
+
+    /// Offset that brings an edge or the centre line of the box
+    /// `min`..`max` onto the nearest line in x and in y.
+    pub fn snap_box(&self, min: Point, max: Point) -> (Point, Vec<Indicator>) {
+        let center = (min + max) * 0.5;
+        let mut offset = Point::ZERO;
+        let mut indicators = Vec::new();
+        if let Some((from, x)) = self.nearest_x(&[min.x, center.x, max.x]) {
+            offset.x = x - from;
+            indicators.push(Indicator::Vertical(x));
+        }
+        if let Some((from, y)) = self.nearest_y(&[min.y, center.y, max.y]) {
+            offset.y = y - from;
+            indicators.push(Indicator::Horizontal(y));
+        }
+        (offset, indicators)
+    }
+
+    /// The closest pair of one of `values` and a vertical line, as
+    /// (value, line).
+    fn nearest_x(&self, values: &[f32]) -> Option<(f32, f32)> {
+        let step = self.grid.map(|grid| grid.column_width());
+        self.nearest(values, &self.xs, step)
+    }
+
+    fn nearest_y(&self, values: &[f32]) -> Option<(f32, f32)> {
+        // Isometric rows are reached through lattice points instead.
+        let step = self
+            .grid
+            .filter(|grid| grid.kind == GridKind::Square)
+            .map(|grid| grid.spacing);
+        self.nearest(values, &self.ys, step)
+    }
+
+    fn nearest(&self, values: &[f32], lines: &[f32], step: Option<f32>) -> Option<(f32, f32)> {
+        values
+            .iter()
+            .flat_map(|&value| {
+                let grid_line = step
+                    .filter(|step| *step > 0.0)
+                    .map(|step| (value / step).round() * step);
+                lines
+                    .iter()
+                    .copied()
+                    .chain(grid_line)
+                    .map(move |line| (value, line))
+            })
+            .filter(|(value, line)| (line - value).abs() < self.tolerance)
+            .min_by(|a, b| (a.1 - a.0).abs().total_cmp(&(b.1 - b.0).abs()))
+    }
+}
+
+/// Smallest axis-aligned box around `points`.
+pub fn bounds(points: &[Point]) -> Option<(Point, Point)> {
+    let first = points.first()?;
+    let (mut min, mut max) = (*first, *first);
+    for p in points {
+        min = Point::new(min.x.min(p.x), min.y.min(p.y));
+        max = Point::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    Some((min, max))
+}
+
+/// True if the node and all its ancestors are visible.
+fn is_shown(doc: &Document, id: NodeId) -> bool {
+    doc.ancestry(id)
+        .into_iter()
+        .filter_map(|id| doc.get(id))
+        .all(|node| node.visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Geometry, Shape};
+
+    /// A 100x100 document snapping to nothing but what a test turns on.
+    fn document() -> Document {
+        let mut doc = Document::new(100, 100);
+        doc.snap = SnapSettings {
+            to_guides: false,
+            to_objects: false,
+            to_anchors: false,
+            ..SnapSettings::default()
+        };
+        doc
+    }
+
+    fn guide(doc: &mut Document, axis: Axis, position: f32) {
+        doc.snap.to_guides = true;
+        doc.snap.guides.push(Guide { axis, position });
+    }
+
+    #[test]
+    fn tolerance_is_in_screen_pixels() {
+        let mut doc = document();
+        guide(&mut doc, Axis::Vertical, 50.0);
+        let p = Point::new(56.0, 20.0);
+        let snap =
+            |doc: &Document, pixel_size: f32| Snapper::new(doc, 0.0, pixel_size, &[]).snap_point(p);
+
+        assert_eq!(
+            snap(&doc, 1.0),
+            (Point::new(50.0, 20.0), vec![Indicator::Vertical(50.0)])
+        );
+        // Zoomed in, six document units are twelve screen pixels.
+        assert_eq!(snap(&doc, 0.5), (p, Vec::new()));
+        doc.snap.enabled = false;
+        assert_eq!(snap(&doc, 1.0), (p, Vec::new()));
+    }
+
+    #[test]
+    fn points_win_over_closer_lines() {
+        let mut doc = document();
+        doc.snap.to_objects = true;
+        guide(&mut doc, Axis::Vertical, 51.0);
+        let snapper = Snapper::new(&doc, 0.0, 1.0, &[]);
+
+        // The artboard centre is further away than the guide.
+        let (p, indicators) = snapper.snap_point(Point::new(51.5, 47.0));
+        assert_eq!(p, Point::new(50.0, 50.0));
+        assert_eq!(indicators, [Indicator::Point(p)]);
+
+        let (p, indicators) = snapper.snap_point(Point::new(51.5, 30.0));
+        assert_eq!(p, Point::new(51.0, 30.0));
+        assert_eq!(indicators, [Indicator::Vertical(51.0)]);
+    }
+
+    #[test]
+    fn isometric_lattice_offsets_odd_columns() {
+        let grid = Grid {
+            kind: GridKind::Isometric,
+            ..Grid::default()
+        };
+        let w = grid.column_width();
+        assert!((w - 17.320_51).abs() < 1e-4);
+        assert_eq!(
+            grid.nearest_point(Point::new(17.0, 11.0)),
+            Point::new(w, 10.0)
+        );
+        assert_eq!(
+            grid.nearest_point(Point::new(34.0, 21.0)),
+            Point::new(2.0 * w, 20.0)
+        );
+
+        let mut doc = document();
+        doc.snap.to_grid = true;
+        doc.snap.grid = grid;
+        let snapper = Snapper::new(&doc, 0.0, 1.0, &[]);
+        let (p, _) = snapper.snap_point(Point::new(-18.0, -11.0));
+        assert_eq!(p, Point::new(-w, -10.0));
+        // Away from lattice points only the vertical lines pull.
+        let (p, indicators) = snapper.snap_point(Point::new(25.0, 1.0));
+        assert_eq!(p, Point::new(w, 1.0));
+        assert_eq!(indicators, [Indicator::Vertical(w)]);
+    }
+
+    #[test]
+    fn excluded_nodes_are_not_targets() {
+        let mut doc = document();
+        doc.snap.to_objects = true;
+        let layer = doc.add_layer("Layer");
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(20.0, 20.0),
+            corner_radius: 0.0,
+        });
+        let id = doc.add_shape(layer, "Rect", shape).unwrap();
+        doc.get_mut(id).unwrap().transform.position = Point::new(30.0, 30.0);
+        let p = Point::new(31.0, 31.0);
+
+        let snapper = Snapper::new(&doc, 0.0, 1.0, &[]);
+        assert_eq!(snapper.snap_point(p).0, Point::new(30.0, 30.0));
+        for exclude in [id, layer] {
+            let snapper = Snapper::new(&doc, 0.0, 1.0, &[exclude]);
+            assert_eq!(snapper.snap_point(p), (p, Vec::new()));
+        }
+    }
+
+    #[test]
+    fn boxes_snap_by_their_nearest_edge_or_centre() {
+        let mut doc = document();
+        guide(&mut doc, Axis::Vertical, 50.0);
+        guide(&mut doc, Axis::Horizontal, 10.0);
+        let snapper = Snapper::new(&doc, 0.0, 1.0, &[]);
+        let (offset, indicators) = snapper.snap_box(Point::new(42.0, 13.0), Point::new(56.0, 33.0));
+        assert_eq!(offset, Point::new(1.0, -3.0));
+        assert_eq!(
+            indicators,
+            [Indicator::Vertical(50.0), Indicator::Horizontal(10.0)]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+// Freehand stroke processing.
+//
+// Raw pointer samples go through three steps before they become a brush
+// shape: samples closer than a minimum distance are dropped, the rest are
+// smoothed with a moving average, and the result is simplified with
+// Ramer-Douglas-Peucker. When drawn, the sparse points are interpolated
+// with a Catmull-Rom spline and offset by the per-point width into a closed
+// outline that is filled.
+
+use serde::{Deserialize, Serialize};
+
+use crate::scene::Point;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrushPoint {
+    pub position: Point,
+    /// Full stroke width at this point.
+    pub width: f32,
+}
+
+/// Appends `p` unless it is closer than `min_distance` to the last sample.
+/// Returns `true` if the sample was kept.
+pub fn push_sample(samples: &mut Vec<Point>, p: Point, min_distance: f32) -> bool {
+    if let Some(last) = samples.last() {
+        if last.distance(p) < min_distance {
+            return false;
+        }
+    }
+    samples.push(p);
+    true
+}
+
+/// Centred moving average over `radius` neighbours on each side. The end
+/// points are kept in place so the stroke still starts and ends where the
+/// pointer did.
+pub fn smooth(points: &[Point], radius: usize) -> Vec<Point> {
+    if radius == 0 || points.len() < 3 {
+        return points.to_vec();
+    }
+    let last = points.len() - 1;
+    (0..points.len())
+        .map(|i| {
+            if i == 0 || i == last {
+                return points[i];
+            }
+            // Shrink the window near the ends so it stays symmetric.
+            let r = radius.min(i).min(last - i);
+            let window = &points[i - r..=i + r];
+            let sum = window.iter().fold(Point::ZERO, |acc, p| acc + *p);
+            sum * (1.0 / window.len() as f32)
+        })
+        .collect()
+}
+
+/// Assigns a width to every point. Quick movements (long distances between
+/// consecutive samples) thin the line, and both ends taper off.
+pub fn widths(points: &[Point], size: f32, thinning: f32, taper: f32) -> Vec<BrushPoint> {
+    let mut lengths = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    for (i, p) in points.iter().enumerate() {
+        if i > 0 {
+            total += points[i - 1].distance(*p);
+        }
+        lengths.push(total);
+    }
+
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let step = if i > 0 {
+                points[i - 1].distance(*p)
+            } else {
+                0.0
+            };
+            let speed = (step / (size * 2.0).max(f32::EPSILON)).min(1.0);
+            let mut width = size * (1.0 - thinning.clamp(0.0, 1.0) * speed);
+
+            if taper > 0.0 {
+                let from_end = lengths[i].min(total - lengths[i]);
+                let t = (from_end / taper).min(1.0);
+                width *= 0.35 + 0.65 * t;
+            }
+            BrushPoint {
+                position: *p,
+                width,
+            }
+        })
+        .collect()
+}
+
+/// Ramer-Douglas-Peucker simplification: drops points that deviate less
+/// than `tolerance` from the line between the points that are kept.
+pub fn simplify(points: &[BrushPoint], tolerance: f32) -> Vec<BrushPoint> {
+    if points.len() < 3 || tolerance <= 0.0 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let (a, b) = (points[start].position, points[end].position);
+        let farthest = (start + 1..end)
+            .map(|i| (i, segment_distance(points[i].position, a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                stack.push((start, index));
+                stack.push((index, end));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect()
+}
+
+/// Distance from `p` to the segment `a`-`b`.
+pub fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+    let ab = b - a;
+    let length_sq = ab.x * ab.x + ab.y * ab.y;
+    if length_sq == 0.0 {
+        return p.distance(a);
+    }
+    let t = (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / length_sq).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+/// Interpolates the points with a uniform Catmull-Rom spline, emitting
+/// `steps` points per segment. Widths are interpolated linearly.
+pub fn catmull_rom(points: &[BrushPoint], steps: usize) -> Vec<BrushPoint> {
+    if points.len() < 3 || steps < 2 {
+        return points.to_vec();
+    }
+    let at = |i: isize| points[i.clamp(0, points.len() as isize - 1) as usize];
+    let mut out = Vec::with_capacity((points.len() - 1) * steps + 1);
+    for i in 0..points.len() as isize - 1 {
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        for step in 0..steps {
+            let t = step as f32 / steps as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let blend = |a: f32, b: f32, c: f32, d: f32| {
+                0.5 * (2.0 * b
+                    + (c - a) * t
+                    + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+                    + (3.0 * b - a - 3.0 * c + d) * t3)
+            };
+            out.push(BrushPoint {
+                position: Point::new(
+                    blend(p0.position.x, p1.position.x, p2.position.x, p3.position.x),
+                    blend(p0.position.y, p1.position.y, p2.position.y, p3.position.y),
+                ),
+                width: p1.width + (p2.width - p1.width) * t,
+            });
+        }
+    }
+    out.push(points[points.len() - 1]);
+    out
+}
+
+/// Closed outline polygon of a variable-width stroke with round caps.
+pub fn outline(points: &[BrushPoint]) -> Vec<Point> {
+    const CAP_STEPS: usize = 8;
+    let dense = catmull_rom(points, 6);
+    match dense.len() {
+        0 => return Vec::new(),
+        1 => return circle(dense[0].position, dense[0].width / 2.0, CAP_STEPS * 2),
+        _ => {}
+    }
+
+    let last = dense.len() - 1;
+    let normals: Vec<Point> = (0..dense.len())
+        .map(|i| {
+            let d = dense[(i + 1).min(last)].position - dense[i.saturating_sub(1)].position;
+            let length = d.length();
+            if length == 0.0 {
+                Point::new(0.0, -1.0)
+            } else {
+                Point::new(-d.y / length, d.x / length)
+            }
+        })
+        .collect();
+
+    let mut polygon = Vec::with_capacity(dense.len() * 2 + CAP_STEPS * 2);
+    for (p, n) in dense.iter().zip(&normals) {
+        polygon.push(p.position + *n * (p.width / 2.0));
+    }
+    polygon.extend(cap(dense[last], normals[last], CAP_STEPS));
+    for (p, n) in dense.iter().zip(&normals).rev() {
+        polygon.push(p.position - *n * (p.width / 2.0));
+    }
+    polygon.extend(cap(dense[0], normals[0] * -1.0, CAP_STEPS));
+    polygon
+}
+
+// Half circle from the `normal` side round to the opposite side, bulging
+// away from the stroke.
+fn cap(point: BrushPoint, normal: Point, steps: usize) -> impl Iterator<Item = Point> {
+    let radius = point.width / 2.0;
+    let start = normal.y.atan2(normal.x);
+    (1..steps).map(move |i| {
+        let angle = start - std::f32::consts::PI * i as f32 / steps as f32;
+        point.position + Point::new(angle.cos(), angle.sin()) * radius
+    })
+}
+
+fn circle(center: Point, radius: f32, steps: usize) -> Vec<Point> {
+    (0..steps)
+        .map(|i| {
+            let angle = std::f32::consts::TAU * i as f32 / steps as f32;
+            center + Point::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brush(points: &[(f32, f32)], width: f32) -> Vec<BrushPoint> {
+        points
+            .iter()
+            .map(|&(x, y)| BrushPoint {
+                position: Point::new(x, y),
+                width,
+            })
+            .collect()
+    }
+
+    fn positions(points: &[BrushPoint]) -> Vec<Point> {
+        points.iter().map(|p| p.position).collect()
+    }
+
+    fn close(a: Point, b: Point) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    #[test]
+    fn samples_closer_than_the_minimum_are_dropped() {
+        let mut samples = Vec::new();
+        assert!(push_sample(&mut samples, Point::ZERO, 2.0));
+        assert!(!push_sample(&mut samples, Point::new(1.0, 1.0), 2.0));
+        assert!(push_sample(&mut samples, Point::new(2.0, 0.0), 2.0));
+        assert_eq!(samples, [Point::ZERO, Point::new(2.0, 0.0)]);
+    }
+
+    #[test]
+    fn smoothing_averages_inner_points_and_keeps_the_ends() {
+        let zigzag: Vec<Point> = [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0), (4.0, 0.0)]
+            .map(|(x, y)| Point::new(x, y))
+            .into();
+        let smoothed = smooth(&zigzag, 1);
+        assert_eq!(smoothed[0], zigzag[0]);
+        assert_eq!(smoothed[4], zigzag[4]);
+        assert!(close(smoothed[1], Point::new(1.0, 1.0 / 3.0)));
+        assert!(close(smoothed[2], Point::new(2.0, 2.0 / 3.0)));
+
+        // A wide radius shrinks near the ends instead of pulling them in.
+        let line: Vec<Point> = (0..5).map(|i| Point::new(i as f32, 0.0)).collect();
+        assert_eq!(smooth(&line, 10), line);
+        assert_eq!(smooth(&zigzag, 0), zigzag);
+    }
+
+    #[test]
+    fn fast_movement_thins_the_stroke() {
+        let points = [Point::ZERO, Point::new(5.0, 0.0), Point::new(25.0, 0.0)];
+        let widths: Vec<f32> = widths(&points, 10.0, 0.5, 0.0)
+            .iter()
+            .map(|p| p.width)
+            .collect();
+        // Speed is the step over twice the size: 0, 0.25 and 1.
+        assert_eq!(widths, [10.0, 8.75, 5.0]);
+    }
+
+    #[test]
+    fn ends_taper_over_the_taper_length() {
+        let points: Vec<Point> = (0..=10).map(|i| Point::new(i as f32 * 10.0, 0.0)).collect();
+        let widths: Vec<f32> = widths(&points, 10.0, 0.0, 20.0)
+            .iter()
+            .map(|p| p.width)
+            .collect();
+        assert!((widths[0] - 3.5).abs() < 1e-5);
+        assert!((widths[1] - 6.75).abs() < 1e-5);
+        assert!(widths[2..=8].iter().all(|w| *w == 10.0));
+        assert!((widths[9] - 6.75).abs() < 1e-5);
+        assert!((widths[10] - 3.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn simplify_drops_points_within_tolerance() {
+        let points = brush(
+            &[
+                (0.0, 0.0),
+                (5.0, 0.5),
+                (10.0, 0.0),
+                (10.0, 5.0),
+                (10.0, 10.0),
+            ],
+            2.0,
+        );
+        let kept = positions(&simplify(&points, 1.0));
+        assert_eq!(
+            kept,
+            [Point::ZERO, Point::new(10.0, 0.0), Point::new(10.0, 10.0)]
+        );
+        // A tighter tolerance keeps the small bump.
+        assert_eq!(simplify(&points, 0.25).len(), 4);
+        assert_eq!(simplify(&points, 0.0), points);
+    }
+
+    #[test]
+    fn segment_distance_clamps_to_the_ends() {
+        let (a, b) = (Point::ZERO, Point::new(10.0, 0.0));
+        assert_eq!(segment_distance(Point::new(5.0, 3.0), a, b), 3.0);
+        assert_eq!(segment_distance(Point::new(-3.0, 4.0), a, b), 5.0);
+        assert_eq!(segment_distance(Point::new(1.0, 1.0), a, a), 2f32.sqrt());
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_the_points() {
+        let mut points = brush(&[(0.0, 0.0), (10.0, 10.0), (20.0, 0.0), (30.0, 10.0)], 2.0);
+        points[1].width = 4.0;
+        let dense = catmull_rom(&points, 4);
+        assert_eq!(dense.len(), 3 * 4 + 1);
+        for (i, point) in points.iter().enumerate() {
+            assert!(close(dense[i * 4].position, point.position));
+            assert_eq!(dense[i * 4].width, point.width);
+        }
+        // Widths are interpolated linearly between the points.
+        assert_eq!(dense[2].width, 3.0);
+    }
+
+    #[test]
+    fn catmull_rom_keeps_straight_lines_straight() {
+        let points = brush(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)], 1.0);
+        let dense = catmull_rom(&points, 5);
+        assert!(dense.iter().all(|p| p.position.y == 0.0));
+        assert!(dense.windows(2).all(|w| w[0].position.x < w[1].position.x));
+    }
+
+    #[test]
+    fn outline_surrounds_the_stroke_at_half_width() {
+        let points = brush(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)], 4.0);
+        let polygon = outline(&points);
+        let dense = catmull_rom(&points, 6).len();
+        assert_eq!(polygon.len(), dense * 2 + 2 * 7);
+        let (a, b) = (Point::ZERO, Point::new(20.0, 0.0));
+        for p in &polygon {
+            assert!((segment_distance(*p, a, b) - 2.0).abs() < 1e-4, "{p:?}");
+        }
+        // The caps reach past both ends.
+        let min_x = polygon.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let max_x = polygon.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        assert!(min_x < -1.9 && max_x > 21.9);
+    }
+
+    #[test]
+    fn outline_of_a_dot_is_a_circle() {
+        let polygon = outline(&brush(&[(5.0, 5.0)], 6.0));
+        assert_eq!(polygon.len(), 16);
+        assert!(polygon
+            .iter()
+            .all(|p| (p.distance(Point::new(5.0, 5.0)) - 3.0).abs() < 1e-4));
+        assert!(outline(&[]).is_empty());
+    }
+}
@@ -0,0 +1,493 @@
+// SVG export.
+//
+// Writes one frame of the document as a standalone SVG file. Nodes map to
+// SVG elements one to one: containers become `<g>` groups carrying the
+// node's transform, opacity and blend mode, and shapes become `<rect>`,
+// `<ellipse>` or `<path>` elements, preceded by their gradient if they
+// have one. Animated properties are sampled at the exported frame, so the
+// result is static artwork. The writer is hand rolled rather than going
+// through skia's SVG canvas so that shapes stay editable elements instead
+// of flattened paths, and the output is byte-for-byte stable for a given
+// document and frame.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::animation::{self, PropertyValues};
+use crate::cel;
+use crate::scene::{
+    BlendMode, Color, Document, Fill, Geometry, LineCap, LineJoin, Node, NodeKind, PathPoint,
+    Shape, Transform,
+};
+use crate::stroke;
+
+/// SVG markup for `doc` at `frame`. `scale` sizes the image relative to
+/// the artboard; the view box always spans the artboard.
+pub fn export(doc: &Document, frame: f32, scale: f32) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        num(doc.width as f32 * scale),
+        num(doc.height as f32 * scale),
+        doc.width,
+        doc.height,
+    );
+    let _ = writeln!(
+        out,
+        r#"  <rect width="{}" height="{}"{}/>"#,
+        doc.width,
+        doc.height,
+        paint_attrs("fill", doc.background),
+    );
+    for layer in &doc.layers {
+        write_node(&mut out, layer, frame, 1);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Writes `doc` at `frame` to `path`.
+pub fn save(doc: &Document, frame: f32, scale: f32, path: &Path) -> Result<()> {
+    fs::write(path, export(doc, frame, scale))
+        .with_context(|| format!("cannot write {}", path.display()))
+}
+
+fn write_node(out: &mut String, node: &Node, frame: f32, depth: usize) {
+    let values = animation::sample(node, frame);
+    if !node.visible || values.opacity <= 0.0 {
+        return;
+    }
+    let indent = "  ".repeat(depth);
+    let mut attrs = format!(r#" id="n{}" data-name="{}""#, node.id.0, escape(&node.name));
+    let transform = transform_attr(&values.transform(&node.transform));
+    if !transform.is_empty() {
+        let _ = write!(attrs, r#" transform="{transform}""#);
+    }
+    if values.opacity < 1.0 {
+        let _ = write!(attrs, r#" opacity="{}""#, num(values.opacity));
+    }
+    if let Some(mode) = css_blend_mode(node.blend_mode) {
+        let _ = write!(attrs, r#" style="mix-blend-mode:{mode}""#);
+    }
+
+    let children: Vec<&Node> = match &node.kind {
+        NodeKind::Shape(shape) => {
+            let paint_server = format!("p{}", node.id.0);
+            if let Some(gradient) = shape.fill.as_ref().and_then(|f| gradient(f, &paint_server)) {
+                let _ = writeln!(out, "{indent}{gradient}");
+            }
+            let element = shape_element(shape, &values, &attrs, &paint_server);
+            let _ = writeln!(out, "{indent}{element}");
+            return;
+        }
+        NodeKind::CelLayer { children } => cel::exposed_index(node, frame)
+            .map(|index| vec![&children[index]])
+            .unwrap_or_default(),
+        _ => node.children().iter().collect(),
+    };
+    if children.is_empty() {
+        let _ = writeln!(out, "{indent}<g{attrs}/>");
+        return;
+    }
+    let _ = writeln!(out, "{indent}<g{attrs}>");
+    for child in children {
+        write_node(out, child, frame, depth + 1);
+    }
+    let _ = writeln!(out, "{indent}</g>");
+}
+
+/// A `<linearGradient>` or `<radialGradient>` element with id `id` for a
+/// gradient fill. Coordinates are in the user space of the shape using it,
+/// which is the shape's local space.
+fn gradient(fill: &Fill, id: &str) -> Option<String> {
+    let (element, geometry, stops) = match fill {
+        Fill::Solid(_) => return None,
+        Fill::LinearGradient { start, end, stops } => (
+            "linearGradient",
+            format!(
+                r#"x1="{}" y1="{}" x2="{}" y2="{}""#,
+                num(start.x),
+                num(start.y),
+                num(end.x),
+                num(end.y)
+            ),
+            stops,
+        ),
+        Fill::RadialGradient {
+            center,
+            radius,
+            stops,
+        } => (
+            "radialGradient",
+            format!(
+                r#"cx="{}" cy="{}" r="{}""#,
+                num(center.x),
+                num(center.y),
+                num(*radius)
+            ),
+            stops,
+        ),
+    };
+    let mut out = format!(r#"<{element} id="{id}" gradientUnits="userSpaceOnUse" {geometry}>"#);
+    for stop in stops {
+        let [r, g, b, _] = stop.color.to_rgba8();
+        let _ = write!(
+            out,
+            r##"<stop offset="{}" stop-color="#{r:02x}{g:02x}{b:02x}""##,
+            num(stop.offset)
+        );
+        if stop.color.a < 1.0 {
+            let _ = write!(out, r#" stop-opacity="{}""#, num(stop.color.a.max(0.0)));
+        }
+        out.push_str("/>");
+    }
+    let _ = write!(out, "</{element}>");
+    Some(out)
+}
+
+fn shape_element(
+    shape: &Shape,
+    values: &PropertyValues,
+    attrs: &str,
+    paint_server: &str,
+) -> String {
+    let mut paint = String::new();
+    match &shape.fill {
+        Some(Fill::Solid(color)) => {
+            paint += &paint_attrs("fill", values.fill_color.unwrap_or(*color));
+        }
+        Some(_) => {
+            let _ = write!(paint, r#" fill="url(#{paint_server})""#);
+        }
+        None => paint += r#" fill="none""#,
+    }
+    if let Some(stroke) = &shape.stroke {
+        paint += &paint_attrs("stroke", stroke.color);
+        let width = values.stroke_width.unwrap_or(stroke.width);
+        let _ = write!(paint, r#" stroke-width="{}""#, num(width));
+        let cap = match stroke.cap {
+            LineCap::Round => "round",
+            LineCap::Butt => "butt",
+            LineCap::Square => "square",
+        };
+        let join = match stroke.join {
+            LineJoin::Round => "round",
+            LineJoin::Miter => "miter",
+            LineJoin::Bevel => "bevel",
+        };
+        let _ = write!(paint, r#" stroke-linecap="{cap}" stroke-linejoin="{join}""#);
+    }
+
+    match (&shape.geometry, values.path_points.as_deref()) {
+        (Geometry::Path(data), points) => {
+            let points = points.unwrap_or(&data.points);
+            let d = path_data(points, data.closed);
+            format!(r#"<path{attrs} d="{d}"{paint}/>"#)
+        }
+        (
+            Geometry::Rect {
+                size,
+                corner_radius,
+            },
+            _,
+        ) => {
+            let radius = if *corner_radius > 0.0 {
+                let r = num(*corner_radius);
+                format!(r#" rx="{r}" ry="{r}""#)
+            } else {
+                String::new()
+            };
+            format!(
+                r#"<rect{attrs} width="{}" height="{}"{radius}{paint}/>"#,
+                num(size.x),
+                num(size.y)
+            )
+        }
+        (Geometry::Ellipse { radii }, _) => format!(
+            r#"<ellipse{attrs} rx="{}" ry="{}"{paint}/>"#,
+            num(radii.x),
+            num(radii.y)
+        ),
+        (Geometry::Brush(points), _) => {
+            let mut d = String::new();
+            for (index, p) in stroke::outline(points).into_iter().enumerate() {
+                let command = if index == 0 { 'M' } else { 'L' };
+                let _ = write!(d, "{command}{} {} ", num(p.x), num(p.y));
+            }
+            d.push('Z');
+            format!(r#"<path{attrs} d="{d}"{paint}/>"#)
+        }
+    }
+}
+
+/// Cubic Bezier path data matching the renderer's outline.
+fn path_data(points: &[PathPoint], closed: bool) -> String {
+    let Some(first) = points.first() else {
+        return String::new();
+    };
+    let mut d = format!("M{} {}", num(first.anchor.x), num(first.anchor.y));
+    let mut curve = |from: &PathPoint, to: &PathPoint| {
+        let _ = write!(
+            d,
+            " C{} {} {} {} {} {}",
+            num(from.handle_out.x),
+            num(from.handle_out.y),
+            num(to.handle_in.x),
+            num(to.handle_in.y),
+            num(to.anchor.x),
+            num(to.anchor.y),
+        );
+    };
+    for pair in points.windows(2) {
+        curve(&pair[0], &pair[1]);
+    }
+    if closed && points.len() > 1 {
+        curve(&points[points.len() - 1], first);
+        d.push_str(" Z");
+    }
+    d
+}
+
+/// The node transform as SVG transform functions, in the order the
+/// renderer applies them; identity steps are left out.
+fn transform_attr(t: &Transform) -> String {
+    let mut parts = Vec::new();
+    if t.position.x != 0.0 || t.position.y != 0.0 {
+        parts.push(format!(
+            "translate({} {})",
+            num(t.position.x),
+            num(t.position.y)
+        ));
+    }
+    if t.rotation != 0.0 {
+        parts.push(format!("rotate({})", num(t.rotation)));
+    }
+    if t.scale.x != 1.0 || t.scale.y != 1.0 {
+        parts.push(format!("scale({} {})", num(t.scale.x), num(t.scale.y)));
+    }
+    if t.anchor.x != 0.0 || t.anchor.y != 0.0 {
+        parts.push(format!(
+            "translate({} {})",
+            num(-t.anchor.x),
+            num(-t.anchor.y)
+        ));
+    }
+    parts.join(" ")
+}
+
+/// `fill` or `stroke` color attributes, with a separate opacity attribute
+/// for translucent colors since not every SVG consumer reads `#rrggbbaa`.
+fn paint_attrs(name: &str, color: Color) -> String {
+    let [r, g, b, _] = color.to_rgba8();
+    let mut attrs = format!(r##" {name}="#{r:02x}{g:02x}{b:02x}""##);
+    if color.a < 1.0 {
+        let _ = write!(attrs, r#" {name}-opacity="{}""#, num(color.a.max(0.0)));
+    }
+    attrs
+}
+
+fn css_blend_mode(mode: BlendMode) -> Option<&'static str> {
+    match mode {
+        BlendMode::Normal => None,
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::Overlay => Some("overlay"),
+        BlendMode::Darken => Some("darken"),
+        BlendMode::Lighten => Some("lighten"),
+        BlendMode::ColorDodge => Some("color-dodge"),
+        BlendMode::ColorBurn => Some("color-burn"),
+        BlendMode::Difference => Some("difference"),
+        BlendMode::Plus => Some("plus-lighter"),
+    }
+}
+
+/// Formats a coordinate with at most three decimals and no trailing zeros.
+fn num(value: f32) -> String {
+    let text = format!("{value:.3}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" | "" => "0".into(),
+        text => text.into(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{GradientStop, NodeId, PathData, Point, Stroke};
+    use crate::stroke::BrushPoint;
+
+    fn document() -> (Document, NodeId) {
+        let mut doc = Document::new(100, 80);
+        let layer = doc.add_layer("Layer");
+        (doc, layer)
+    }
+
+    fn add(doc: &mut Document, parent: NodeId, shape: Shape, position: Point) -> NodeId {
+        let id = doc.add_shape(parent, "Shape", shape).unwrap();
+        doc.get_mut(id).unwrap().transform.position = position;
+        id
+    }
+
+    fn rectangle(width: f32, height: f32, corner_radius: f32) -> Shape {
+        Shape::new(Geometry::Rect {
+            size: Point::new(width, height),
+            corner_radius,
+        })
+    }
+
+    /// Compares the export of `doc` at frame 0 with a file in
+    /// `testdata/svg`.
+    fn assert_golden(doc: &Document, golden: &str) {
+        assert_eq!(export(doc, 0.0, 1.0), golden);
+    }
+
+    #[test]
+    fn rect() {
+        let (mut doc, layer) = document();
+        let mut shape = rectangle(40.0, 20.0, 4.0);
+        shape.fill = Some(Fill::Solid(Color::rgb(1.0, 0.0, 0.0)));
+        add(&mut doc, layer, shape, Point::new(10.0, 20.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/rect.svg"));
+    }
+
+    #[test]
+    fn ellipse() {
+        let (mut doc, layer) = document();
+        let mut shape = Shape::new(Geometry::Ellipse {
+            radii: Point::new(15.0, 10.5),
+        });
+        shape.fill = Some(Fill::Solid(Color::rgba(0.0, 0.0, 1.0, 0.5)));
+        add(&mut doc, layer, shape, Point::new(50.0, 40.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/ellipse.svg"));
+    }
+
+    #[test]
+    fn path() {
+        let (mut doc, layer) = document();
+        let points = vec![
+            PathPoint::corner(Point::new(0.0, 0.0)),
+            PathPoint {
+                anchor: Point::new(30.0, 0.0),
+                handle_in: Point::new(20.0, -10.0),
+                handle_out: Point::new(40.0, 10.0),
+            },
+            PathPoint::corner(Point::new(15.0, 25.0)),
+        ];
+        let shape = Shape::new(Geometry::Path(PathData {
+            points,
+            closed: true,
+        }));
+        add(&mut doc, layer, shape, Point::new(5.0, 5.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/path.svg"));
+    }
+
+    #[test]
+    fn stroke() {
+        let (mut doc, layer) = document();
+        let mut shape = rectangle(30.0, 30.0, 0.0);
+        shape.fill = None;
+        shape.stroke = Some(Stroke {
+            color: Color::rgba(0.0, 0.5, 0.0, 0.25),
+            width: 2.5,
+            cap: LineCap::Square,
+            join: LineJoin::Bevel,
+        });
+        add(&mut doc, layer, shape, Point::ZERO);
+        assert_golden(&doc, include_str!("../../testdata/svg/stroke.svg"));
+    }
+
+    #[test]
+    fn group_with_transform() {
+        let (mut doc, layer) = document();
+        let group = doc.make_node(
+            "Group <1>",
+            NodeKind::Group {
+                children: Vec::new(),
+            },
+        );
+        let group_id = group.id;
+        doc.insert(Some(layer), usize::MAX, group).unwrap();
+        let node = doc.get_mut(group_id).unwrap();
+        node.opacity = 0.5;
+        node.transform = Transform {
+            position: Point::new(50.0, 40.0),
+            rotation: 45.0,
+            scale: Point::new(2.0, 1.5),
+            anchor: Point::new(5.0, 5.0),
+        };
+        add(&mut doc, group_id, rectangle(10.0, 10.0, 0.0), Point::ZERO);
+        assert_golden(&doc, include_str!("../../testdata/svg/group.svg"));
+    }
+
+    #[test]
+    fn brush() {
+        let (mut doc, layer) = document();
+        let points = vec![
+            BrushPoint {
+                position: Point::new(0.0, 0.0),
+                width: 4.0,
+            },
+            BrushPoint {
+                position: Point::new(20.0, 10.0),
+                width: 2.0,
+            },
+        ];
+        let mut shape = Shape::new(Geometry::Brush(points));
+        shape.fill = Some(Fill::Solid(Color::rgb(0.0, 0.0, 0.0)));
+        add(&mut doc, layer, shape, Point::new(10.0, 10.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/brush.svg"));
+    }
+
+    fn stops() -> Vec<GradientStop> {
+        vec![
+            GradientStop {
+                offset: 0.0,
+                color: Color::rgb(1.0, 0.0, 0.0),
+            },
+            GradientStop {
+                offset: 1.0,
+                color: Color::rgba(0.0, 0.0, 1.0, 0.5),
+            },
+        ]
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let (mut doc, layer) = document();
+        let mut shape = rectangle(40.0, 20.0, 0.0);
+        shape.fill = Some(Fill::LinearGradient {
+            start: Point::new(0.0, 0.0),
+            end: Point::new(40.0, 0.0),
+            stops: stops(),
+        });
+        add(&mut doc, layer, shape, Point::new(10.0, 20.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/linear-gradient.svg"));
+    }
+
+    #[test]
+    fn radial_gradient() {
+        let (mut doc, layer) = document();
+        let mut shape = Shape::new(Geometry::Ellipse {
+            radii: Point::new(20.0, 20.0),
+        });
+        shape.fill = Some(Fill::RadialGradient {
+            center: Point::ZERO,
+            radius: 20.0,
+            stops: stops(),
+        });
+        add(&mut doc, layer, shape, Point::new(50.0, 40.0));
+        assert_golden(&doc, include_str!("../../testdata/svg/radial-gradient.svg"));
+    }
+}
@@ -0,0 +1,1398 @@
+// SVG import.
+//
+// Turns an SVG file into editable nodes. Every top-level `<g>` becomes a
+// layer and runs of loose top-level shapes go into a layer named after the
+// file; nested groups stay groups. Rectangles, circles and ellipses keep
+// their parametric geometry when their transform is a plain translate,
+// rotate and scale; everything else becomes Bezier paths with the transform
+// baked into the points. SVG user units map to document pixels.
+//
+// Content without an equivalent in the document (text, images, filters,
+// masks, CSS style sheets, ...) is skipped and reported as a warning instead
+// of failing the import.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use roxmltree::Node as XmlNode;
+use svgtypes::{Length, LengthUnit, Paint, PathParser, PointsParser, SimplifyingPathParser};
+
+use crate::scene::{
+    Color, Document, Fill, Geometry, GradientStop, LineCap, LineJoin, Node, NodeId, NodeKind,
+    PathData, PathPoint, Point, Shape, Stroke, Transform,
+};
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+/// Tolerance for treating a matrix as free of skew.
+const EPSILON: f32 = 1e-4;
+
+/// Result of an import.
+#[derive(Clone, Debug, Default)]
+pub struct Imported {
+    /// New top-level layers, bottom-most first.
+    pub layers: Vec<NodeId>,
+    /// One line per kind of content that was skipped or approximated.
+    pub warnings: Vec<String>,
+}
+
+/// Imports the SVG file at `path` as new layers on top of `doc`.
+pub fn import_file(doc: &mut Document, path: &Path) -> Result<Imported> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let name = path.file_stem().map_or("Imported".into(), |stem| {
+        stem.to_string_lossy().into_owned()
+    });
+    import(doc, &text, &name).with_context(|| format!("cannot import {}", path.display()))
+}
+
+/// Imports SVG markup as new layers on top of `doc`. `name` labels the
+/// layer holding top-level shapes that are not in a group.
+pub fn import(doc: &mut Document, text: &str, name: &str) -> Result<Imported> {
+    let xml = roxmltree::Document::parse(text).context("not a valid SVG file")?;
+    let root = xml.root_element();
+    if root.tag_name().name() != "svg" {
+        bail!(
+            "not an SVG file: the root element is <{}>",
+            root.tag_name().name()
+        );
+    }
+
+    let mut importer = Importer {
+        xml: &xml,
+        doc,
+        viewport: (0.0, 0.0),
+        warnings: BTreeMap::new(),
+    };
+    let matrix = importer.root_matrix(root);
+    let style = importer.style(root, &Style::default());
+
+    // Top-level groups become layers; anything between them is collected
+    // into a layer of its own so the stacking order is kept.
+    let mut layers: Vec<Node> = Vec::new();
+    let mut loose: Vec<Node> = Vec::new();
+    for child in root.children().filter(XmlNode::is_element) {
+        let Some(mut node) = importer.element(child, &style, matrix) else {
+            continue;
+        };
+        if let NodeKind::Group { children } = node.kind {
+            if !loose.is_empty() {
+                layers.push(importer.loose_layer(name, &mut loose, layers.len()));
+            }
+            node.kind = NodeKind::Layer { children };
+            layers.push(node);
+        } else {
+            loose.push(node);
+        }
+    }
+    if !loose.is_empty() {
+        layers.push(importer.loose_layer(name, &mut loose, layers.len()));
+    }
+    if layers.is_empty() {
+        importer.warn("the file contains nothing that can be imported");
+    }
+
+    let mut warnings: Vec<String> = importer
+        .warnings
+        .into_iter()
+        .map(|(message, count)| match count {
+            1 => message,
+            n => format!("{message} ({n} times)"),
+        })
+        .collect();
+    warnings.sort();
+
+    let mut ids = Vec::new();
+    for layer in layers {
+        ids.push(layer.id);
+        doc.insert(None, usize::MAX, layer)?;
+    }
+    Ok(Imported {
+        layers: ids,
+        warnings,
+    })
+}
+
+/// 2D affine matrix in SVG order: `x' = a x + c y + e`, `y' = b x + d y + f`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Matrix {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+impl Matrix {
+    const IDENTITY: Matrix = Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    fn translate(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    fn scale(x: f32, y: f32) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// `self` applied after `inner`.
+    fn then(self, inner: Matrix) -> Matrix {
+        Matrix {
+            a: self.a * inner.a + self.c * inner.b,
+            b: self.b * inner.a + self.d * inner.b,
+            c: self.a * inner.c + self.c * inner.d,
+            d: self.b * inner.c + self.d * inner.d,
+            e: self.a * inner.e + self.c * inner.f + self.e,
+            f: self.b * inner.e + self.d * inner.f + self.f,
+        }
+    }
+
+    fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// Geometric mean of the scale, for lengths such as stroke widths.
+    fn scale_factor(&self) -> f32 {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// The equivalent node transform, or `None` if the matrix skews.
+    fn decompose(&self) -> Option<Transform> {
+        let sx = self.a.hypot(self.b);
+        if sx < EPSILON {
+            return None;
+        }
+        let sy = (self.a * self.d - self.b * self.c) / sx;
+        let skew = (self.a * self.c + self.b * self.d) / sx;
+        if skew.abs() > EPSILON * sy.abs().max(1.0) || sy.abs() < EPSILON {
+            return None;
+        }
+        Some(Transform {
+            position: Point::new(self.e, self.f),
+            rotation: self.b.atan2(self.a).to_degrees(),
+            scale: Point::new(sx, sy),
+            anchor: Point::ZERO,
+        })
+    }
+
+    /// True for translate, rotate and uniform scale only, which keep
+    /// circles circular.
+    fn is_conformal(&self) -> bool {
+        self.decompose()
+            .is_some_and(|t| (t.scale.x.abs() - t.scale.y.abs()).abs() < EPSILON * t.scale.x)
+    }
+}
+
+impl From<svgtypes::Transform> for Matrix {
+    fn from(t: svgtypes::Transform) -> Self {
+        let [a, b, c, d, e, f] = [t.a, t.b, t.c, t.d, t.e, t.f].map(|v| v as f32);
+        Matrix::new(a, b, c, d, e, f)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PaintSpec {
+    None,
+    Color(Color),
+    /// Reference to a paint server such as a gradient.
+    Url(String),
+}
+
+/// Inherited presentation properties.
+#[derive(Clone, Debug)]
+struct Style {
+    fill: PaintSpec,
+    fill_opacity: f32,
+    stroke: PaintSpec,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    cap: LineCap,
+    join: LineJoin,
+    /// Value of `currentColor`.
+    color: Color,
+    hidden: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: PaintSpec::Color(Color::BLACK),
+            fill_opacity: 1.0,
+            stroke: PaintSpec::None,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            color: Color::BLACK,
+            hidden: false,
+        }
+    }
+}
+
+/// Shape geometry in the element's own user space.
+enum Outline {
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        rx: f32,
+        ry: f32,
+    },
+    Ellipse {
+        center: Point,
+        rx: f32,
+        ry: f32,
+    },
+    Paths(Vec<PathData>),
+}
+
+impl Outline {
+    /// Bounding box as `(min, max)`, counting Bezier handles.
+    fn bounds(&self) -> (Point, Point) {
+        match self {
+            Outline::Rect {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } => (Point::new(*x, *y), Point::new(x + width, y + height)),
+            Outline::Ellipse { center, rx, ry } => (
+                Point::new(center.x - rx, center.y - ry),
+                Point::new(center.x + rx, center.y + ry),
+            ),
+            Outline::Paths(paths) => {
+                let mut min = Point::new(f32::MAX, f32::MAX);
+                let mut max = Point::new(f32::MIN, f32::MIN);
+                let points = paths.iter().flat_map(|path| &path.points);
+                for p in points.flat_map(|p| [p.anchor, p.handle_in, p.handle_out]) {
+                    min = Point::new(min.x.min(p.x), min.y.min(p.y));
+                    max = Point::new(max.x.max(p.x), max.y.max(p.y));
+                }
+                if min.x > max.x {
+                    (Point::ZERO, Point::ZERO)
+                } else {
+                    (min, max)
+                }
+            }
+        }
+    }
+
+    /// The geometry as Bezier paths.
+    fn into_paths(self) -> Vec<PathData> {
+        match self {
+            Outline::Paths(paths) => paths,
+            Outline::Ellipse { center, rx, ry } => vec![ellipse_path(center, rx, ry)],
+            Outline::Rect {
+                x,
+                y,
+                width,
+                height,
+                rx,
+                ry,
+            } => vec![rect_path(x, y, width, height, rx, ry)],
+        }
+    }
+}
+
+struct Importer<'a, 'input> {
+    xml: &'a roxmltree::Document<'input>,
+    doc: &'a mut Document,
+    /// Size of the root viewport, for percentage lengths.
+    viewport: (f32, f32),
+    /// Warning messages with how often they occurred.
+    warnings: BTreeMap<String, usize>,
+}
+
+impl Importer<'_, '_> {
+    fn warn(&mut self, message: impl Into<String>) {
+        *self.warnings.entry(message.into()).or_default() += 1;
+    }
+
+    /// Maps the root `viewBox` onto the root's width and height, centred
+    /// and uniformly scaled as with the default `preserveAspectRatio`.
+    fn root_matrix(&mut self, root: XmlNode) -> Matrix {
+        let view_box = root
+            .attribute("viewBox")
+            .and_then(|text| svgtypes::ViewBox::from_str(text).ok());
+        let width = root
+            .attribute("width")
+            .and_then(|t| Length::from_str(t).ok());
+        let height = root
+            .attribute("height")
+            .and_then(|t| Length::from_str(t).ok());
+        let absolute = |length: Option<Length>, fallback: f32| match length {
+            Some(length) if length.unit != LengthUnit::Percent => to_px(length),
+            _ => fallback,
+        };
+        let Some(view_box) = view_box else {
+            self.viewport = (
+                absolute(width, self.doc.width as f32),
+                absolute(height, self.doc.height as f32),
+            );
+            return Matrix::IDENTITY;
+        };
+        let (vx, vy) = (view_box.x as f32, view_box.y as f32);
+        let (vw, vh) = (view_box.w as f32, view_box.h as f32);
+        let width = absolute(width, vw);
+        let height = absolute(height, vh);
+        self.viewport = (vw, vh);
+
+        let (sx, sy) = (width / vw, height / vh);
+        match root.attribute("preserveAspectRatio").map(str::trim) {
+            Some("none") => Matrix::scale(sx, sy).then(Matrix::translate(-vx, -vy)),
+            other => {
+                if other.is_some_and(|value| value != "xMidYMid" && value != "xMidYMid meet") {
+                    self.warn("preserveAspectRatio other than the default is ignored");
+                }
+                let scale = sx.min(sy);
+                let offset_x = (width - vw * scale) / 2.0;
+                let offset_y = (height - vh * scale) / 2.0;
+                Matrix::translate(offset_x, offset_y)
+                    .then(Matrix::scale(scale, scale))
+                    .then(Matrix::translate(-vx, -vy))
+            }
+        }
+    }
+
+    fn loose_layer(&mut self, name: &str, nodes: &mut Vec<Node>, index: usize) -> Node {
+        let name = if index == 0 {
+            name.to_string()
+        } else {
+            format!("{name} {}", index + 1)
+        };
+        let children = std::mem::take(nodes);
+        self.doc.make_node(name, NodeKind::Layer { children })
+    }
+
+    /// Converts one element and its subtree, or returns `None` if nothing
+    /// in it can be imported. `outer` maps the element's parent user space
+    /// to the space of the node the result is placed in.
+    fn element(&mut self, el: XmlNode, parent: &Style, outer: Matrix) -> Option<Node> {
+        match el.tag_name().namespace() {
+            None | Some(SVG_NS) => {}
+            // Editor metadata such as sodipodi:namedview.
+            Some(_) => return None,
+        }
+        let tag = el.tag_name().name();
+        match tag {
+            "defs" | "title" | "desc" | "metadata" | "linearGradient" | "radialGradient"
+            | "symbol" | "clipPath" | "mask" | "marker" | "pattern" | "filter" => return None,
+            "style" => {
+                self.warn("CSS style sheets are ignored; only inline styles are imported");
+                return None;
+            }
+            _ => {}
+        }
+        if property(el, "display") == Some("none") {
+            return None;
+        }
+
+        let style = self.style(el, parent);
+        let own = el
+            .attribute("transform")
+            .and_then(|text| svgtypes::Transform::from_str(text).ok())
+            .map_or(Matrix::IDENTITY, Matrix::from);
+        let matrix = outer.then(own);
+        for (attribute, feature) in [
+            ("filter", "filters"),
+            ("mask", "masks"),
+            ("clip-path", "clip paths"),
+        ] {
+            if property(el, attribute).is_some_and(|value| value != "none") {
+                self.warn(format!("{feature} are not supported and were ignored"));
+            }
+        }
+        if property(el, "stroke-dasharray").is_some_and(|value| value != "none") {
+            self.warn("dashed strokes were imported as solid strokes");
+        }
+
+        let mut node = match tag {
+            "g" | "a" | "svg" | "switch" => {
+                if tag == "svg" {
+                    self.warn("nested <svg> elements were imported as groups");
+                }
+                self.group(el, &style, matrix)?
+            }
+            "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {
+                self.shape(el, &style, matrix)?
+            }
+            other => {
+                self.warn(format!(
+                    "<{other}> elements are not supported and were skipped"
+                ));
+                return None;
+            }
+        };
+        node.visible = !style.hidden;
+        if let Some(opacity) = property(el, "opacity").and_then(parse_opacity) {
+            node.opacity = opacity;
+        }
+        Some(node)
+    }
+
+    fn group(&mut self, el: XmlNode, style: &Style, matrix: Matrix) -> Option<Node> {
+        // A skewing transform cannot be a node transform; push it down to
+        // the children instead.
+        let (transform, inner) = match matrix.decompose() {
+            Some(transform) => (transform, Matrix::IDENTITY),
+            None => (Transform::default(), matrix),
+        };
+        let children: Vec<Node> = el
+            .children()
+            .filter(XmlNode::is_element)
+            .filter_map(|child| self.element(child, style, inner))
+            .collect();
+        if children.is_empty() {
+            return None;
+        }
+        let mut node = self
+            .doc
+            .make_node(label(el, "Group"), NodeKind::Group { children });
+        node.transform = transform;
+        Some(node)
+    }
+
+    fn shape(&mut self, el: XmlNode, style: &Style, matrix: Matrix) -> Option<Node> {
+        let outline = self.outline(el)?;
+        let bounds = outline.bounds();
+        let name = label(el, default_label(el.tag_name().name()));
+
+        // `to_local` maps the element's user space to the shape's local
+        // space, where gradients and stroke widths live.
+        let (transform, to_local, geometry) = match outline {
+            Outline::Rect {
+                x,
+                y,
+                width,
+                height,
+                rx,
+                ry,
+            } if (rx - ry).abs() < EPSILON => {
+                match matrix.then(Matrix::translate(x, y)).decompose() {
+                    Some(transform) => {
+                        let size = Point::new(width, height);
+                        let geometry = Geometry::Rect {
+                            size,
+                            corner_radius: rx,
+                        };
+                        (transform, Matrix::translate(-x, -y), vec![geometry])
+                    }
+                    None => baked(matrix, rect_path(x, y, width, height, rx, ry)),
+                }
+            }
+            Outline::Ellipse { center, rx, ry } => {
+                let translated = matrix.then(Matrix::translate(center.x, center.y));
+                match translated.decompose() {
+                    Some(transform) => {
+                        let radii = Point::new(rx, ry);
+                        let to_local = Matrix::translate(-center.x, -center.y);
+                        (transform, to_local, vec![Geometry::Ellipse { radii }])
+                    }
+                    None => baked(matrix, ellipse_path(center, rx, ry)),
+                }
+            }
+            outline => match matrix.decompose() {
+                Some(transform) => {
+                    let paths = outline.into_paths();
+                    let geometry = paths.into_iter().map(Geometry::Path).collect();
+                    (transform, Matrix::IDENTITY, geometry)
+                }
+                None => {
+                    let paths = outline.into_paths();
+                    let geometry = paths
+                        .into_iter()
+                        .map(|path| Geometry::Path(transform_path(&matrix, path)))
+                        .collect();
+                    (Transform::default(), matrix, geometry)
+                }
+            },
+        };
+
+        let fill = self.fill(&style.fill, style.fill_opacity, bounds, to_local);
+        let stroke = self.stroke(style, to_local);
+        if geometry.len() > 1 && fill.is_some() {
+            self.warn("compound paths were split into separate shapes; holes are filled");
+        }
+
+        let mut shapes: Vec<Node> = geometry
+            .into_iter()
+            .map(|geometry| {
+                let mut shape = Shape::new(geometry);
+                shape.fill = fill.clone();
+                shape.stroke = stroke.clone();
+                self.doc.make_node(name.clone(), NodeKind::Shape(shape))
+            })
+            .collect();
+        let mut node = if shapes.len() == 1 {
+            shapes.pop()?
+        } else {
+            for (index, shape) in shapes.iter_mut().enumerate() {
+                shape.name = format!("{name} {}", index + 1);
+            }
+            self.doc
+                .make_node(name, NodeKind::Group { children: shapes })
+        };
+        node.transform = transform;
+        Some(node)
+    }
+
+    /// The element's geometry in its own user space.
+    fn outline(&mut self, el: XmlNode) -> Option<Outline> {
+        let number = |name: &str, axis: Axis| self.length(el, name, axis);
+        let outline = match el.tag_name().name() {
+            "rect" => {
+                let width = number("width", Axis::X)?;
+                let height = number("height", Axis::Y)?;
+                if width <= 0.0 || height <= 0.0 {
+                    return None;
+                }
+                let (rx, ry) = match (number("rx", Axis::X), number("ry", Axis::Y)) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (0.0, 0.0),
+                };
+                Outline::Rect {
+                    x: number("x", Axis::X).unwrap_or(0.0),
+                    y: number("y", Axis::Y).unwrap_or(0.0),
+                    width,
+                    height,
+                    rx: rx.clamp(0.0, width / 2.0),
+                    ry: ry.clamp(0.0, height / 2.0),
+                }
+            }
+            "circle" | "ellipse" => {
+                let (rx, ry) = if el.tag_name().name() == "circle" {
+                    let r = number("r", Axis::Diagonal)?;
+                    (r, r)
+                } else {
+                    (number("rx", Axis::X)?, number("ry", Axis::Y)?)
+                };
+                if rx <= 0.0 || ry <= 0.0 {
+                    return None;
+                }
+                let center = Point::new(
+                    number("cx", Axis::X).unwrap_or(0.0),
+                    number("cy", Axis::Y).unwrap_or(0.0),
+                );
+                Outline::Ellipse { center, rx, ry }
+            }
+            "line" => {
+                let from = Point::new(
+                    number("x1", Axis::X).unwrap_or(0.0),
+                    number("y1", Axis::Y).unwrap_or(0.0),
+                );
+                let to = Point::new(
+                    number("x2", Axis::X).unwrap_or(0.0),
+                    number("y2", Axis::Y).unwrap_or(0.0),
+                );
+                let points = vec![PathPoint::corner(from), PathPoint::corner(to)];
+                Outline::Paths(vec![PathData {
+                    points,
+                    closed: false,
+                }])
+            }
+            "polyline" | "polygon" => {
+                let points: Vec<PathPoint> = PointsParser::from(el.attribute("points")?)
+                    .map(|(x, y)| PathPoint::corner(Point::new(x as f32, y as f32)))
+                    .collect();
+                if points.len() < 2 {
+                    return None;
+                }
+                let closed = el.tag_name().name() == "polygon";
+                Outline::Paths(vec![PathData { points, closed }])
+            }
+            "path" => {
+                let d = el.attribute("d")?;
+                // Validate with the plain parser first so a broken path
+                // reports an error instead of silently truncating.
+                if PathParser::from(d).any(|segment| segment.is_err()) {
+                    self.warn("path data with errors was imported up to the first error");
+                }
+                let paths = parse_path(d);
+                if paths.is_empty() {
+                    return None;
+                }
+                Outline::Paths(paths)
+            }
+            _ => return None,
+        };
+        Some(outline)
+    }
+
+    /// A length attribute in user units. Percentages are relative to the
+    /// root viewport.
+    fn length(&self, el: XmlNode, name: &str, axis: Axis) -> Option<f32> {
+        let length = Length::from_str(el.attribute(name)?.trim()).ok()?;
+        if length.unit != LengthUnit::Percent {
+            return Some(to_px(length));
+        }
+        let (width, height) = self.viewport;
+        let reference = match axis {
+            Axis::X => width,
+            Axis::Y => height,
+            Axis::Diagonal => (width * width + height * height).sqrt() / 2f32.sqrt(),
+        };
+        Some(length.number as f32 / 100.0 * reference)
+    }
+
+    /// Cascades the element's presentation attributes and inline style
+    /// over the inherited `parent` style.
+    fn style(&mut self, el: XmlNode, parent: &Style) -> Style {
+        let mut style = parent.clone();
+        if let Some(color) = property(el, "color").and_then(parse_color) {
+            style.color = color;
+        }
+        if let Some(paint) = property(el, "fill").and_then(|v| self.paint(v, &style, &parent.fill))
+        {
+            style.fill = paint;
+        }
+        if let Some(paint) =
+            property(el, "stroke").and_then(|v| self.paint(v, &style, &parent.stroke))
+        {
+            style.stroke = paint;
+        }
+        if let Some(opacity) = property(el, "fill-opacity").and_then(parse_opacity) {
+            style.fill_opacity = opacity;
+        }
+        if let Some(opacity) = property(el, "stroke-opacity").and_then(parse_opacity) {
+            style.stroke_opacity = opacity;
+        }
+        if let Some(width) = property(el, "stroke-width")
+            .and_then(|text| Length::from_str(text).ok())
+            .filter(|length| length.unit != LengthUnit::Percent)
+        {
+            style.stroke_width = to_px(width).max(0.0);
+        }
+        match property(el, "stroke-linecap") {
+            Some("butt") => style.cap = LineCap::Butt,
+            Some("round") => style.cap = LineCap::Round,
+            Some("square") => style.cap = LineCap::Square,
+            _ => {}
+        }
+        match property(el, "stroke-linejoin") {
+            Some("miter" | "miter-clip" | "arcs") => style.join = LineJoin::Miter,
+            Some("round") => style.join = LineJoin::Round,
+            Some("bevel") => style.join = LineJoin::Bevel,
+            _ => {}
+        }
+        match property(el, "visibility") {
+            Some("hidden" | "collapse") => style.hidden = true,
+            Some("visible") => style.hidden = false,
+            _ => {}
+        }
+        style
+    }
+
+    fn paint(&mut self, text: &str, style: &Style, inherited: &PaintSpec) -> Option<PaintSpec> {
+        let paint = match Paint::from_str(text) {
+            Ok(paint) => paint,
+            Err(_) => {
+                self.warn(format!("unrecognized paint `{text}` was ignored"));
+                return None;
+            }
+        };
+        Some(match paint {
+            Paint::None => PaintSpec::None,
+            Paint::Inherit => inherited.clone(),
+            Paint::CurrentColor => PaintSpec::Color(style.color),
+            Paint::Color(color) => PaintSpec::Color(from_svg_color(color)),
+            Paint::FuncIRI(id, _) => PaintSpec::Url(id.to_string()),
+            Paint::ContextFill | Paint::ContextStroke => {
+                self.warn("context-fill and context-stroke are not supported");
+                PaintSpec::None
+            }
+        })
+    }
+
+    fn fill(
+        &mut self,
+        paint: &PaintSpec,
+        opacity: f32,
+        bounds: (Point, Point),
+        to_local: Matrix,
+    ) -> Option<Fill> {
+        match paint {
+            PaintSpec::None => None,
+            PaintSpec::Color(color) => Some(Fill::Solid(with_alpha(*color, opacity))),
+            // Missing or unsupported paint servers paint nothing, as in a
+            // browser when no fallback color is given.
+            PaintSpec::Url(id) => self.gradient(id, opacity, bounds, to_local),
+        }
+    }
+
+    fn stroke(&mut self, style: &Style, to_local: Matrix) -> Option<Stroke> {
+        let color = match &style.stroke {
+            PaintSpec::None => return None,
+            PaintSpec::Color(color) => *color,
+            PaintSpec::Url(id) => {
+                self.warn("gradient strokes were replaced by a solid color");
+                self.gradient_stops(id)
+                    .and_then(|stops| stops.first().map(|stop| stop.color))
+                    .unwrap_or(Color::BLACK)
+            }
+        };
+        if style.stroke_width <= 0.0 {
+            return None;
+        }
+        Some(Stroke {
+            color: with_alpha(color, style.stroke_opacity),
+            width: style.stroke_width * to_local.scale_factor(),
+            cap: style.cap,
+            join: style.join,
+        })
+    }
+
+    /// The gradient with `id`, in the shape's local space.
+    fn gradient(
+        &mut self,
+        id: &str,
+        opacity: f32,
+        bounds: (Point, Point),
+        to_local: Matrix,
+    ) -> Option<Fill> {
+        let Some(el) = find(self.xml, id) else {
+            self.warn(format!(
+                "missing paint server `#{id}`; the shape is unfilled"
+            ));
+            return None;
+        };
+        let tag = el.tag_name().name();
+        if tag != "linearGradient" && tag != "radialGradient" {
+            self.warn(format!(
+                "<{tag}> fills are not supported; the shape is unfilled"
+            ));
+            return None;
+        }
+        let mut stops = self.gradient_stops(id)?;
+        for stop in &mut stops {
+            stop.color.a *= opacity;
+        }
+        if stops.is_empty() {
+            return None;
+        }
+
+        let xml = self.xml;
+        let attribute = |name: &str| gradient_attribute(xml, el, name);
+        if attribute("spreadMethod").is_some_and(|method| method != "pad") {
+            self.warn("reflected and repeated gradients were imported as padded");
+        }
+        let bounding_box = attribute("gradientUnits") != Some("userSpaceOnUse");
+        let gradient_transform = attribute("gradientTransform")
+            .and_then(|text| svgtypes::Transform::from_str(text).ok())
+            .map_or(Matrix::IDENTITY, Matrix::from);
+        let space = if bounding_box {
+            let (min, max) = bounds;
+            Matrix::translate(min.x, min.y).then(Matrix::scale(max.x - min.x, max.y - min.y))
+        } else {
+            Matrix::IDENTITY
+        };
+        let to_local = to_local.then(space).then(gradient_transform);
+
+        // Coordinates are fractions of the bounding box or user units.
+        let (width, height) = if bounding_box {
+            (1.0, 1.0)
+        } else {
+            self.viewport
+        };
+        let coordinate = |name: &str, default: f32, reference: f32| {
+            attribute(name)
+                .and_then(|text| Length::from_str(text).ok())
+                .map_or(default, |length| match length.unit {
+                    LengthUnit::Percent => length.number as f32 / 100.0 * reference,
+                    _ if bounding_box => length.number as f32,
+                    _ => to_px(length),
+                })
+        };
+        if tag == "linearGradient" {
+            let start = Point::new(coordinate("x1", 0.0, width), coordinate("y1", 0.0, height));
+            let end = Point::new(
+                coordinate("x2", width, width),
+                coordinate("y2", 0.0, height),
+            );
+            // Lines of equal color stay parallel under any affine map but
+            // not perpendicular to the gradient vector, so the end point is
+            // projected onto the mapped lines' normal.
+            let direction = Point::new(start.y - end.y, end.x - start.x);
+            let from = to_local.apply(start);
+            let to = to_local.apply(end);
+            let along = to_local.apply(Point::new(start.x + direction.x, start.y + direction.y));
+            let normal = Point::new(from.y - along.y, along.x - from.x);
+            let length = normal.x * normal.x + normal.y * normal.y;
+            let t = if length > 0.0 {
+                ((to.x - from.x) * normal.x + (to.y - from.y) * normal.y) / length
+            } else {
+                0.0
+            };
+            Some(Fill::LinearGradient {
+                start: from,
+                end: Point::new(from.x + normal.x * t, from.y + normal.y * t),
+                stops,
+            })
+        } else {
+            let diagonal = (width * width + height * height).sqrt() / 2f32.sqrt();
+            let center = Point::new(
+                coordinate("cx", width / 2.0, width),
+                coordinate("cy", height / 2.0, height),
+            );
+            let radius = coordinate("r", diagonal / 2.0, diagonal);
+            if !to_local.is_conformal() {
+                self.warn("radial gradients on stretched or skewed shapes are approximated");
+            }
+            if attribute("fx").is_some() || attribute("fy").is_some() {
+                self.warn("radial gradient focal points were moved to the center");
+            }
+            Some(Fill::RadialGradient {
+                center: to_local.apply(center),
+                radius: radius * to_local.scale_factor(),
+                stops,
+            })
+        }
+    }
+
+    /// Stops of the gradient with `id`, following `href` references.
+    fn gradient_stops(&mut self, id: &str) -> Option<Vec<GradientStop>> {
+        let mut el = find(self.xml, id)?;
+        for _ in 0..8 {
+            let stops: Vec<GradientStop> = el
+                .children()
+                .filter(|child| child.has_tag_name((SVG_NS, "stop")) || child.has_tag_name("stop"))
+                .map(|stop| {
+                    let color = property(stop, "stop-color")
+                        .and_then(parse_color)
+                        .unwrap_or(Color::BLACK);
+                    let opacity = property(stop, "stop-opacity")
+                        .and_then(parse_opacity)
+                        .unwrap_or(1.0);
+                    GradientStop {
+                        offset: stop.attribute("offset").map_or(0.0, parse_offset),
+                        color: with_alpha(color, opacity),
+                    }
+                })
+                .collect();
+            if !stops.is_empty() {
+                // Offsets never decrease along the gradient.
+                let mut previous = 0.0;
+                return Some(
+                    stops
+                        .into_iter()
+                        .map(|mut stop| {
+                            stop.offset = stop.offset.clamp(previous, 1.0);
+                            previous = stop.offset;
+                            stop
+                        })
+                        .collect(),
+                );
+            }
+            el = find(self.xml, href(el)?)?;
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+    /// Normalized diagonal, for radii.
+    Diagonal,
+}
+
+/// A property from the inline `style` attribute, which takes precedence,
+/// or from the presentation attribute of the same name.
+fn property<'a>(el: XmlNode<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = el.attribute("style").and_then(|style| {
+        style.split(';').rev().find_map(|declaration| {
+            let (key, value) = declaration.split_once(':')?;
+            (key.trim() == name).then(|| value.trim())
+        })
+    });
+    from_style
+        .or_else(|| el.attribute(name).map(str::trim))
+        .filter(|value| *value != "inherit")
+}
+
+/// An attribute of a gradient or of the gradients it references.
+fn gradient_attribute<'a>(
+    xml: &'a roxmltree::Document,
+    el: XmlNode<'a, '_>,
+    name: &str,
+) -> Option<&'a str> {
+    let mut current = el;
+    for _ in 0..8 {
+        if let Some(value) = current.attribute(name) {
+            return Some(value);
+        }
+        current = find(xml, href(current)?)?;
+    }
+    None
+}
+
+fn find<'a, 'input>(xml: &'a roxmltree::Document<'input>, id: &str) -> Option<XmlNode<'a, 'input>> {
+    xml.descendants()
+        .find(|node| node.attribute("id") == Some(id))
+}
+
+fn href<'a>(el: XmlNode<'a, '_>) -> Option<&'a str> {
+    el.attribute((XLINK_NS, "href"))
+        .or_else(|| el.attribute("href"))?
+        .strip_prefix('#')
+}
+
+fn label(el: XmlNode, default: &str) -> String {
+    el.attribute((INKSCAPE_NS, "label"))
+        .or_else(|| el.attribute("id"))
+        .unwrap_or(default)
+        .to_string()
+}
+
+fn default_label(tag: &str) -> &'static str {
+    match tag {
+        "rect" => "Rectangle",
+        "circle" | "ellipse" => "Ellipse",
+        "line" => "Line",
+        "polyline" | "polygon" => "Polygon",
+        _ => "Path",
+    }
+}
+
+fn to_px(length: Length) -> f32 {
+    let factor = match length.unit {
+        LengthUnit::In => 96.0,
+        LengthUnit::Cm => 96.0 / 2.54,
+        LengthUnit::Mm => 96.0 / 25.4,
+        LengthUnit::Pt => 4.0 / 3.0,
+        LengthUnit::Pc => 16.0,
+        LengthUnit::Em => 16.0,
+        LengthUnit::Ex => 8.0,
+        LengthUnit::None | LengthUnit::Px | LengthUnit::Percent => 1.0,
+    };
+    (length.number * factor) as f32
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    svgtypes::Color::from_str(text).ok().map(from_svg_color)
+}
+
+fn from_svg_color(color: svgtypes::Color) -> Color {
+    Color::from_rgba8(color.red, color.green, color.blue, color.alpha)
+}
+
+fn with_alpha(color: Color, opacity: f32) -> Color {
+    Color {
+        a: color.a * opacity,
+        ..color
+    }
+}
+
+/// A number or percentage clamped to 0..1.
+fn parse_opacity(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let value = match text.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? / 100.0,
+        None => text.parse().ok()?,
+    };
+    Some(value.clamp(0.0, 1.0))
+}
+
+fn parse_offset(text: &str) -> f32 {
+    parse_opacity(text).unwrap_or(0.0)
+}
+
+/// Path data as one `PathData` per subpath. Arcs and shorthand commands
+/// arrive as cubic and quadratic curves from the simplifying parser.
+fn parse_path(d: &str) -> Vec<PathData> {
+    use svgtypes::SimplePathSegment as Segment;
+
+    let mut paths: Vec<PathData> = Vec::new();
+    let mut current: Vec<PathPoint> = Vec::new();
+    let finish = |points: &mut Vec<PathPoint>, closed: bool, paths: &mut Vec<PathData>| {
+        if points.len() >= 2 {
+            paths.push(PathData {
+                points: std::mem::take(points),
+                closed,
+            });
+        }
+        points.clear();
+    };
+    let point = |x: f64, y: f64| Point::new(x as f32, y as f32);
+
+    for segment in SimplifyingPathParser::from(d).map_while(Result::ok) {
+        match segment {
+            Segment::MoveTo { x, y } => {
+                finish(&mut current, false, &mut paths);
+                current.push(PathPoint::corner(point(x, y)));
+            }
+            Segment::LineTo { x, y } => current.push(PathPoint::corner(point(x, y))),
+            Segment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                if let Some(last) = current.last_mut() {
+                    last.handle_out = point(x1, y1);
+                }
+                current.push(PathPoint {
+                    anchor: point(x, y),
+                    handle_in: point(x2, y2),
+                    handle_out: point(x, y),
+                });
+            }
+            Segment::Quadratic { x1, y1, x, y } => {
+                // Degree elevation: the cubic handles lie two thirds of the
+                // way from each end towards the quadratic control point.
+                let control = point(x1, y1);
+                let end = point(x, y);
+                if let Some(last) = current.last_mut() {
+                    last.handle_out = last.anchor.lerp(control, 2.0 / 3.0);
+                }
+                current.push(PathPoint {
+                    anchor: end,
+                    handle_in: end.lerp(control, 2.0 / 3.0),
+                    handle_out: end,
+                });
+            }
+            Segment::ClosePath => {
+                // A final point on top of the first one is the closing
+                // segment drawn explicitly; merge the two.
+                if current.len() > 2 {
+                    let (first, last) = (current[0], current[current.len() - 1]);
+                    if first.anchor.distance(last.anchor) < EPSILON {
+                        current[0].handle_in = last.handle_in;
+                        current.pop();
+                    }
+                }
+                finish(&mut current, true, &mut paths);
+            }
+        }
+    }
+    finish(&mut current, false, &mut paths);
+    paths
+}
+
+/// Bakes `matrix` into a path's points.
+fn transform_path(matrix: &Matrix, mut path: PathData) -> PathData {
+    for point in &mut path.points {
+        point.anchor = matrix.apply(point.anchor);
+        point.handle_in = matrix.apply(point.handle_in);
+        point.handle_out = matrix.apply(point.handle_out);
+    }
+    path
+}
+
+/// Shape placement for geometry whose transform had to be baked in.
+fn baked(matrix: Matrix, path: PathData) -> (Transform, Matrix, Vec<Geometry>) {
+    let geometry = vec![Geometry::Path(transform_path(&matrix, path))];
+    (Transform::default(), matrix, geometry)
+}
+
+/// Handle length for approximating a quarter ellipse with a cubic curve.
+const KAPPA: f32 = 0.552_284_8;
+
+fn ellipse_path(center: Point, rx: f32, ry: f32) -> PathData {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let (cx, cy) = (center.x, center.y);
+    let point = |x: f32, y: f32, dx: f32, dy: f32| PathPoint {
+        anchor: Point::new(x, y),
+        handle_in: Point::new(x - dx, y - dy),
+        handle_out: Point::new(x + dx, y + dy),
+    };
+    PathData {
+        points: vec![
+            point(cx + rx, cy, 0.0, ky),
+            point(cx, cy + ry, -kx, 0.0),
+            point(cx - rx, cy, 0.0, -ky),
+            point(cx, cy - ry, kx, 0.0),
+        ],
+        closed: true,
+    }
+}
+
+fn rect_path(x: f32, y: f32, width: f32, height: f32, rx: f32, ry: f32) -> PathData {
+    let (right, bottom) = (x + width, y + height);
+    if rx <= 0.0 || ry <= 0.0 {
+        let points = [(x, y), (right, y), (right, bottom), (x, bottom)]
+            .map(|(x, y)| PathPoint::corner(Point::new(x, y)));
+        return PathData {
+            points: points.to_vec(),
+            closed: true,
+        };
+    }
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    // Each corner arc runs between two anchors; the straight edges are the
+    // segments between arcs, whose handles sit on their anchors.
+    let anchor = |x: f32, y: f32, handle_in: (f32, f32), handle_out: (f32, f32)| PathPoint {
+        anchor: Point::new(x, y),
+        handle_in: Point::new(x + handle_in.0, y + handle_in.1),
+        handle_out: Point::new(x + handle_out.0, y + handle_out.1),
+    };
+    PathData {
+        points: vec![
+            anchor(x + rx, y, (-kx, 0.0), (0.0, 0.0)),
+            anchor(right - rx, y, (0.0, 0.0), (kx, 0.0)),
+            anchor(right, y + ry, (0.0, -ky), (0.0, 0.0)),
+            anchor(right, bottom - ry, (0.0, 0.0), (0.0, ky)),
+            anchor(right - rx, bottom, (kx, 0.0), (0.0, 0.0)),
+            anchor(x + rx, bottom, (0.0, 0.0), (-kx, 0.0)),
+            anchor(x, bottom - ry, (0.0, ky), (0.0, 0.0)),
+            anchor(x, y + ry, (0.0, 0.0), (0.0, -ky)),
+        ],
+        closed: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> String {
+        format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="80">{body}</svg>"#)
+    }
+
+    fn import_body(body: &str) -> (Document, Imported) {
+        let mut doc = Document::new(100, 80);
+        let imported = import(&mut doc, &svg(body), "Drawing").unwrap();
+        (doc, imported)
+    }
+
+    fn layer(doc: &Document, imported: &Imported, index: usize) -> Node {
+        doc.get(imported.layers[index]).unwrap().clone()
+    }
+
+    fn shape(node: &Node) -> &Shape {
+        match &node.kind {
+            NodeKind::Shape(shape) => shape,
+            kind => panic!("not a shape: {kind:?}"),
+        }
+    }
+
+    fn anchors(shape: &Shape) -> (Vec<Point>, bool) {
+        match &shape.geometry {
+            Geometry::Path(path) => (path.points.iter().map(|p| p.anchor).collect(), path.closed),
+            geometry => panic!("not a path: {geometry:?}"),
+        }
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(a.distance(b) < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn each_shape_type() {
+        let (doc, imported) = import_body(
+            r#"<rect x="10" y="20" width="30" height="15" rx="2"/>
+               <circle id="dot" cx="50" cy="40" r="5"/>
+               <ellipse cx="5" cy="6" rx="3" ry="4"/>
+               <line x1="0" y1="0" x2="10" y2="5" stroke="black"/>
+               <polyline points="0,0 10,0 10,10"/>
+               <polygon points="0,0 10,0 5,8"/>
+               <path d="M0 0 C10 0 20 10 20 20 Z"/>"#,
+        );
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        let layer = layer(&doc, &imported, 0);
+        assert_eq!(layer.name, "Drawing");
+        let shapes = layer.children();
+        let names: Vec<&str> = shapes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Rectangle",
+                "dot",
+                "Ellipse",
+                "Line",
+                "Polygon",
+                "Polygon",
+                "Path"
+            ]
+        );
+
+        let rect = shape(&shapes[0]);
+        let size = Point::new(30.0, 15.0);
+        assert_eq!(
+            rect.geometry,
+            Geometry::Rect {
+                size,
+                corner_radius: 2.0
+            }
+        );
+        assert_eq!(shapes[0].transform.position, Point::new(10.0, 20.0));
+        assert_eq!(rect.fill, Some(Fill::Solid(Color::BLACK)));
+        assert_eq!(rect.stroke, None);
+
+        let radii = Point::new(5.0, 5.0);
+        assert_eq!(shape(&shapes[1]).geometry, Geometry::Ellipse { radii });
+        assert_eq!(shapes[1].transform.position, Point::new(50.0, 40.0));
+        let radii = Point::new(3.0, 4.0);
+        assert_eq!(shape(&shapes[2]).geometry, Geometry::Ellipse { radii });
+
+        let line = shape(&shapes[3]);
+        let points = vec![Point::ZERO, Point::new(10.0, 5.0)];
+        assert_eq!(anchors(line), (points, false));
+        assert_eq!(line.stroke.as_ref().map(|s| s.width), Some(1.0));
+        assert!(!anchors(shape(&shapes[4])).1);
+        assert_eq!(anchors(shape(&shapes[5])).0.len(), 3);
+        assert!(anchors(shape(&shapes[5])).1);
+
+        let Geometry::Path(path) = &shape(&shapes[6]).geometry else {
+            panic!("not a path");
+        };
+        assert!(path.closed);
+        assert_eq!(path.points[0].handle_out, Point::new(10.0, 0.0));
+        assert_eq!(path.points[1].handle_in, Point::new(20.0, 10.0));
+        assert_eq!(path.points[1].anchor, Point::new(20.0, 20.0));
+    }
+
+    #[test]
+    fn nested_transforms_stay_node_transforms() {
+        let (doc, imported) = import_body(
+            r#"<g id="Layer" transform="translate(10 20)">
+                 <g id="Turned" transform="rotate(90) scale(2)">
+                   <rect x="5" y="0" width="4" height="4"/>
+                 </g>
+               </g>"#,
+        );
+        let layer = layer(&doc, &imported, 0);
+        assert!(matches!(layer.kind, NodeKind::Layer { .. }));
+        assert_eq!(layer.transform.position, Point::new(10.0, 20.0));
+
+        let group = &layer.children()[0];
+        assert_eq!(group.name, "Turned");
+        assert!((group.transform.rotation - 90.0).abs() < 1e-3);
+        assert_near(group.transform.scale, Point::new(2.0, 2.0));
+        assert_eq!(group.children()[0].transform.position, Point::new(5.0, 0.0));
+    }
+
+    #[test]
+    fn skewed_transforms_are_baked_into_paths() {
+        let (doc, imported) = import_body(
+            r#"<g>
+                 <g transform="translate(10 0) skewX(45)">
+                   <rect width="10" height="10"/>
+                 </g>
+               </g>"#,
+        );
+        let layer = layer(&doc, &imported, 0);
+        let group = &layer.children()[0];
+        assert_eq!(group.transform, Transform::default());
+
+        let rect = &group.children()[0];
+        assert_eq!(rect.transform, Transform::default());
+        let (points, closed) = anchors(shape(rect));
+        assert!(closed);
+        let expected = [(10.0, 0.0), (20.0, 0.0), (30.0, 10.0), (20.0, 10.0)];
+        for (point, (x, y)) in points.into_iter().zip(expected) {
+            assert_near(point, Point::new(x, y));
+        }
+    }
+
+    #[test]
+    fn bounding_box_gradients_map_to_local_space() {
+        let (doc, imported) = import_body(
+            r#"<defs>
+                 <linearGradient id="g" x2="1" y2="1">
+                   <stop offset="0" stop-color="red"/>
+                   <stop offset="50%" stop-color="blue" stop-opacity="0.5"/>
+                 </linearGradient>
+               </defs>
+               <rect x="10" y="20" width="40" height="20" fill="url(#g)" fill-opacity="0.5"/>"#,
+        );
+        let layer = layer(&doc, &imported, 0);
+        let Some(Fill::LinearGradient { start, end, stops }) = &shape(&layer.children()[0]).fill
+        else {
+            panic!("not a linear gradient");
+        };
+        // Lines of equal color run along the box diagonal, so the end point
+        // is projected onto their normal.
+        assert_near(*start, Point::ZERO);
+        assert_near(*end, Point::new(16.0, 32.0));
+        let expected = [
+            GradientStop {
+                offset: 0.0,
+                color: Color::rgba(1.0, 0.0, 0.0, 0.5),
+            },
+            GradientStop {
+                offset: 0.5,
+                color: Color::rgba(0.0, 0.0, 1.0, 0.25),
+            },
+        ];
+        assert_eq!(stops, &expected);
+    }
+
+    #[test]
+    fn user_space_gradients_map_to_local_space() {
+        let (doc, imported) = import_body(
+            r#"<radialGradient id="g" gradientUnits="userSpaceOnUse" cx="60" cy="40" r="10">
+                 <stop offset="0" stop-color="white"/>
+                 <stop offset="1" stop-color="black"/>
+               </radialGradient>
+               <circle cx="50" cy="40" r="10" fill="url(#g)"/>"#,
+        );
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        let layer = layer(&doc, &imported, 0);
+        let Some(Fill::RadialGradient { center, radius, .. }) = &shape(&layer.children()[0]).fill
+        else {
+            panic!("not a radial gradient");
+        };
+        assert_near(*center, Point::new(10.0, 0.0));
+        assert!((radius - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn current_color_and_inherit() {
+        let (doc, imported) = import_body(
+            r##"<g style="color: #ff0000" stroke="#00ff00" stroke-width="3">
+                 <rect width="10" height="10" fill="currentColor" stroke="inherit"/>
+                 <circle r="5" color="#0000ff" fill="currentColor" stroke-width="inherit"/>
+               </g>"##,
+        );
+        let layer = layer(&doc, &imported, 0);
+        let rect = shape(&layer.children()[0]);
+        assert_eq!(rect.fill, Some(Fill::Solid(Color::rgb(1.0, 0.0, 0.0))));
+        let stroke = rect.stroke.as_ref().unwrap();
+        assert_eq!(stroke.color, Color::rgb(0.0, 1.0, 0.0));
+        assert_eq!(stroke.width, 3.0);
+
+        let circle = shape(&layer.children()[1]);
+        assert_eq!(circle.fill, Some(Fill::Solid(Color::rgb(0.0, 0.0, 1.0))));
+        assert_eq!(circle.stroke.as_ref().map(|s| s.width), Some(3.0));
+    }
+
+    #[test]
+    fn unsupported_elements_are_warned_about() {
+        let (doc, imported) = import_body(
+            r#"<text>one</text>
+               <rect width="10" height="10"/>
+               <text>two</text>
+               <image width="10" height="10"/>"#,
+        );
+        assert_eq!(
+            imported.warnings,
+            [
+                "<image> elements are not supported and were skipped",
+                "<text> elements are not supported and were skipped (2 times)",
+            ]
+        );
+        assert_eq!(layer(&doc, &imported, 0).children().len(), 1);
+
+        let (_, imported) = import_body("<text>only</text>");
+        assert!(imported.layers.is_empty());
+        assert!(imported
+            .warnings
+            .contains(&"the file contains nothing that can be imported".to_string()));
+    }
+}
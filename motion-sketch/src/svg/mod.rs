@@ -0,0 +1,8 @@
+// SVG interchange: exporting frames as static artwork and importing
+// existing vector art as editable layers.
+
+mod export;
+mod import;
+
+pub use export::{export, save};
+pub use import::{import, import_file, Imported};
@@ -0,0 +1,1065 @@
+// Timeline panel.
+//
+// Every layer gets a summary row showing the keyframes of everything inside
+// it, followed by one row per animated property of the layer and its nodes.
+// Summary rows of cel layers also show one cell per drawing exposure; the
+// right edge of a cell drags to retime its hold.
+// The panel is drawn with skia through `render::rasterize`, the same raster
+// path as the viewport, so a document and view state always produce the
+// same pixels. Pointer handling works in panel pixels and lives here too;
+// the app only forwards events.
+
+use anyhow::Result;
+use skia_safe::{Canvas, Color4f, Font, FontMgr, FontStyle, Paint, PaintStyle, Path, Rect};
+
+use crate::animation::{self, Keyframe, Property, Track, Value};
+use crate::cel::{self, Exposure};
+use crate::easing::Easing;
+use crate::render;
+use crate::scene::{Document, Node, NodeId};
+use crate::tools::Modifiers;
+
+pub const RULER_HEIGHT: f32 = 24.0;
+pub const ROW_HEIGHT: f32 = 20.0;
+/// Width of the row label column.
+pub const HEADER_WIDTH: f32 = 160.0;
+/// Pick radius around a keyframe diamond in pixels.
+const KEY_RADIUS: f32 = 5.0;
+/// Pick distance from the right edge of a cel cell in pixels.
+const CELL_EDGE: f32 = 4.0;
+/// Zoom limits in pixels per frame.
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 64.0;
+/// Minimum distance between labelled ruler ticks in pixels.
+const TICK_SPACING: f32 = 60.0;
+
+/// One keyframe of one node's track.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyRef {
+    pub node: NodeId,
+    pub property: Property,
+    pub frame: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub node: NodeId,
+    /// `None` for a layer's summary row.
+    pub property: Option<Property>,
+    pub label: String,
+    pub depth: usize,
+}
+
+/// Rows top to bottom: layers top-most first, each followed by its tracks.
+pub fn rows(doc: &Document) -> Vec<Row> {
+    fn tracks(node: &Node, depth: usize, rows: &mut Vec<Row>) {
+        for track in node
+            .animation
+            .tracks
+            .iter()
+            .filter(|t| !t.keyframes.is_empty())
+        {
+            rows.push(Row {
+                node: node.id,
+                property: Some(track.property),
+                label: format!("{} {}", node.name, track.property.label()),
+                depth,
+            });
+        }
+        for child in node.children() {
+            tracks(child, depth + 1, rows);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for layer in doc.layers.iter().rev() {
+        rows.push(Row {
+            node: layer.id,
+            property: None,
+            label: layer.name.clone(),
+            depth: 0,
+        });
+        tracks(layer, 1, &mut rows);
+    }
+    rows
+}
+
+/// Keyframes shown in `row`.
+pub fn row_keys(doc: &Document, row: &Row) -> Vec<KeyRef> {
+    fn collect(node: &Node, property: Option<Property>, deep: bool, keys: &mut Vec<KeyRef>) {
+        for track in &node.animation.tracks {
+            if property.is_some_and(|p| p != track.property) {
+                continue;
+            }
+            keys.extend(track.keyframes.iter().map(|k| KeyRef {
+                node: node.id,
+                property: track.property,
+                frame: k.frame,
+            }));
+        }
+        if deep {
+            for child in node.children() {
+                collect(child, property, deep, keys);
+            }
+        }
+    }
+
+    let mut keys = Vec::new();
+    if let Some(node) = doc.get(row.node) {
+        collect(node, row.property, row.property.is_none(), &mut keys);
+    }
+    keys
+}
+
+/// Moves keyframes by `delta` frames, updating `keys` to match. Keyframes
+/// landing on an existing one replace it.
+pub fn move_keys(doc: &mut Document, keys: &mut [KeyRef], delta: f32) {
+    // Lift everything first so moved keyframes cannot replace each other.
+    let mut lifted: Vec<(NodeId, Property, Keyframe)> = Vec::new();
+    for key in keys.iter() {
+        let Some(node) = doc.get_mut(key.node) else {
+            continue;
+        };
+        let tracks = &mut node.animation.tracks;
+        if let Some(track) = tracks.iter_mut().find(|t| t.property == key.property) {
+            if let Some(keyframe) = track.remove(key.frame) {
+                lifted.push((key.node, key.property, keyframe));
+            }
+        }
+    }
+    for (id, property, mut keyframe) in lifted {
+        if let Some(node) = doc.get_mut(id) {
+            keyframe.frame += delta;
+            node.animation.track_mut(property).insert(keyframe);
+        }
+    }
+    for key in keys {
+        key.frame += delta;
+    }
+}
+
+fn keyframe<'a>(doc: &'a Document, key: &KeyRef) -> Option<&'a Keyframe> {
+    doc.get(key.node)?
+        .animation
+        .track(key.property)?
+        .keyframe_at(key.frame)
+}
+
+/// Copied keyframe, positioned relative to the earliest copied one.
+#[derive(Clone, Debug)]
+struct CopiedKey {
+    node: NodeId,
+    property: Property,
+    offset: f32,
+    value: Value,
+    easing: Easing,
+}
+
+/// Editor state the timeline reads and changes.
+pub struct TimelineContext<'a> {
+    pub document: &'a mut Document,
+    /// Playhead position in frames.
+    pub frame: &'a mut f32,
+    /// Node selection, set by clicking a row label.
+    pub selection: &'a mut Vec<NodeId>,
+    pub layer: &'a mut Option<NodeId>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Drag {
+    Playhead,
+    /// Moving the selected keyframes; `grab` is the frame under the pointer
+    /// at the start and `moved` the offset of the keys from where they
+    /// started.
+    Keys {
+        grab: f32,
+        moved: f32,
+    },
+    Box {
+        start: (f32, f32),
+        current: (f32, f32),
+    },
+    /// Dragging the end of a cel exposure that starts at `start`.
+    Hold {
+        drawing: NodeId,
+        start: u32,
+    },
+}
+
+pub struct Timeline {
+    /// Pixels per frame.
+    pub zoom: f32,
+    /// Frame at the left edge of the keyframe area.
+    pub scroll: f32,
+    /// Index of the top-most visible row.
+    pub first_row: usize,
+    /// Label the ruler in seconds instead of frames.
+    pub show_seconds: bool,
+    pub selection: Vec<KeyRef>,
+    drag: Option<Drag>,
+    /// Selection before a shift box-select started.
+    box_base: Vec<KeyRef>,
+    /// Keys being dragged at their starting frames, and the tracks they
+    /// belong to as they were then. Each move of a key drag starts over
+    /// from these, so keys dragged across are back once the drag moves on
+    /// and only the drop position replaces anything.
+    drag_keys: Vec<KeyRef>,
+    drag_tracks: Vec<(NodeId, Track)>,
+    clipboard: Vec<CopiedKey>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            zoom: 8.0,
+            scroll: 0.0,
+            first_row: 0,
+            show_seconds: false,
+            selection: Vec::new(),
+            drag: None,
+            box_base: Vec::new(),
+            drag_keys: Vec::new(),
+            drag_tracks: Vec::new(),
+            clipboard: Vec::new(),
+        }
+    }
+}
+
+impl Timeline {
+    pub fn x_of(&self, frame: f32) -> f32 {
+        HEADER_WIDTH + (frame - self.scroll) * self.zoom
+    }
+
+    pub fn frame_at(&self, x: f32) -> f32 {
+        self.scroll + (x - HEADER_WIDTH) / self.zoom
+    }
+
+    fn row_at(&self, y: f32, count: usize) -> Option<usize> {
+        if y < RULER_HEIGHT {
+            return None;
+        }
+        let index = ((y - RULER_HEIGHT) / ROW_HEIGHT) as usize + self.first_row;
+        (index < count).then_some(index)
+    }
+
+    fn row_top(&self, index: usize) -> f32 {
+        RULER_HEIGHT + (index as f32 - self.first_row as f32) * ROW_HEIGHT
+    }
+
+    /// Keyframes under the pointer: every key of the row at the hit frame.
+    fn keys_at(&self, doc: &Document, rows: &[Row], x: f32, y: f32) -> Vec<KeyRef> {
+        let Some(row) = self.row_at(y, rows.len()).map(|i| &rows[i]) else {
+            return Vec::new();
+        };
+        let keys = row_keys(doc, row);
+        let nearest = keys
+            .iter()
+            .map(|k| (k.frame, (self.x_of(k.frame) - x).abs()))
+            .filter(|(_, distance)| *distance <= KEY_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((frame, _)) => keys.into_iter().filter(|k| k.frame == frame).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Exposures of the cel layer shown in the row under `y`, if any.
+    fn cells_at(&self, doc: &Document, rows: &[Row], y: f32) -> Option<(NodeId, Vec<Exposure>)> {
+        let row = &rows[self.row_at(y, rows.len())?];
+        let layer = doc.get(row.node).filter(|node| node.is_cel_layer())?;
+        row.property
+            .is_none()
+            .then(|| (layer.id, cel::exposures(layer)))
+    }
+
+    fn seek(&self, ctx: &mut TimelineContext, x: f32) {
+        let last = ctx.document.duration.saturating_sub(1) as f32;
+        *ctx.frame = self.frame_at(x).round().clamp(0.0, last);
+    }
+
+    pub fn pointer_down(
+        &mut self,
+        ctx: &mut TimelineContext,
+        x: f32,
+        y: f32,
+        modifiers: Modifiers,
+    ) {
+        if x >= HEADER_WIDTH && y < RULER_HEIGHT {
+            self.drag = Some(Drag::Playhead);
+            self.seek(ctx, x);
+            return;
+        }
+
+        let rows = rows(ctx.document);
+        if x < HEADER_WIDTH {
+            if let Some(index) = self.row_at(y, rows.len()) {
+                let node = rows[index].node;
+                *ctx.selection = vec![node];
+                *ctx.layer = ctx.document.layer_of(node);
+            }
+            return;
+        }
+
+        let hit = self.keys_at(ctx.document, &rows, x, y);
+        if hit.is_empty() {
+            if let Some((layer, cells)) = self.cells_at(ctx.document, &rows, y) {
+                let edge = cells
+                    .iter()
+                    .find(|cell| (self.x_of(cell.end() as f32) - x).abs() <= CELL_EDGE);
+                if let Some(cell) = edge {
+                    self.drag = Some(Drag::Hold {
+                        drawing: cell.drawing,
+                        start: cell.start,
+                    });
+                    return;
+                }
+                // Clicking inside a cell picks the layer and shows that frame.
+                if cells.iter().any(|cell| cell.contains(self.frame_at(x))) {
+                    *ctx.layer = Some(layer);
+                    self.drag = Some(Drag::Playhead);
+                    self.seek(ctx, x);
+                    return;
+                }
+            }
+            if !modifiers.shift {
+                self.selection.clear();
+            }
+            self.box_base = self.selection.clone();
+            self.drag = Some(Drag::Box {
+                start: (x, y),
+                current: (x, y),
+            });
+            return;
+        }
+
+        let selected = hit.iter().all(|k| self.selection.contains(k));
+        if modifiers.shift && selected {
+            self.selection.retain(|k| !hit.contains(k));
+            return;
+        }
+        if modifiers.shift {
+            for key in hit {
+                if !self.selection.contains(&key) {
+                    self.selection.push(key);
+                }
+            }
+        } else if !selected {
+            self.selection = hit;
+        }
+        self.drag_keys = self.selection.clone();
+        self.drag_tracks.clear();
+        for key in &self.selection {
+            let known =
+                |(id, track): &(NodeId, Track)| *id == key.node && track.property == key.property;
+            if self.drag_tracks.iter().any(known) {
+                continue;
+            }
+            if let Some(track) = ctx
+                .document
+                .get(key.node)
+                .and_then(|n| n.animation.track(key.property))
+            {
+                self.drag_tracks.push((key.node, track.clone()));
+            }
+        }
+        self.drag = Some(Drag::Keys {
+            grab: self.frame_at(x).round(),
+            moved: 0.0,
+        });
+    }
+
+    pub fn pointer_move(&mut self, ctx: &mut TimelineContext, x: f32, y: f32) {
+        match self.drag {
+            Some(Drag::Playhead) => self.seek(ctx, x),
+            Some(Drag::Keys { grab, moved }) => {
+                // Whole frames only, and never before frame 0.
+                let earliest = self
+                    .drag_keys
+                    .iter()
+                    .map(|k| k.frame)
+                    .fold(f32::MAX, f32::min);
+                let offset = (self.frame_at(x).round() - grab).max(-earliest);
+                if offset != moved && earliest != f32::MAX {
+                    for (id, track) in &self.drag_tracks {
+                        if let Some(node) = ctx.document.get_mut(*id) {
+                            *node.animation.track_mut(track.property) = track.clone();
+                        }
+                    }
+                    self.selection = self.drag_keys.clone();
+                    move_keys(ctx.document, &mut self.selection, offset);
+                    self.drag = Some(Drag::Keys {
+                        grab,
+                        moved: offset,
+                    });
+                }
+            }
+            Some(Drag::Hold { drawing, start }) => {
+                let hold = (self.frame_at(x).round() - start as f32).max(1.0) as u32;
+                let current = ctx.document.get(drawing).and_then(cel::hold);
+                if current.is_some_and(|current| current != hold) {
+                    // Only fails if the drawing vanished under the drag.
+                    let _ = cel::set_hold(ctx.document, drawing, hold);
+                }
+            }
+            Some(Drag::Box { start, .. }) => {
+                self.drag = Some(Drag::Box {
+                    start,
+                    current: (x, y),
+                });
+                self.selection = self.box_base.clone();
+                let rows = rows(ctx.document);
+                let (left, right) = (start.0.min(x), start.0.max(x));
+                let (top, bottom) = (start.1.min(y), start.1.max(y));
+                for (index, row) in rows.iter().enumerate() {
+                    let center = self.row_top(index) + ROW_HEIGHT / 2.0;
+                    if center < top || center > bottom {
+                        continue;
+                    }
+                    for key in row_keys(ctx.document, row) {
+                        let kx = self.x_of(key.frame);
+                        if kx >= left && kx <= right && !self.selection.contains(&key) {
+                            self.selection.push(key);
+                        }
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn pointer_up(&mut self) {
+        self.drag = None;
+        self.box_base.clear();
+        self.drag_keys.clear();
+        self.drag_tracks.clear();
+    }
+
+    /// Mouse wheel: zooms around the pointer with `zoom`, otherwise
+    /// scrolls rows vertically and time horizontally.
+    pub fn scroll_by(&mut self, doc: &Document, x: f32, dx: f32, dy: f32, zoom: bool) {
+        if zoom {
+            self.zoom_by(1.1f32.powf(dy / 40.0), x);
+            return;
+        }
+        self.scroll = (self.scroll - dx / self.zoom).max(0.0);
+        let rows = rows(doc).len();
+        if dy < 0.0 {
+            self.first_row = (self.first_row + 1).min(rows.saturating_sub(1));
+        } else if dy > 0.0 {
+            self.first_row = self.first_row.saturating_sub(1);
+        }
+    }
+
+    /// Scales the time axis by `factor`, keeping the frame at `pivot_x`
+    /// under it.
+    pub fn zoom_by(&mut self, factor: f32, pivot_x: f32) {
+        let pivot = self.frame_at(pivot_x);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.scroll = (pivot - (pivot_x - HEADER_WIDTH) / self.zoom).max(0.0);
+    }
+
+    /// Drops selected keys that no longer exist, e.g. after an undo.
+    pub fn forget_missing(&mut self, doc: &Document) {
+        self.selection.retain(|key| keyframe(doc, key).is_some());
+    }
+
+    pub fn copy(&mut self, doc: &Document) -> bool {
+        let earliest = self
+            .selection
+            .iter()
+            .map(|k| k.frame)
+            .fold(f32::MAX, f32::min);
+        self.clipboard = self
+            .selection
+            .iter()
+            .filter_map(|key| {
+                let keyframe = keyframe(doc, key)?;
+                Some(CopiedKey {
+                    node: key.node,
+                    property: key.property,
+                    offset: key.frame - earliest,
+                    value: keyframe.value.clone(),
+                    easing: keyframe.easing,
+                })
+            })
+            .collect();
+        !self.clipboard.is_empty()
+    }
+
+    /// Pastes the copied keyframes starting at `frame`. Keys copied from a
+    /// single node go to every node in `targets` when there are any;
+    /// otherwise each key returns to the node it came from. The pasted keys
+    /// become the selection.
+    pub fn paste(&mut self, doc: &mut Document, frame: f32, targets: &[NodeId]) -> bool {
+        let single_source = self
+            .clipboard
+            .first()
+            .is_some_and(|first| self.clipboard.iter().all(|k| k.node == first.node));
+        let mut pasted = Vec::new();
+        for copied in &self.clipboard {
+            let nodes = if single_source && !targets.is_empty() {
+                targets.to_vec()
+            } else {
+                vec![copied.node]
+            };
+            for id in nodes {
+                let Some(node) = doc.get_mut(id) else {
+                    continue;
+                };
+                // Skip properties the target does not have, e.g. a fill
+                // color pasted onto a group.
+                if animation::sample(node, frame)
+                    .get(copied.property)
+                    .is_none()
+                {
+                    continue;
+                }
+                let key = KeyRef {
+                    node: id,
+                    property: copied.property,
+                    frame: frame + copied.offset,
+                };
+                node.animation.track_mut(copied.property).insert(Keyframe {
+                    frame: key.frame,
+                    value: copied.value.clone(),
+                    easing: copied.easing,
+                });
+                pasted.push(key);
+            }
+        }
+        if pasted.is_empty() {
+            return false;
+        }
+        self.selection = pasted;
+        true
+    }
+
+    pub fn delete_selected(&mut self, doc: &mut Document) -> bool {
+        let mut deleted = false;
+        for key in self.selection.drain(..) {
+            let Some(node) = doc.get_mut(key.node) else {
+                continue;
+            };
+            let tracks = &mut node.animation.tracks;
+            if let Some(track) = tracks.iter_mut().find(|t| t.property == key.property) {
+                deleted |= track.remove(key.frame).is_some();
+            }
+        }
+        deleted
+    }
+
+    /// Frames between labelled ruler ticks at the current zoom.
+    fn tick_step(&self, frame_rate: f32) -> f32 {
+        let steps: Vec<f32> = if self.show_seconds {
+            [0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 300.0]
+                .iter()
+                .map(|secs| secs * frame_rate)
+                .collect()
+        } else {
+            vec![1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0]
+        };
+        steps
+            .iter()
+            .copied()
+            .find(|step| step * self.zoom >= TICK_SPACING)
+            .unwrap_or(steps[steps.len() - 1])
+    }
+
+    fn tick_label(&self, doc: &Document, frame: f32) -> String {
+        if !self.show_seconds {
+            return format!("{frame}");
+        }
+        let secs = format!("{:.2}", doc.frame_to_secs(frame));
+        format!("{}s", secs.trim_end_matches('0').trim_end_matches('.'))
+    }
+
+    /// One cell per exposure of a cel layer's drawings. The drawing at the
+    /// playhead is highlighted and empty drawings are drawn hollow.
+    fn draw_cells(
+        &self,
+        canvas: &Canvas,
+        layer: &Node,
+        playhead: f32,
+        top: f32,
+        font: Option<&Font>,
+    ) {
+        for (index, cell) in cel::exposures(layer).iter().enumerate() {
+            let rect = Rect::from_ltrb(
+                self.x_of(cell.start as f32) + 1.0,
+                top + 3.0,
+                self.x_of(cell.end() as f32) - 1.0,
+                top + ROW_HEIGHT - 3.0,
+            );
+            let empty = layer
+                .children()
+                .iter()
+                .find(|d| d.id == cell.drawing)
+                .is_some_and(|d| d.children().is_empty());
+            let shade = if cell.contains(playhead) { 0.5 } else { 0.36 };
+            let mut paint = Paint::new(Color4f::new(shade, shade + 0.04, shade + 0.1, 1.0), None);
+            paint.set_anti_alias(true);
+            if empty {
+                paint.set_style(PaintStyle::Stroke);
+            }
+            canvas.draw_rect(rect, &paint);
+            if let Some(font) = font.filter(|_| rect.width() >= 14.0) {
+                let label = Paint::new(Color4f::new(0.9, 0.9, 0.9, 1.0), None);
+                let origin = (rect.left + 3.0, rect.bottom - 3.0);
+                canvas.draw_str(format!("{}", index + 1), origin, font, &label);
+            }
+        }
+    }
+
+    /// Draws the panel into a `width` x `height` pixel area, marking the
+    /// playback loop range on the ruler if there is one.
+    pub fn draw(
+        &self,
+        canvas: &Canvas,
+        doc: &Document,
+        playhead: f32,
+        loop_range: Option<(u32, u32)>,
+        width: f32,
+        height: f32,
+    ) {
+        let font = label_font();
+        let fill = |r: f32, g: f32, b: f32| {
+            let mut paint = Paint::new(Color4f::new(r, g, b, 1.0), None);
+            paint.set_anti_alias(true);
+            paint
+        };
+        let text = fill(0.85, 0.85, 0.85);
+        let dim = fill(0.55, 0.55, 0.55);
+        let accent = fill(0.94, 0.63, 0.19);
+
+        canvas.clear(Color4f::new(0.17, 0.17, 0.17, 1.0));
+
+        // Time past the end of the animation.
+        let end = self.x_of(doc.duration as f32).max(HEADER_WIDTH);
+        if end < width {
+            let rect = Rect::from_ltrb(end, RULER_HEIGHT, width, height);
+            canvas.draw_rect(rect, &fill(0.12, 0.12, 0.12));
+        }
+
+        let rows = rows(doc);
+        for (index, row) in rows.iter().enumerate().skip(self.first_row) {
+            let top = self.row_top(index);
+            if top > height {
+                break;
+            }
+            let center = top + ROW_HEIGHT / 2.0;
+            if row.property.is_none() {
+                let rect = Rect::from_ltrb(0.0, top, width, top + ROW_HEIGHT);
+                canvas.draw_rect(rect, &fill(0.22, 0.22, 0.22));
+            }
+
+            canvas.save();
+            canvas.clip_rect(
+                Rect::from_ltrb(HEADER_WIDTH, RULER_HEIGHT, width, height),
+                None,
+                None,
+            );
+            if let Some(layer) = doc.get(row.node).filter(|n| n.is_cel_layer()) {
+                if row.property.is_none() {
+                    self.draw_cells(canvas, layer, playhead, top, font.as_ref());
+                }
+            }
+            let mut drawn: Vec<f32> = Vec::new();
+            for key in row_keys(doc, row) {
+                let selected = self.selection.contains(&key);
+                // Summary rows show one diamond per frame.
+                if drawn.contains(&key.frame) && !selected {
+                    continue;
+                }
+                drawn.push(key.frame);
+                let paint = match (selected, row.property) {
+                    (true, _) => &accent,
+                    (false, Some(_)) => &text,
+                    (false, None) => &dim,
+                };
+                let radius = if row.property.is_some() { 5.0 } else { 4.0 };
+                canvas.draw_path(&diamond(self.x_of(key.frame), center, radius), paint);
+            }
+            canvas.restore();
+
+            let header = Rect::from_ltrb(0.0, top, HEADER_WIDTH, top + ROW_HEIGHT);
+            canvas.draw_rect(header, &fill(0.14, 0.14, 0.14));
+            if let Some(font) = &font {
+                let x = 6.0 + row.depth as f32 * 10.0;
+                let paint = if row.property.is_some() { &dim } else { &text };
+                canvas.save();
+                canvas.clip_rect(header, None, None);
+                canvas.draw_str(&row.label, (x, center + 4.0), font, paint);
+                canvas.restore();
+            }
+        }
+
+        // Ruler.
+        canvas.draw_rect(
+            Rect::from_ltrb(0.0, 0.0, width, RULER_HEIGHT),
+            &fill(0.24, 0.24, 0.24),
+        );
+        if let Some((start, end)) = loop_range {
+            let left = self.x_of(start as f32).max(HEADER_WIDTH);
+            let right = self.x_of(end as f32);
+            if right > left {
+                let band = Rect::from_ltrb(left, 0.0, right, RULER_HEIGHT);
+                canvas.draw_rect(band, &Paint::new(Color4f::new(0.3, 0.55, 0.9, 0.35), None));
+            }
+        }
+        let step = self.tick_step(doc.frame_rate);
+        let mut tick = (self.scroll / step).floor() * step;
+        let mut line = fill(0.55, 0.55, 0.55);
+        line.set_style(PaintStyle::Stroke);
+        line.set_stroke_width(1.0);
+        while self.x_of(tick) < width {
+            let x = self.x_of(tick);
+            if x >= HEADER_WIDTH {
+                canvas.draw_line((x, RULER_HEIGHT - 8.0), (x, RULER_HEIGHT), &line);
+                if let Some(font) = &font {
+                    let label = self.tick_label(doc, tick);
+                    canvas.draw_str(&label, (x + 3.0, RULER_HEIGHT - 10.0), font, &text);
+                }
+            }
+            // Unlabelled tick per frame once frames are far enough apart.
+            if self.zoom >= 6.0 {
+                let mut frame = tick + 1.0;
+                while frame < tick + step {
+                    let x = self.x_of(frame);
+                    if x >= HEADER_WIDTH {
+                        canvas.draw_line((x, RULER_HEIGHT - 4.0), (x, RULER_HEIGHT), &line);
+                    }
+                    frame += 1.0;
+                }
+            }
+            tick += step;
+        }
+
+        // Playhead.
+        let x = self.x_of(playhead);
+        if x >= HEADER_WIDTH {
+            let red = fill(0.9, 0.25, 0.25);
+            canvas.draw_rect(Rect::from_ltrb(x - 0.5, 0.0, x + 0.5, height), &red);
+            let mut handle = Path::new();
+            handle.move_to((x - 5.0, 0.0));
+            handle.line_to((x + 5.0, 0.0));
+            handle.line_to((x + 5.0, 8.0));
+            handle.line_to((x, 13.0));
+            handle.line_to((x - 5.0, 8.0));
+            handle.close();
+            canvas.draw_path(&handle, &red);
+        }
+
+        if let Some(Drag::Box { start, current }) = self.drag {
+            let rect = Rect::from_ltrb(
+                start.0.min(current.0),
+                start.1.min(current.1),
+                start.0.max(current.0),
+                start.1.max(current.1),
+            );
+            canvas.draw_rect(rect, &Paint::new(Color4f::new(0.2, 0.6, 1.0, 0.2), None));
+            let mut outline = Paint::new(Color4f::new(0.2, 0.6, 1.0, 1.0), None);
+            outline.set_style(PaintStyle::Stroke);
+            canvas.draw_rect(rect, &outline);
+        }
+    }
+}
+
+fn diamond(x: f32, y: f32, radius: f32) -> Path {
+    let mut path = Path::new();
+    path.move_to((x, y - radius));
+    path.line_to((x + radius, y));
+    path.line_to((x, y + radius));
+    path.line_to((x - radius, y));
+    path.close();
+    path
+}
+
+/// Labels are skipped on systems without any usable font.
+fn label_font() -> Option<Font> {
+    let typeface = FontMgr::new().legacy_make_typeface(None, FontStyle::normal())?;
+    Some(Font::new(typeface, 11.0))
+}
+
+/// Renders the panel to pixels.
+pub fn render(
+    doc: &Document,
+    timeline: &Timeline,
+    playhead: f32,
+    loop_range: Option<(u32, u32)>,
+    width: u32,
+    height: u32,
+) -> Result<render::Frame> {
+    render::rasterize(width, height, |canvas| {
+        timeline.draw(
+            canvas,
+            doc,
+            playhead,
+            loop_range,
+            width as f32,
+            height as f32,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Geometry, Point, Shape};
+
+    /// Timeline document: a bottom layer holding an animated box and a top
+    /// layer whose own opacity is animated.
+    fn document() -> (Document, NodeId, NodeId) {
+        let mut doc = Document::new(100, 100);
+        let bottom = doc.add_layer("Bottom");
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(10.0, 10.0),
+            corner_radius: 0.0,
+        });
+        let rect = doc.add_shape(bottom, "Box", shape).unwrap();
+        let top = doc.add_layer("Top");
+        let node = doc.get_mut(rect).unwrap();
+        for frame in [0.0, 10.0] {
+            let value = Value::Point(Point::new(frame, 0.0));
+            node.animation
+                .set_keyframe(Property::Position, frame, value);
+        }
+        let node = doc.get_mut(top).unwrap();
+        for (frame, opacity) in [(0.0, 1.0), (5.0, 0.0)] {
+            node.animation
+                .set_keyframe(Property::Opacity, frame, Value::Scalar(opacity));
+        }
+        (doc, rect, top)
+    }
+
+    /// One line per row: indented label, then the frames of its keys.
+    fn snapshot(doc: &Document) -> String {
+        rows(doc)
+            .iter()
+            .map(|row| {
+                let frames: Vec<String> = row_keys(doc, row)
+                    .iter()
+                    .map(|key| key.frame.to_string())
+                    .collect();
+                let indent = "  ".repeat(row.depth);
+                format!("{indent}{} | {}\n", row.label, frames.join(" "))
+            })
+            .collect()
+    }
+
+    fn context<'a>(
+        document: &'a mut Document,
+        frame: &'a mut f32,
+        selection: &'a mut Vec<NodeId>,
+        layer: &'a mut Option<NodeId>,
+    ) -> TimelineContext<'a> {
+        TimelineContext {
+            document,
+            frame,
+            selection,
+            layer,
+        }
+    }
+
+    #[test]
+    fn rows_list_layers_top_first_with_their_tracks() {
+        let (doc, _, _) = document();
+        let expected = "\
+Top | 0 5
+  Top Opacity | 0 5
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(&doc), expected);
+    }
+
+    #[test]
+    fn dragged_keys_only_replace_keys_where_they_are_dropped() {
+        let (mut doc, _, top) = document();
+        let (mut frame, mut selection, mut layer) = (0.0, Vec::new(), None);
+        let mut timeline = Timeline::default();
+        let mut ctx = context(&mut doc, &mut frame, &mut selection, &mut layer);
+        let opacity_row = RULER_HEIGHT + ROW_HEIGHT * 1.5;
+        let opacity = |doc: &Document, frame: f32| {
+            let track = doc.get(top).unwrap().animation.track(Property::Opacity);
+            track.unwrap().keyframe_at(frame).map(|k| k.value.clone())
+        };
+
+        // Over the key at 5 and on to 8: the key at 5 is back.
+        timeline.pointer_down(
+            &mut ctx,
+            timeline.x_of(0.0),
+            opacity_row,
+            Modifiers::default(),
+        );
+        timeline.pointer_move(&mut ctx, timeline.x_of(5.0), opacity_row);
+        timeline.pointer_move(&mut ctx, timeline.x_of(8.0), opacity_row);
+        let expected = "\
+Top | 5 8
+  Top Opacity | 5 8
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(ctx.document), expected);
+        assert_eq!(opacity(ctx.document, 5.0), Some(Value::Scalar(0.0)));
+        assert_eq!(timeline.selection[0].frame, 8.0);
+
+        // Dropped on it, the dragged key wins.
+        timeline.pointer_move(&mut ctx, timeline.x_of(5.0), opacity_row);
+        timeline.pointer_up();
+        let expected = "\
+Top | 5
+  Top Opacity | 5
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(ctx.document), expected);
+        assert_eq!(opacity(ctx.document, 5.0), Some(Value::Scalar(1.0)));
+    }
+
+    #[test]
+    fn copy_paste_and_delete() {
+        let (mut doc, _, top) = document();
+        let mut timeline = Timeline {
+            selection: row_keys(&doc, &rows(&doc)[1]),
+            ..Timeline::default()
+        };
+        assert!(timeline.copy(&doc));
+        assert!(timeline.paste(&mut doc, 20.0, &[]));
+        assert!(timeline.selection.iter().all(|key| key.node == top));
+        let expected = "\
+Top | 0 5 20 25
+  Top Opacity | 0 5 20 25
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(&doc), expected);
+        assert!(timeline.delete_selected(&mut doc));
+        timeline.forget_missing(&doc);
+        assert!(timeline.selection.is_empty());
+        let expected = "\
+Top | 0 5
+  Top Opacity | 0 5
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(&doc), expected);
+    }
+
+    #[test]
+    fn seeking_stops_at_the_last_frame() {
+        let (mut doc, _, _) = document();
+        let (mut frame, mut selection, mut layer) = (0.0, Vec::new(), None);
+        let mut timeline = Timeline::default();
+        let mut ctx = context(&mut doc, &mut frame, &mut selection, &mut layer);
+        let ruler = RULER_HEIGHT / 2.0;
+
+        timeline.pointer_down(&mut ctx, timeline.x_of(30.2), ruler, Modifiers::default());
+        assert_eq!(*ctx.frame, 30.0);
+        timeline.pointer_move(&mut ctx, timeline.x_of(500.0), ruler);
+        assert_eq!(*ctx.frame, 119.0);
+        timeline.pointer_move(&mut ctx, 0.0, ruler);
+        assert_eq!(*ctx.frame, 0.0);
+        timeline.pointer_up();
+    }
+
+    #[test]
+    fn dragging_keys_moves_whole_frames_and_not_before_zero() {
+        let (mut doc, _, _) = document();
+        let (mut frame, mut selection, mut layer) = (0.0, Vec::new(), None);
+        let mut timeline = Timeline::default();
+        let mut ctx = context(&mut doc, &mut frame, &mut selection, &mut layer);
+        let row = |index: usize| RULER_HEIGHT + ROW_HEIGHT * (index as f32 + 0.5);
+
+        timeline.pointer_down(&mut ctx, timeline.x_of(5.0), row(1), Modifiers::default());
+        assert_eq!(timeline.selection.len(), 1);
+        timeline.pointer_move(&mut ctx, timeline.x_of(7.4), row(1));
+        timeline.pointer_up();
+        let expected = "\
+Top | 0 7
+  Top Opacity | 0 7
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(ctx.document), expected);
+
+        timeline.pointer_down(&mut ctx, timeline.x_of(7.0), row(1), Modifiers::default());
+        timeline.pointer_move(&mut ctx, timeline.x_of(-20.0), row(1));
+        timeline.pointer_up();
+        let expected = "\
+Top | 0
+  Top Opacity | 0
+Bottom | 0 10
+    Box Position | 0 10
+";
+        assert_eq!(snapshot(ctx.document), expected);
+    }
+
+    #[test]
+    fn box_select_picks_keys_inside_the_box() {
+        let (mut doc, rect, _) = document();
+        let (mut frame, mut selection, mut layer) = (0.0, Vec::new(), None);
+        let mut timeline = Timeline::default();
+        let mut ctx = context(&mut doc, &mut frame, &mut selection, &mut layer);
+        let top = RULER_HEIGHT + ROW_HEIGHT * 3.0;
+        let bottom = RULER_HEIGHT + ROW_HEIGHT * 4.0;
+
+        timeline.pointer_down(&mut ctx, timeline.x_of(8.0), top, Modifiers::default());
+        timeline.pointer_move(&mut ctx, timeline.x_of(12.0), bottom);
+        timeline.pointer_up();
+        let expected = [KeyRef {
+            node: rect,
+            property: Property::Position,
+            frame: 10.0,
+        }];
+        assert_eq!(timeline.selection, expected);
+    }
+
+    #[test]
+    fn render_draws_rows_keys_and_playhead() {
+        let (mut doc, _, top) = document();
+        doc.duration = 20;
+        let timeline = Timeline {
+            selection: vec![KeyRef {
+                node: top,
+                property: Property::Opacity,
+                frame: 5.0,
+            }],
+            ..Timeline::default()
+        };
+        // Frame 15.0625 puts the one pixel wide playhead on whole pixels.
+        let frame = render(&doc, &timeline, 15.0625, None, 400, 120).unwrap();
+        let pixel = |x: u32, y: u32| {
+            let start = ((y * frame.width + x) * 4) as usize;
+            <[u8; 4]>::try_from(&frame.pixels[start..start + 4]).unwrap()
+        };
+        let shade = |r: f32, g: f32, b: f32| [r, g, b, 1.0].map(|v| (v * 255.0).round() as u8);
+        let check = |x: u32, y: u32, expected: [u8; 4], what: &str| {
+            let actual = pixel(x, y);
+            let near = actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 1);
+            assert!(
+                near,
+                "{what} at ({x}, {y}): {actual:?}, expected {expected:?}"
+            );
+        };
+        let (accent, text, dim) = (
+            shade(0.94, 0.63, 0.19),
+            shade(0.85, 0.85, 0.85),
+            shade(0.55, 0.55, 0.55),
+        );
+
+        // Frame f is at x = 160 + 8f; rows are 20 pixels tall under the
+        // 24 pixel ruler: Top, Top Opacity, Bottom, Box Position.
+        check(300, 2, shade(0.24, 0.24, 0.24), "ruler");
+        check(150, 94, shade(0.14, 0.14, 0.14), "row header");
+        check(300, 34, shade(0.22, 0.22, 0.22), "summary row");
+        check(300, 54, shade(0.17, 0.17, 0.17), "track row");
+        check(360, 54, shade(0.12, 0.12, 0.12), "track row past the end");
+        check(360, 34, shade(0.22, 0.22, 0.22), "summary row past the end");
+        check(200, 54, accent, "selected key");
+        check(200, 34, accent, "selected key in the summary");
+        check(240, 94, text, "key");
+        check(240, 74, dim, "key in the summary");
+        check(280, 110, shade(0.9, 0.25, 0.25), "playhead");
+        check(282, 110, shade(0.17, 0.17, 0.17), "beside the playhead");
+    }
+}
@@ -0,0 +1,213 @@
+// Freehand brush instrument.
+
+use skia_safe::{Color4f, Paint, PaintStyle, Path};
+
+use super::{OverlayContext, PointerEvent, Tool, ToolContext, ToolOption};
+use crate::animation;
+use crate::scene::{Color, Fill, Geometry, Point, Shape};
+use crate::stroke::{self, BrushPoint};
+
+pub struct BrushTool {
+    /// Stroke width in document units.
+    pub size: f32,
+    /// Simplification tolerance in screen pixels.
+    pub tolerance: f32,
+    /// Moving-average radius in samples.
+    pub smoothing: usize,
+    /// How much fast movements thin the stroke, `0..=1`.
+    pub thinning: f32,
+    /// Length of the tapered ends in document units.
+    pub taper: f32,
+    samples: Vec<Point>,
+    color: Color,
+    pressed: bool,
+}
+
+impl Default for BrushTool {
+    fn default() -> Self {
+        Self {
+            size: 6.0,
+            tolerance: 1.0,
+            smoothing: 2,
+            thinning: 0.5,
+            taper: 20.0,
+            samples: Vec::new(),
+            color: Color::BLACK,
+            pressed: false,
+        }
+    }
+}
+
+impl BrushTool {
+    /// Runs the raw samples through smoothing, width assignment and
+    /// simplification.
+    fn process(&self, pixel_size: f32) -> Vec<BrushPoint> {
+        let smoothed = stroke::smooth(&self.samples, self.smoothing);
+        let points = stroke::widths(&smoothed, self.size, self.thinning, self.taper);
+        stroke::simplify(&points, self.tolerance * pixel_size)
+    }
+}
+
+impl Tool for BrushTool {
+    fn name(&self) -> &'static str {
+        "Brush"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.pressed = true;
+        self.samples = vec![event.position];
+        self.color = ctx
+            .style
+            .stroke
+            .as_ref()
+            .map(|s| s.color)
+            .unwrap_or(Color::BLACK);
+    }
+
+    fn pointer_move(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        if self.pressed {
+            stroke::push_sample(&mut self.samples, event.position, ctx.pixel_size);
+        }
+    }
+
+    fn pointer_up(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        if !self.pressed {
+            return;
+        }
+        self.pressed = false;
+        stroke::push_sample(&mut self.samples, event.position, ctx.pixel_size);
+
+        // Positions and widths are in document units; the shape lives in
+        // the layer's space.
+        let layer = ctx.target_layer();
+        let scale = animation::document_scale(ctx.document, layer, ctx.frame);
+        let mut points = self.process(ctx.pixel_size);
+        for point in &mut points {
+            point.position = animation::to_local(ctx.document, layer, ctx.frame, point.position);
+            point.width /= scale;
+        }
+        self.samples.clear();
+
+        let mut shape = Shape::new(Geometry::Brush(points));
+        shape.fill = Some(Fill::Solid(self.color));
+        if let Ok(id) = ctx.document.add_shape(layer, "Stroke", shape) {
+            *ctx.selection = vec![id];
+        }
+    }
+
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {
+        self.pressed = false;
+        self.samples.clear();
+    }
+
+    fn draw_overlay(&self, canvas: &skia_safe::Canvas, ctx: &OverlayContext) {
+        if !self.pressed || self.samples.is_empty() {
+            return;
+        }
+        let polygon: Vec<skia_safe::Point> = stroke::outline(&self.process(ctx.pixel_size))
+            .into_iter()
+            .map(|p| skia_safe::Point::new(p.x, p.y))
+            .collect();
+        let mut path = Path::new();
+        path.add_poly(&polygon, true);
+
+        let c = self.color;
+        let mut paint = Paint::new(Color4f::new(c.r, c.g, c.b, c.a), None);
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Fill);
+        canvas.draw_path(&path, &paint);
+    }
+
+    fn options(&self) -> Vec<ToolOption> {
+        vec![
+            ToolOption {
+                name: "Size",
+                value: self.size,
+                min: 0.5,
+                max: 100.0,
+            },
+            ToolOption {
+                name: "Simplify",
+                value: self.tolerance,
+                min: 0.0,
+                max: 10.0,
+            },
+            ToolOption {
+                name: "Smoothing",
+                value: self.smoothing as f32,
+                min: 0.0,
+                max: 8.0,
+            },
+            ToolOption {
+                name: "Thinning",
+                value: self.thinning,
+                min: 0.0,
+                max: 1.0,
+            },
+            ToolOption {
+                name: "Taper",
+                value: self.taper,
+                min: 0.0,
+                max: 200.0,
+            },
+        ]
+    }
+
+    fn set_option(&mut self, name: &str, value: f32) {
+        match name {
+            "Size" => self.size = value.max(0.5),
+            "Simplify" => self.tolerance = value.max(0.0),
+            "Smoothing" => self.smoothing = value.round().max(0.0) as usize,
+            "Thinning" => self.thinning = value.clamp(0.0, 1.0),
+            "Taper" => self.taper = value.max(0.0),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Document;
+    use crate::tools::{Modifiers, Style};
+
+    #[test]
+    fn strokes_on_a_scaled_layer_keep_their_document_size() {
+        let mut document = Document::new(200, 200);
+        let layer = document.add_layer("Layer");
+        document.get_mut(layer).unwrap().transform.scale = Point::new(2.0, 2.0);
+        let (mut selection, mut target, style) = (Vec::new(), Some(layer), Style::default());
+        let mut ctx = ToolContext {
+            document: &mut document,
+            selection: &mut selection,
+            layer: &mut target,
+            style: &style,
+            frame: 0.0,
+            pixel_size: 1.0,
+            auto_key: false,
+        };
+        let mut tool = BrushTool {
+            size: 8.0,
+            thinning: 0.0,
+            taper: 0.0,
+            ..BrushTool::default()
+        };
+        let at = |x| PointerEvent {
+            position: Point::new(x, 20.0),
+            modifiers: Modifiers::default(),
+        };
+        tool.pointer_down(&mut ctx, &at(10.0));
+        for x in [20.0, 30.0, 40.0] {
+            tool.pointer_move(&mut ctx, &at(x));
+        }
+        tool.pointer_up(&mut ctx, &at(50.0));
+
+        let id = selection[0];
+        let Geometry::Brush(points) = &document.get(id).unwrap().shape().unwrap().geometry else {
+            panic!("expected a brush stroke");
+        };
+        assert_eq!(points.first().unwrap().position, Point::new(5.0, 10.0));
+        assert_eq!(points.last().unwrap().position, Point::new(25.0, 10.0));
+        assert!(points.iter().all(|p| p.width == 4.0));
+    }
+}
@@ -0,0 +1,49 @@
+// Eraser instrument: removes every shape the pointer touches while pressed.
+
+use super::{PointerEvent, Tool, ToolContext, HIT_PIXELS};
+use crate::render;
+
+#[derive(Default)]
+pub struct EraserTool {
+    pressed: bool,
+}
+
+impl EraserTool {
+    fn erase_at(&self, ctx: &mut ToolContext, event: &PointerEvent) {
+        if let Some(id) = render::hit_test(
+            ctx.document,
+            ctx.frame,
+            event.position,
+            HIT_PIXELS * ctx.pixel_size,
+        ) {
+            if ctx.document.remove(id).is_ok() {
+                ctx.selection.retain(|selected| *selected != id);
+            }
+        }
+    }
+}
+
+impl Tool for EraserTool {
+    fn name(&self) -> &'static str {
+        "Eraser"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.pressed = true;
+        self.erase_at(ctx, event);
+    }
+
+    fn pointer_move(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        if self.pressed {
+            self.erase_at(ctx, event);
+        }
+    }
+
+    fn pointer_up(&mut self, _ctx: &mut ToolContext, _event: &PointerEvent) {
+        self.pressed = false;
+    }
+
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {
+        self.pressed = false;
+    }
+}
@@ -0,0 +1,471 @@
+// Transform gizmo of the select instrument.
+//
+// The gizmo is a box around the selection with eight scale handles, a
+// rotate handle above the top edge and a pivot. A single node gets a box
+// turned with the node and its anchor as the pivot, so scaling follows the
+// node's own axes; several nodes share one axis-aligned box. A drag is
+// expressed as a `Change` in document space and each node's anchor is
+// carried along with it, so nodes move the same way at any depth of the
+// tree.
+
+use super::HIT_PIXELS;
+use crate::animation::{self, Property, Value};
+use crate::render;
+use crate::scene::{Document, Node, NodeId, Point};
+use crate::snap;
+
+/// Distance of the rotate handle above the top edge in screen pixels.
+pub const ROTATE_OFFSET: f32 = 24.0;
+
+/// Differences below this many document units are rounding noise and do
+/// not count as an edit.
+const EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handle {
+    /// Corner or edge handle; `x` and `y` are -1, 0 or 1 for the side of
+    /// the box it sits on.
+    Scale {
+        x: i8,
+        y: i8,
+    },
+    Rotate,
+    Pivot,
+}
+
+/// Corners first so they win over edge handles on small boxes.
+pub const SCALE_HANDLES: [(i8, i8); 8] = [
+    (-1, -1),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GizmoBox {
+    pub center: Point,
+    /// Half the width and height along the box's own axes.
+    pub half: Point,
+    /// Clockwise rotation of the box in degrees.
+    pub angle: f32,
+    pub pivot: Point,
+}
+
+impl GizmoBox {
+    /// Maps a document point into box coordinates, centred and unrotated.
+    pub fn to_box(self, p: Point) -> Point {
+        (p - self.center).rotate(-self.angle)
+    }
+
+    /// Maps a point in box coordinates back into the document.
+    pub fn to_document(self, p: Point) -> Point {
+        self.center + p.rotate(self.angle)
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        let p = self.to_box(p);
+        p.x.abs() <= self.half.x && p.y.abs() <= self.half.y
+    }
+
+    pub fn handle_position(&self, handle: Handle, pixel_size: f32) -> Point {
+        match handle {
+            Handle::Scale { x, y } => {
+                self.to_document(Point::new(x as f32 * self.half.x, y as f32 * self.half.y))
+            }
+            Handle::Rotate => {
+                self.to_document(Point::new(0.0, -self.half.y - ROTATE_OFFSET * pixel_size))
+            }
+            Handle::Pivot => self.pivot,
+        }
+    }
+
+    /// The handle under `p`, scale handles first, then the rotate handle
+    /// and the pivot.
+    pub fn handle_at(&self, p: Point, pixel_size: f32) -> Option<Handle> {
+        SCALE_HANDLES
+            .iter()
+            .map(|&(x, y)| Handle::Scale { x, y })
+            .chain([Handle::Rotate, Handle::Pivot])
+            .find(|&handle| {
+                self.handle_position(handle, pixel_size).distance(p) <= HIT_PIXELS * pixel_size
+            })
+    }
+
+    /// The box as it looks after `change`.
+    pub fn transformed(&self, change: &Change) -> GizmoBox {
+        let mut gizmo = GizmoBox {
+            center: change.map(self.center),
+            pivot: change.map(self.pivot),
+            ..*self
+        };
+        match *change {
+            Change::Move(_) => {}
+            Change::Rotate { degrees, .. } => gizmo.angle += degrees,
+            Change::Scale { factor, .. } => {
+                gizmo.half = Point::new(
+                    (self.half.x * factor.x).abs(),
+                    (self.half.y * factor.y).abs(),
+                )
+            }
+        }
+        gizmo
+    }
+}
+
+/// A gizmo drag in document space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Move(Point),
+    Rotate {
+        pivot: Point,
+        degrees: f32,
+    },
+    /// Scales by `factor` along the axes of a box turned by `angle`,
+    /// keeping `origin` in place.
+    Scale {
+        origin: Point,
+        angle: f32,
+        factor: Point,
+    },
+}
+
+impl Change {
+    pub fn map(&self, p: Point) -> Point {
+        match *self {
+            Change::Move(delta) => p + delta,
+            Change::Rotate { pivot, degrees } => pivot + (p - pivot).rotate(degrees),
+            Change::Scale {
+                origin,
+                angle,
+                factor,
+            } => {
+                let p = (p - origin).rotate(-angle);
+                origin + Point::new(p.x * factor.x, p.y * factor.y).rotate(angle)
+            }
+        }
+    }
+}
+
+/// Box around the content of `ids` at `frame`, or `None` if none of them
+/// has any.
+pub fn document_box(doc: &Document, ids: &[NodeId], frame: f32) -> Option<GizmoBox> {
+    let angle = match ids {
+        [id] => document_rotation(doc, *id, frame),
+        _ => 0.0,
+    };
+    let corners: Vec<Point> = ids
+        .iter()
+        .flat_map(|&id| render::document_corners(doc, id, frame))
+        .map(|p| p.rotate(-angle))
+        .collect();
+    let (min, max) = snap::bounds(&corners)?;
+    let center = ((min + max) * 0.5).rotate(angle);
+    let pivot = match ids {
+        [id] => doc
+            .get(*id)
+            .map(|node| animation::to_document(doc, *id, frame, node.transform.anchor)),
+        _ => None,
+    };
+    Some(GizmoBox {
+        center,
+        half: (max - min) * 0.5,
+        angle,
+        pivot: pivot.unwrap_or(center),
+    })
+}
+
+/// Applies `change` to node `id` at `frame`, writing only the properties
+/// it alters.
+pub fn apply(doc: &mut Document, id: NodeId, frame: f32, change: &Change, auto_key: bool) {
+    let Some(node) = doc.get(id) else {
+        return;
+    };
+    let values = animation::sample(node, frame);
+    let anchor = animation::to_document(doc, id, frame, node.transform.anchor);
+    let target = change.map(anchor);
+    let position = match doc.parent_of(id) {
+        Some(parent) => animation::to_local(doc, parent, frame, target),
+        None => target,
+    };
+    let mut rotation = values.rotation;
+    let mut scale = values.scale;
+    match *change {
+        Change::Move(_) => {}
+        Change::Rotate { degrees, .. } => rotation += degrees,
+        Change::Scale { angle, factor, .. } => {
+            // Each local axis stretches by how much the factor lengthens a
+            // unit vector pointing along it.
+            let x_angle = document_rotation(doc, id, frame) - angle;
+            scale.x *= axis_factor(x_angle, factor);
+            scale.y *= axis_factor(x_angle + 90.0, factor);
+        }
+    }
+
+    let Some(node) = doc.get_mut(id) else {
+        return;
+    };
+    if position.distance(values.position) > EPSILON {
+        animation::write(
+            node,
+            Property::Position,
+            Value::Point(position),
+            frame,
+            auto_key,
+        );
+    }
+    if rotation != values.rotation {
+        animation::write(
+            node,
+            Property::Rotation,
+            Value::Scalar(rotation),
+            frame,
+            auto_key,
+        );
+    }
+    if scale != values.scale {
+        animation::write(node, Property::Scale, Value::Point(scale), frame, auto_key);
+    }
+}
+
+/// Moves the anchor of node `id` to the document point `target` without
+/// moving the node. The static position and every position keyframe are
+/// shifted to make up for it, using the rotation and scale at their own
+/// frames.
+pub fn set_pivot(doc: &mut Document, id: NodeId, frame: f32, target: Point) {
+    let anchor = animation::to_local(doc, id, frame, target);
+    let Some(node) = doc.get(id) else {
+        return;
+    };
+    let old = node.transform.anchor;
+    if anchor.distance(old) <= EPSILON {
+        return;
+    }
+    let shift = anchor_shift(node, frame, old, anchor);
+    let key_shifts: Vec<Point> = node
+        .animation
+        .track(Property::Position)
+        .map(|track| {
+            track
+                .keyframes
+                .iter()
+                .map(|k| anchor_shift(node, k.frame, old, anchor))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let Some(node) = doc.get_mut(id) else {
+        return;
+    };
+    node.transform.anchor = anchor;
+    node.transform.position = node.transform.position + shift;
+    if let Some(track) = node
+        .animation
+        .tracks
+        .iter_mut()
+        .find(|t| t.property == Property::Position)
+    {
+        for (keyframe, shift) in track.keyframes.iter_mut().zip(key_shifts) {
+            if let Value::Point(p) = &mut keyframe.value {
+                *p = *p + shift;
+            }
+        }
+    }
+}
+
+/// How far the position has to move at `frame` to keep the node in place
+/// when its anchor goes from `from` to `to`.
+fn anchor_shift(node: &Node, frame: f32, from: Point, to: Point) -> Point {
+    let transform = animation::sample(node, frame).transform(&node.transform);
+    transform.apply(to) - transform.apply(from)
+}
+
+/// Clockwise rotation of a node's x axis in document space.
+fn document_rotation(doc: &Document, id: NodeId, frame: f32) -> f32 {
+    animation::transform_chain(doc, id, frame)
+        .iter()
+        .map(|t| t.rotation)
+        .sum()
+}
+
+/// Signed stretch of a unit vector at `degrees` from the x axis under a
+/// per-axis `factor`.
+fn axis_factor(degrees: f32, factor: Point) -> f32 {
+    let unit = Point::new(1.0, 0.0).rotate(degrees);
+    let scaled = Point::new(unit.x * factor.x, unit.y * factor.y);
+    scaled
+        .length()
+        .copysign(scaled.x * unit.x + scaled.y * unit.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Geometry, Shape};
+
+    /// A 40x20 rectangle at (30, 40) on a layer, turned by `rotation`.
+    fn rectangle(rotation: f32) -> (Document, NodeId) {
+        let mut doc = Document::new(200, 200);
+        let layer = doc.add_layer("Layer");
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(40.0, 20.0),
+            corner_radius: 0.0,
+        });
+        let id = doc.add_shape(layer, "Rect", shape).unwrap();
+        let node = doc.get_mut(id).unwrap();
+        node.transform.position = Point::new(30.0, 40.0);
+        node.transform.rotation = rotation;
+        (doc, id)
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!(a.distance(b) < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn changes_map_points() {
+        let p = Point::new(20.0, 0.0);
+        assert_eq!(
+            Change::Move(Point::new(1.0, 2.0)).map(p),
+            Point::new(21.0, 2.0)
+        );
+        let turn = Change::Rotate {
+            pivot: Point::new(10.0, 0.0),
+            degrees: 90.0,
+        };
+        assert_near(turn.map(p), Point::new(10.0, 10.0));
+        // The box is turned a quarter, so its x axis points down.
+        let stretch = Change::Scale {
+            origin: Point::new(0.0, 5.0),
+            angle: 90.0,
+            factor: Point::new(2.0, 1.0),
+        };
+        assert_near(stretch.map(Point::new(3.0, 15.0)), Point::new(3.0, 25.0));
+        assert_near(stretch.map(Point::new(0.0, 5.0)), Point::new(0.0, 5.0));
+    }
+
+    #[test]
+    fn axis_factors_follow_the_axis_direction() {
+        let factor = Point::new(2.0, 3.0);
+        assert!((axis_factor(0.0, factor) - 2.0).abs() < 1e-5);
+        assert!((axis_factor(90.0, factor) - 3.0).abs() < 1e-5);
+        assert!((axis_factor(180.0, factor) - 2.0).abs() < 1e-5);
+        assert!((axis_factor(0.0, Point::new(-2.0, 1.0)) + 2.0).abs() < 1e-5);
+        let diagonal = axis_factor(45.0, factor);
+        assert!((diagonal - 6.5f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn scaling_a_rotated_node_stretches_its_own_axes() {
+        let (mut doc, id) = rectangle(90.0);
+        let origin = Point::new(30.0, 40.0);
+        // Along the node's box, whose x axis is the node's.
+        let along = Change::Scale {
+            origin,
+            angle: 90.0,
+            factor: Point::new(2.0, 1.0),
+        };
+        apply(&mut doc, id, 0.0, &along, false);
+        let node = doc.get(id).unwrap();
+        assert_near(node.transform.scale, Point::new(2.0, 1.0));
+        assert_near(node.transform.position, origin);
+
+        // An axis-aligned box stretches the node's y axis instead.
+        let (mut doc, id) = rectangle(90.0);
+        let across = Change::Scale {
+            origin,
+            angle: 0.0,
+            factor: Point::new(2.0, 1.0),
+        };
+        apply(&mut doc, id, 0.0, &across, false);
+        assert_near(doc.get(id).unwrap().transform.scale, Point::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn scaling_keeps_the_fixed_handle_in_place() {
+        let (mut doc, id) = rectangle(30.0);
+        let gizmo = document_box(&doc, &[id], 0.0).unwrap();
+        assert!((gizmo.angle - 30.0).abs() < 1e-4);
+        assert_near(gizmo.half, Point::new(20.0, 10.0));
+
+        // Dragging the bottom-right corner scales about the top-left one.
+        let fixed = Point::new(-gizmo.half.x, -gizmo.half.y);
+        let change = Change::Scale {
+            origin: gizmo.to_document(fixed),
+            angle: gizmo.angle,
+            factor: Point::new(1.5, 0.5),
+        };
+        apply(&mut doc, id, 0.0, &change, false);
+
+        let after = document_box(&doc, &[id], 0.0).unwrap();
+        assert_near(after.half, Point::new(30.0, 5.0));
+        let corner = Point::new(-after.half.x, -after.half.y);
+        assert_near(after.to_document(corner), gizmo.to_document(fixed));
+        assert_near(after.center, gizmo.transformed(&change).center);
+    }
+
+    #[test]
+    fn moving_the_pivot_keeps_the_node_in_place() {
+        let (mut doc, id) = rectangle(0.0);
+        let node = doc.get_mut(id).unwrap();
+        node.transform.scale = Point::new(2.0, 1.0);
+        let keys = [
+            (
+                Property::Position,
+                0.0,
+                Value::Point(Point::new(30.0, 40.0)),
+            ),
+            (
+                Property::Position,
+                10.0,
+                Value::Point(Point::new(90.0, 60.0)),
+            ),
+            (Property::Rotation, 0.0, Value::Scalar(0.0)),
+            (Property::Rotation, 10.0, Value::Scalar(120.0)),
+        ];
+        for (property, frame, value) in keys {
+            node.animation.set_keyframe(property, frame, value);
+        }
+        let frames = [0.0, 10.0];
+        let before = frames.map(|frame| render::document_corners(&doc, id, frame));
+
+        let target = Point::new(50.0, 45.0);
+        set_pivot(&mut doc, id, 0.0, target);
+        let node = doc.get(id).unwrap();
+        assert_near(node.transform.anchor, Point::new(10.0, 5.0));
+        assert_near(
+            animation::to_document(&doc, id, 0.0, node.transform.anchor),
+            target,
+        );
+        // Only keyframes are exact: between them the position is
+        // interpolated linearly while the shift follows the rotation.
+        for (frame, before) in frames.into_iter().zip(before) {
+            let after = render::document_corners(&doc, id, frame);
+            for (a, b) in after.into_iter().zip(before) {
+                assert_near(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn several_nodes_share_an_axis_aligned_box() {
+        let (mut doc, first) = rectangle(0.0);
+        let layer = doc.parent_of(first).unwrap();
+        let shape = Shape::new(Geometry::Rect {
+            size: Point::new(10.0, 10.0),
+            corner_radius: 0.0,
+        });
+        let second = doc.add_shape(layer, "Small", shape).unwrap();
+        doc.get_mut(second).unwrap().transform.rotation = 45.0;
+
+        let gizmo = document_box(&doc, &[first, second], 0.0).unwrap();
+        assert_eq!(gizmo.angle, 0.0);
+        let diagonal = 50f32.sqrt();
+        assert_near(gizmo.center, Point::new((70.0 - diagonal) / 2.0, 30.0));
+        assert_eq!(gizmo.pivot, gizmo.center);
+        assert!(document_box(&doc, &[], 0.0).is_none());
+    }
+}
@@ -0,0 +1,307 @@
+// Instruments of the tool palette.
+//
+// Each instrument is its own type implementing `Tool`. The viewport converts
+// pointer and key input into document space and forwards it to the active
+// tool, which edits the document through a `ToolContext` and may draw a
+// preview overlay on top of the rendered frame.
+
+mod brush;
+mod eraser;
+mod gizmo;
+mod pen;
+mod select;
+mod shape;
+
+pub use brush::BrushTool;
+pub use eraser::EraserTool;
+pub use pen::PenTool;
+pub use select::SelectTool;
+pub use shape::{EllipseTool, RectangleTool};
+
+use skia_safe::Canvas;
+
+use crate::cel;
+use crate::scene::{Color, Document, Fill, Node, NodeId, Point, Stroke};
+
+/// Pick radius around handles, anchors and strokes in screen pixels.
+pub const HIT_PIXELS: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub control: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerEvent {
+    /// Pointer position in document coordinates.
+    pub position: Point,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Escape,
+    Enter,
+    Delete,
+    Backspace,
+    Char(char),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+/// Fill and stroke given to newly drawn shapes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(Fill::Solid(Color::from_rgba8(0x4a, 0x90, 0xe2, 0xff))),
+            stroke: Some(Stroke::new(Color::BLACK, 2.0)),
+        }
+    }
+}
+
+/// Numeric setting of a tool, edited in the panel under the palette.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolOption {
+    pub name: &'static str,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Editor state a tool may read and modify while handling an event.
+pub struct ToolContext<'a> {
+    pub document: &'a mut Document,
+    pub selection: &'a mut Vec<NodeId>,
+    /// Layer that receives new shapes.
+    pub layer: &'a mut Option<NodeId>,
+    pub style: &'a Style,
+    /// Playhead position in frames.
+    pub frame: f32,
+    /// Size of one screen pixel in document units, for hit tolerances and
+    /// handle sizes that should look the same at every zoom level.
+    pub pixel_size: f32,
+    /// Transform edits set keyframes at the playhead.
+    pub auto_key: bool,
+}
+
+impl ToolContext<'_> {
+    /// Returns the container new content goes to, creating a layer if the
+    /// document has none. On a cel layer that is the drawing exposed at the
+    /// playhead, which is created if the playhead is past the last one.
+    pub fn target_layer(&mut self) -> NodeId {
+        let layer = match *self.layer {
+            Some(id) if self.document.get(id).is_some() => id,
+            _ => {
+                let id = match self.document.layers.last() {
+                    Some(layer) => layer.id,
+                    None => self.document.add_layer("Layer 1"),
+                };
+                *self.layer = Some(id);
+                id
+            }
+        };
+        if self.document.get(layer).is_some_and(Node::is_cel_layer) {
+            if let Ok(drawing) = cel::drawing_for_edit(self.document, layer, self.frame) {
+                return drawing;
+            }
+        }
+        layer
+    }
+}
+
+/// Read-only view of the editor handed to `Tool::draw_overlay`.
+pub struct OverlayContext<'a> {
+    pub document: &'a Document,
+    pub selection: &'a [NodeId],
+    pub frame: f32,
+    pub pixel_size: f32,
+}
+
+/// An instrument of the palette. All handlers default to doing nothing so
+/// tools only implement what they react to.
+pub trait Tool {
+    fn name(&self) -> &'static str;
+
+    fn pointer_down(&mut self, _ctx: &mut ToolContext, _event: &PointerEvent) {}
+
+    fn pointer_move(&mut self, _ctx: &mut ToolContext, _event: &PointerEvent) {}
+
+    fn pointer_up(&mut self, _ctx: &mut ToolContext, _event: &PointerEvent) {}
+
+    /// Returns `true` if the key was handled.
+    fn key(&mut self, _ctx: &mut ToolContext, _event: &KeyEvent) -> bool {
+        false
+    }
+
+    /// Called when another tool becomes active; drop any half-finished
+    /// interaction.
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {}
+
+    /// Draws previews and handles in document space on top of the frame.
+    fn draw_overlay(&self, _canvas: &Canvas, _ctx: &OverlayContext) {}
+
+    fn options(&self) -> Vec<ToolOption> {
+        Vec::new()
+    }
+
+    /// Updates the option called `name`; unknown names are ignored.
+    fn set_option(&mut self, _name: &str, _value: f32) {}
+}
+
+/// The registered tools and which one is active.
+pub struct Palette {
+    tools: Vec<Box<dyn Tool>>,
+    active: usize,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let mut palette = Self::new();
+        palette.register(Box::new(SelectTool::default()));
+        palette.register(Box::new(BrushTool::default()));
+        palette.register(Box::new(PenTool::default()));
+        palette.register(Box::new(RectangleTool::default()));
+        palette.register(Box::new(EllipseTool::default()));
+        palette.register(Box::new(EraserTool::default()));
+        palette
+    }
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            tools: Vec::new(),
+            active: 0,
+        }
+    }
+
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        self.tools.push(tool);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.tools.iter().map(|t| t.name()).collect()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn activate(&mut self, index: usize, ctx: &mut ToolContext) {
+        if index < self.tools.len() && index != self.active {
+            self.tools[self.active].deactivate(ctx);
+            self.active = index;
+        }
+    }
+
+    pub fn active(&self) -> Option<&dyn Tool> {
+        self.tools.get(self.active).map(|t| t.as_ref())
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut (dyn Tool + 'static)> {
+        self.tools.get_mut(self.active).map(|t| t.as_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Logs every call it receives as "<name> <event>".
+    struct Recorder {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, event: &str) {
+            self.log.borrow_mut().push(format!("{} {event}", self.name));
+        }
+    }
+
+    impl Tool for Recorder {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn pointer_down(&mut self, _ctx: &mut ToolContext, _event: &PointerEvent) {
+            self.record("down");
+        }
+
+        fn key(&mut self, _ctx: &mut ToolContext, event: &KeyEvent) -> bool {
+            self.record("key");
+            event.key == Key::Escape
+        }
+
+        fn deactivate(&mut self, _ctx: &mut ToolContext) {
+            self.record("deactivate");
+        }
+    }
+
+    #[test]
+    fn events_go_to_the_active_tool_and_switching_deactivates_it() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut palette = Palette::new();
+        for name in ["a", "b"] {
+            let log = log.clone();
+            palette.register(Box::new(Recorder { name, log }));
+        }
+        assert_eq!(palette.names(), ["a", "b"]);
+
+        let mut document = Document::new(100, 100);
+        let (mut selection, mut layer, style) = (Vec::new(), None, Style::default());
+        let mut ctx = ToolContext {
+            document: &mut document,
+            selection: &mut selection,
+            layer: &mut layer,
+            style: &style,
+            frame: 0.0,
+            pixel_size: 1.0,
+            auto_key: false,
+        };
+        let press = PointerEvent {
+            position: Point::ZERO,
+            modifiers: Modifiers::default(),
+        };
+        let escape = KeyEvent {
+            key: Key::Escape,
+            modifiers: Modifiers::default(),
+        };
+
+        palette.active_mut().unwrap().pointer_down(&mut ctx, &press);
+        // Re-activating the active tool and unknown indices change nothing.
+        palette.activate(0, &mut ctx);
+        palette.activate(7, &mut ctx);
+        palette.activate(1, &mut ctx);
+        assert_eq!(palette.active_index(), 1);
+        assert_eq!(palette.active().unwrap().name(), "b");
+        let tool = palette.active_mut().unwrap();
+        tool.pointer_down(&mut ctx, &press);
+        assert!(tool.key(&mut ctx, &escape));
+
+        assert_eq!(*log.borrow(), ["a down", "a deactivate", "b down", "b key"]);
+    }
+
+    #[test]
+    fn default_palette_starts_with_the_select_tool() {
+        let palette = Palette::default();
+        let names = palette.names();
+        assert_eq!(names.len(), 6);
+        assert_eq!(palette.active().unwrap().name(), names[0]);
+        assert_eq!(names[0], SelectTool::default().name());
+    }
+}
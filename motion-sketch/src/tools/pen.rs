@@ -0,0 +1,515 @@
+// Bezier pen instrument.
+//
+// Click to place corner anchors, drag to pull out smooth handles, click the
+// first anchor to close the path and press Enter or Escape to finish an open
+// one. On an existing path: drag anchors and handles to edit them (Alt
+// breaks handle symmetry), click a segment to insert an anchor, Alt-click an
+// anchor to toggle corner/smooth and Ctrl-click it to delete it. New and
+// dragged anchors snap; handles do not.
+
+use skia_safe::{Color4f, Paint, PaintStyle, Rect};
+
+use super::{Key, KeyEvent, OverlayContext, PointerEvent, Tool, ToolContext, HIT_PIXELS};
+use crate::animation::{self, Property, Value};
+use crate::bezier;
+use crate::render;
+use crate::scene::{Geometry, NodeId, PathData, PathPoint, Point, Shape};
+use crate::snap::{Indicator, Snapper};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Drag {
+    /// Pulling symmetric handles out of a freshly placed anchor.
+    NewAnchor(usize),
+    Anchor(usize),
+    Handle {
+        index: usize,
+        outgoing: bool,
+    },
+}
+
+#[derive(Default)]
+pub struct PenTool {
+    /// Open path that clicks keep extending.
+    drawing: Option<NodeId>,
+    drag: Option<Drag>,
+    snapper: Snapper,
+    indicators: Vec<Indicator>,
+}
+
+/// Points of a path node as seen at the playhead, and whether it is closed.
+fn path_points(ctx: &ToolContext, id: NodeId) -> Option<(Vec<PathPoint>, bool)> {
+    let node = ctx.document.get(id)?;
+    let Geometry::Path(data) = &node.shape()?.geometry else {
+        return None;
+    };
+    let points = animation::sample(node, ctx.frame).path_points?;
+    Some((points, data.closed))
+}
+
+/// Moves points without changing how many there are. Animated paths get
+/// the result as a keyframe at the playhead.
+fn update_points(ctx: &mut ToolContext, id: NodeId, edit: impl FnOnce(&mut Vec<PathPoint>)) {
+    let Some((mut points, _)) = path_points(ctx, id) else {
+        return;
+    };
+    edit(&mut points);
+    let frame = ctx.frame;
+    let Some(node) = ctx.document.get_mut(id) else {
+        return;
+    };
+    if node.animation.is_animated(Property::PathPoints) {
+        node.animation
+            .set_keyframe(Property::PathPoints, frame, Value::Path(points));
+    } else if let Some(Shape {
+        geometry: Geometry::Path(data),
+        ..
+    }) = node.shape_mut()
+    {
+        data.points = points;
+    }
+}
+
+/// Adds or removes points. The edit is applied to the static path and to
+/// every keyframe so animated point lists keep matching lengths.
+fn restructure(ctx: &mut ToolContext, id: NodeId, edit: impl Fn(&mut Vec<PathPoint>)) {
+    let Some(node) = ctx.document.get_mut(id) else {
+        return;
+    };
+    if let Some(Shape {
+        geometry: Geometry::Path(data),
+        ..
+    }) = node.shape_mut()
+    {
+        edit(&mut data.points);
+    }
+    for track in &mut node.animation.tracks {
+        if track.property != Property::PathPoints {
+            continue;
+        }
+        for keyframe in &mut track.keyframes {
+            if let Value::Path(points) = &mut keyframe.value {
+                edit(points);
+            }
+        }
+    }
+}
+
+/// Snap targets for editing path `id`: everything but the path itself, and
+/// its own anchors except the one at `moving`, which follows the pointer.
+fn snapper(ctx: &ToolContext, id: Option<NodeId>, moving: Option<usize>) -> Snapper {
+    let exclude: Vec<NodeId> = id.into_iter().collect();
+    let snapper = Snapper::new(ctx.document, ctx.frame, ctx.pixel_size, &exclude);
+    let Some((id, (points, _))) = id.and_then(|id| Some((id, path_points(ctx, id)?))) else {
+        return snapper;
+    };
+    snapper.with_points(
+        points
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != moving)
+            .map(|(_, p)| animation::to_document(ctx.document, id, ctx.frame, p.anchor)),
+    )
+}
+
+impl PenTool {
+    /// Path edited by this tool: the one being drawn, else the selected one.
+    fn target(&self, ctx: &ToolContext) -> Option<NodeId> {
+        self.drawing
+            .or_else(|| ctx.selection.first().copied())
+            .filter(|id| path_points(ctx, *id).is_some())
+    }
+
+    fn finish(&mut self, ctx: &mut ToolContext) {
+        if let Some(id) = self.drawing.take() {
+            // A single click leaves a one-point path that can never render.
+            if path_points(ctx, id).is_some_and(|(points, _)| points.len() < 2) {
+                let _ = ctx.document.remove(id);
+                ctx.selection.retain(|selected| *selected != id);
+            }
+        }
+        self.drag = None;
+        self.indicators.clear();
+    }
+
+    fn start_path(&mut self, ctx: &mut ToolContext, position: Point) {
+        let layer = ctx.target_layer();
+        let local = animation::to_local(ctx.document, layer, ctx.frame, position);
+        let mut shape = Shape::new(Geometry::Path(PathData {
+            points: vec![PathPoint::corner(local)],
+            closed: false,
+        }));
+        shape.fill = ctx.style.fill.clone();
+        shape.stroke = ctx.style.stroke.clone();
+        if let Ok(id) = ctx.document.add_shape(layer, "Path", shape) {
+            *ctx.selection = vec![id];
+            self.drawing = Some(id);
+            self.drag = Some(Drag::NewAnchor(0));
+        }
+    }
+
+    /// Handles a press on an existing path. Returns `false` if nothing on
+    /// the path was hit.
+    fn press_path(&mut self, ctx: &mut ToolContext, id: NodeId, event: &PointerEvent) -> bool {
+        let Some((points, closed)) = path_points(ctx, id) else {
+            return false;
+        };
+        let local = animation::to_local(ctx.document, id, ctx.frame, event.position);
+        let tolerance = HIT_PIXELS * ctx.pixel_size;
+        let near = |p: Point| p.distance(local) <= tolerance;
+
+        // Clicking the first anchor closes the path being drawn.
+        if self.drawing == Some(id) && points.len() > 1 && near(points[0].anchor) {
+            if let Some(Shape {
+                geometry: Geometry::Path(data),
+                ..
+            }) = ctx.document.get_mut(id).and_then(|n| n.shape_mut())
+            {
+                data.closed = true;
+            }
+            self.drawing = None;
+            self.drag = Some(Drag::NewAnchor(0));
+            return true;
+        }
+
+        for (index, point) in points.iter().enumerate() {
+            for outgoing in [true, false] {
+                let handle = if outgoing {
+                    point.handle_out
+                } else {
+                    point.handle_in
+                };
+                if handle != point.anchor && near(handle) {
+                    self.drag = Some(Drag::Handle { index, outgoing });
+                    return true;
+                }
+            }
+        }
+
+        if let Some(index) = points.iter().position(|p| near(p.anchor)) {
+            let modifiers = event.modifiers;
+            if modifiers.control {
+                restructure(ctx, id, |points| bezier::remove_point(points, index));
+            } else if modifiers.alt {
+                update_points(ctx, id, |points| {
+                    if bezier::is_smooth(&points[index]) {
+                        bezier::make_corner(&mut points[index]);
+                    } else {
+                        bezier::make_smooth(points, closed, index);
+                    }
+                });
+            } else {
+                self.drag = Some(Drag::Anchor(index));
+            }
+            return true;
+        }
+
+        if self.drawing != Some(id) {
+            if let Some((segment, t, distance)) = bezier::nearest(&points, closed, local) {
+                if distance <= tolerance {
+                    restructure(ctx, id, |points| {
+                        bezier::split_segment(points, closed, segment, t);
+                    });
+                    self.drag = Some(Drag::Anchor(segment + 1));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Tool for PenTool {
+    fn name(&self) -> &'static str {
+        "Pen"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        let target = self.target(ctx);
+        self.snapper = snapper(ctx, target, None);
+        self.indicators.clear();
+        if let Some(id) = target {
+            // Hits on the path use the pointer as is; only new anchors snap.
+            if self.press_path(ctx, id, event) {
+                if let Some(Drag::Anchor(index)) = self.drag {
+                    self.snapper = snapper(ctx, Some(id), Some(index));
+                }
+                return;
+            }
+            if self.drawing == Some(id) {
+                let (position, indicators) = self.snapper.snap_point(event.position);
+                self.indicators = indicators;
+                let local = animation::to_local(ctx.document, id, ctx.frame, position);
+                restructure(ctx, id, |points| points.push(PathPoint::corner(local)));
+                let count = path_points(ctx, id).map_or(0, |(points, _)| points.len());
+                self.drag = Some(Drag::NewAnchor(count.saturating_sub(1)));
+                return;
+            }
+        }
+        let (position, indicators) = self.snapper.snap_point(event.position);
+        self.indicators = indicators;
+        self.start_path(ctx, position);
+    }
+
+    fn pointer_move(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        let (Some(drag), Some(id)) = (self.drag, self.target(ctx)) else {
+            return;
+        };
+        let position = if let Drag::Anchor(_) = drag {
+            let (position, indicators) = self.snapper.snap_point(event.position);
+            self.indicators = indicators;
+            position
+        } else {
+            self.indicators.clear();
+            event.position
+        };
+        let local = animation::to_local(ctx.document, id, ctx.frame, position);
+        let break_symmetry = event.modifiers.alt;
+        update_points(ctx, id, |points| match drag {
+            Drag::NewAnchor(index) => {
+                if let Some(point) = points.get_mut(index) {
+                    point.handle_out = local;
+                    point.handle_in = point.anchor * 2.0 - local;
+                }
+            }
+            Drag::Anchor(index) => {
+                if let Some(point) = points.get_mut(index) {
+                    bezier::move_anchor(point, local);
+                }
+            }
+            Drag::Handle { index, outgoing } => {
+                if let Some(point) = points.get_mut(index) {
+                    bezier::set_handle(point, outgoing, local, break_symmetry);
+                }
+            }
+        });
+    }
+
+    fn pointer_up(&mut self, ctx: &mut ToolContext, _event: &PointerEvent) {
+        // A click without a real drag leaves a corner point.
+        if let (Some(Drag::NewAnchor(index)), Some(id)) = (self.drag, self.target(ctx)) {
+            let tolerance = ctx.pixel_size;
+            update_points(ctx, id, |points| {
+                if let Some(point) = points.get_mut(index) {
+                    if point.handle_out.distance(point.anchor) < tolerance {
+                        bezier::make_corner(point);
+                    }
+                }
+            });
+        }
+        self.drag = None;
+        self.indicators.clear();
+    }
+
+    fn key(&mut self, ctx: &mut ToolContext, event: &KeyEvent) -> bool {
+        match event.key {
+            Key::Enter | Key::Escape if self.drawing.is_some() => {
+                self.finish(ctx);
+                true
+            }
+            Key::Backspace | Key::Delete => {
+                let Some(id) = self.drawing else {
+                    return false;
+                };
+                restructure(ctx, id, |points| {
+                    points.pop();
+                });
+                if path_points(ctx, id).is_some_and(|(points, _)| points.is_empty()) {
+                    self.finish(ctx);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn deactivate(&mut self, ctx: &mut ToolContext) {
+        self.finish(ctx);
+    }
+
+    fn draw_overlay(&self, canvas: &skia_safe::Canvas, ctx: &OverlayContext) {
+        render::draw_snap_indicators(canvas, &self.indicators, ctx.pixel_size);
+        let Some(id) = self.drawing.or_else(|| ctx.selection.first().copied()) else {
+            return;
+        };
+        let Some(node) = ctx.document.get(id) else {
+            return;
+        };
+        let Some(points) = animation::sample(node, ctx.frame).path_points else {
+            return;
+        };
+        let to_doc = |p: Point| {
+            let p = animation::to_document(ctx.document, id, ctx.frame, p);
+            skia_safe::Point::new(p.x, p.y)
+        };
+
+        let mut line = Paint::new(Color4f::new(0.2, 0.6, 1.0, 1.0), None);
+        line.set_anti_alias(true);
+        line.set_style(PaintStyle::Stroke);
+        line.set_stroke_width(ctx.pixel_size);
+        let mut fill = Paint::new(Color4f::new(1.0, 1.0, 1.0, 1.0), None);
+        fill.set_anti_alias(true);
+
+        let size = 3.5 * ctx.pixel_size;
+        for point in &points {
+            let anchor = to_doc(point.anchor);
+            for handle in [point.handle_in, point.handle_out] {
+                if handle != point.anchor {
+                    let handle = to_doc(handle);
+                    canvas.draw_line(anchor, handle, &line);
+                    canvas.draw_circle(handle, size * 0.8, &fill);
+                    canvas.draw_circle(handle, size * 0.8, &line);
+                }
+            }
+            let square = Rect::from_xywh(anchor.x - size, anchor.y - size, size * 2.0, size * 2.0);
+            canvas.draw_rect(square, &fill);
+            canvas.draw_rect(square, &line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Document;
+    use crate::tools::{Modifiers, Style};
+
+    fn corners(points: &[(f32, f32)]) -> Vec<PathPoint> {
+        points
+            .iter()
+            .map(|&(x, y)| PathPoint::corner(Point::new(x, y)))
+            .collect()
+    }
+
+    /// An open three-point path whose points are keyed at frames 0 and 10,
+    /// moving 10 units right.
+    fn animated_path(doc: &mut Document) -> NodeId {
+        let layer = doc.add_layer("Layer");
+        let points = corners(&[(0.0, 0.0), (20.0, 0.0), (20.0, 20.0)]);
+        let shape = Shape::new(Geometry::Path(PathData {
+            points: points.clone(),
+            closed: false,
+        }));
+        let id = doc.add_shape(layer, "Path", shape).unwrap();
+        let moved = corners(&[(10.0, 0.0), (30.0, 0.0), (30.0, 20.0)]);
+        let animation = &mut doc.get_mut(id).unwrap().animation;
+        animation.set_keyframe(Property::PathPoints, 0.0, Value::Path(points));
+        animation.set_keyframe(Property::PathPoints, 10.0, Value::Path(moved));
+        id
+    }
+
+    fn static_points(doc: &Document, id: NodeId) -> Vec<PathPoint> {
+        match &doc.get(id).unwrap().shape().unwrap().geometry {
+            Geometry::Path(data) => data.points.clone(),
+            _ => panic!("expected a path"),
+        }
+    }
+
+    fn keyed_points(doc: &Document, id: NodeId) -> Vec<Vec<PathPoint>> {
+        let animation = &doc.get(id).unwrap().animation;
+        let track = animation.track(Property::PathPoints).unwrap();
+        track
+            .keyframes
+            .iter()
+            .map(|k| match &k.value {
+                Value::Path(points) => points.clone(),
+                _ => panic!("expected path points"),
+            })
+            .collect()
+    }
+
+    fn click(tool: &mut PenTool, ctx: &mut ToolContext, x: f32, y: f32, modifiers: Modifiers) {
+        let event = PointerEvent {
+            position: Point::new(x, y),
+            modifiers,
+        };
+        tool.pointer_down(ctx, &event);
+        tool.pointer_up(ctx, &event);
+    }
+
+    #[test]
+    fn inserting_and_deleting_anchors_edits_every_keyframe() {
+        let mut document = Document::new(100, 100);
+        let id = animated_path(&mut document);
+        let (mut selection, mut layer, style) = (vec![id], None, Style::default());
+        let mut ctx = ToolContext {
+            document: &mut document,
+            selection: &mut selection,
+            layer: &mut layer,
+            style: &style,
+            frame: 0.0,
+            pixel_size: 1.0,
+            auto_key: false,
+        };
+        let mut tool = PenTool::default();
+
+        // A click on the first segment splits it in the static path and in
+        // both keyframes, each at its own midpoint.
+        click(&mut tool, &mut ctx, 10.0, 0.0, Modifiers::default());
+        assert_eq!(static_points(ctx.document, id).len(), 4);
+        let keyed = keyed_points(ctx.document, id);
+        assert_eq!(keyed[0][1].anchor, Point::new(10.0, 0.0));
+        assert_eq!(keyed[1][1].anchor, Point::new(20.0, 0.0));
+
+        // Ctrl-clicking the last anchor removes it everywhere.
+        let control = Modifiers {
+            control: true,
+            ..Modifiers::default()
+        };
+        click(&mut tool, &mut ctx, 20.0, 20.0, control);
+        assert_eq!(static_points(ctx.document, id).len(), 3);
+        assert!(keyed_points(ctx.document, id).iter().all(|k| k.len() == 3));
+    }
+
+    #[test]
+    fn moving_points_of_an_animated_path_keys_the_playhead() {
+        let mut document = Document::new(100, 100);
+        let id = animated_path(&mut document);
+        let before = static_points(&document, id);
+        let (mut selection, mut layer, style) = (vec![id], None, Style::default());
+        let mut ctx = ToolContext {
+            document: &mut document,
+            selection: &mut selection,
+            layer: &mut layer,
+            style: &style,
+            frame: 5.0,
+            pixel_size: 1.0,
+            auto_key: false,
+        };
+
+        update_points(&mut ctx, id, |points| points[0].anchor.y = 5.0);
+        let keyed = keyed_points(ctx.document, id);
+        assert_eq!(keyed.len(), 3);
+        // Frame 5 samples halfway between the keys before the edit.
+        assert_eq!(keyed[1][0].anchor, Point::new(5.0, 5.0));
+        assert_eq!(static_points(ctx.document, id), before);
+    }
+
+    #[test]
+    fn moving_points_of_a_static_path_edits_it_in_place() {
+        let mut document = Document::new(100, 100);
+        let id = animated_path(&mut document);
+        document.get_mut(id).unwrap().animation.tracks.clear();
+        let (mut selection, mut layer, style) = (vec![id], None, Style::default());
+        let mut ctx = ToolContext {
+            document: &mut document,
+            selection: &mut selection,
+            layer: &mut layer,
+            style: &style,
+            frame: 5.0,
+            pixel_size: 1.0,
+            auto_key: false,
+        };
+
+        update_points(&mut ctx, id, |points| points[0].anchor.y = 5.0);
+        assert_eq!(
+            static_points(ctx.document, id)[0].anchor,
+            Point::new(0.0, 5.0)
+        );
+        assert!(!ctx
+            .document
+            .get(id)
+            .unwrap()
+            .animation
+            .is_animated(Property::PathPoints));
+    }
+}
@@ -0,0 +1,559 @@
+// Selection instrument.
+//
+// Click a shape to select it, or the top-level group it belongs to, and
+// Shift-click to add or remove it; drag over empty canvas for a marquee,
+// which adds to the selection with Shift. The selection gets a transform
+// gizmo: drag inside it to move (Shift keeps to one axis), drag a corner or
+// edge handle to scale (Shift keeps proportions, Alt scales about the
+// centre), drag the handle above it to rotate (Shift snaps to 15°) and drag
+// the pivot, or Ctrl-drag anywhere, to move the point rotation turns around.
+// Moves snap the selection's bounds, scaling snaps the dragged handle and
+// the pivot snaps to the box's handles as well. Escape cancels a drag, or
+// clears the selection.
+
+use skia_safe::{Color4f, Paint, PaintStyle, Path, Rect};
+
+use super::gizmo::{self, Change, GizmoBox, Handle};
+use super::{Key, KeyEvent, OverlayContext, PointerEvent, Tool, ToolContext, HIT_PIXELS};
+use crate::animation::Animation;
+use crate::cel;
+use crate::render;
+use crate::scene::{Document, NodeId, NodeKind, Point, Transform};
+use crate::snap::{self, Indicator, Snapper};
+
+/// Rotation steps with Shift held, in degrees.
+const SNAP_DEGREES: f32 = 15.0;
+
+/// Marquees smaller than this many screen pixels are treated as a click.
+const MIN_MARQUEE_PIXELS: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Drag {
+    Move,
+    Scale {
+        x: i8,
+        y: i8,
+    },
+    Rotate,
+    /// `grab` is the offset from the pointer to the pivot.
+    Pivot {
+        grab: Point,
+    },
+    Marquee {
+        current: Point,
+        additive: bool,
+    },
+}
+
+struct Gesture {
+    drag: Drag,
+    start: Point,
+    /// The gizmo when the drag started.
+    gizmo: GizmoBox,
+    /// The dragged nodes' transforms and animation as they were, restored
+    /// before every move so changes never accumulate rounding errors.
+    originals: Vec<(NodeId, Transform, Animation)>,
+    /// Latest change of a move, scale or rotate drag.
+    change: Option<Change>,
+    /// `SelectTool::pivot` when the drag started.
+    pivot: Option<(Vec<NodeId>, Point)>,
+    snapper: Snapper,
+    /// What the latest position snapped to.
+    indicators: Vec<Indicator>,
+}
+
+#[derive(Default)]
+pub struct SelectTool {
+    gesture: Option<Gesture>,
+    /// Pivot of a selection of several nodes, which have no shared anchor
+    /// to keep it in, and the selection it belongs to.
+    pivot: Option<(Vec<NodeId>, Point)>,
+}
+
+/// The selected nodes a transform applies to: editable ones, leaving out
+/// those already moved along with a selected ancestor.
+fn targets(doc: &Document, selection: &[NodeId]) -> Vec<NodeId> {
+    selection
+        .iter()
+        .copied()
+        .filter(|&id| doc.get(id).is_some() && render::is_editable(doc, id))
+        .filter(|&id| !selection.iter().any(|&other| doc.is_ancestor(other, id)))
+        .collect()
+}
+
+/// What a click on `id` selects: the ancestor sitting directly in a layer
+/// or drawing, so grouped shapes are picked as a whole.
+fn selectable(doc: &Document, id: NodeId) -> NodeId {
+    let ancestry = doc.ancestry(id);
+    ancestry
+        .windows(2)
+        .find(|pair| {
+            doc.get(pair[0]).is_some_and(|parent| {
+                matches!(
+                    parent.kind,
+                    NodeKind::Layer { .. } | NodeKind::Drawing { .. }
+                )
+            })
+        })
+        .map_or(id, |pair| pair[1])
+}
+
+impl SelectTool {
+    /// The gizmo of the current selection at the playhead.
+    fn gizmo(&self, doc: &Document, ids: &[NodeId], frame: f32) -> Option<GizmoBox> {
+        let mut gizmo = gizmo::document_box(doc, ids, frame)?;
+        if let Some((owner, pivot)) = &self.pivot {
+            if ids.len() > 1 && owner == ids {
+                gizmo.pivot = *pivot;
+            }
+        }
+        Some(gizmo)
+    }
+
+    fn begin(&mut self, ctx: &ToolContext, drag: Drag, start: Point, gizmo: GizmoBox) {
+        let ids = targets(ctx.document, ctx.selection);
+        let originals = ids
+            .iter()
+            .filter_map(|&id| ctx.document.get(id))
+            .map(|node| (node.id, node.transform, node.animation.clone()))
+            .collect();
+        let snapper = match drag {
+            Drag::Marquee { .. } | Drag::Rotate => Snapper::default(),
+            Drag::Pivot { .. } => Snapper::new(ctx.document, ctx.frame, ctx.pixel_size, &ids)
+                .with_points(
+                    gizmo::SCALE_HANDLES
+                        .map(|(x, y)| gizmo.handle_position(Handle::Scale { x, y }, ctx.pixel_size))
+                        .into_iter()
+                        .chain([gizmo.center]),
+                ),
+            _ => Snapper::new(ctx.document, ctx.frame, ctx.pixel_size, &ids),
+        };
+        self.gesture = Some(Gesture {
+            drag,
+            start,
+            gizmo,
+            originals,
+            change: None,
+            pivot: self.pivot.clone(),
+            snapper,
+            indicators: Vec::new(),
+        });
+    }
+
+    /// Puts the dragged nodes back the way they were.
+    fn restore(gesture: &Gesture, doc: &mut Document) {
+        for (id, transform, animation) in &gesture.originals {
+            if let Some(node) = doc.get_mut(*id) {
+                node.transform = *transform;
+                node.animation.clone_from(animation);
+            }
+        }
+    }
+
+    /// Selects what the finished marquee touches.
+    fn select_in_marquee(ctx: &mut ToolContext, gesture: &Gesture, additive: bool, end: Point) {
+        let (start, doc) = (gesture.start, &*ctx.document);
+        let min = Point::new(start.x.min(end.x), start.y.min(end.y));
+        let max = Point::new(start.x.max(end.x), start.y.max(end.y));
+        if max.x - min.x < MIN_MARQUEE_PIXELS * ctx.pixel_size
+            && max.y - min.y < MIN_MARQUEE_PIXELS * ctx.pixel_size
+        {
+            return;
+        }
+        let mut selection = if additive {
+            ctx.selection.clone()
+        } else {
+            Vec::new()
+        };
+        for (_, node) in doc.walk() {
+            if node.shape().is_none()
+                || !render::is_editable(doc, node.id)
+                || !cel::is_exposed(doc, node.id, ctx.frame)
+            {
+                continue;
+            }
+            let corners = render::document_corners(doc, node.id, ctx.frame);
+            let inside = |axis: fn(&Point) -> f32, low: f32, high: f32| {
+                corners.iter().map(axis).any(|v| v >= low)
+                    && corners.iter().map(axis).any(|v| v <= high)
+            };
+            if !corners.is_empty() && inside(|p| p.x, min.x, max.x) && inside(|p| p.y, min.y, max.y)
+            {
+                let id = selectable(doc, node.id);
+                if !selection.contains(&id) {
+                    selection.push(id);
+                }
+            }
+        }
+        *ctx.selection = selection;
+    }
+
+    /// The change the pointer asks for in a move, scale or rotate drag,
+    /// and what it snapped to.
+    fn change(gesture: &Gesture, event: &PointerEvent) -> Option<(Change, Vec<Indicator>)> {
+        let (p, start, gizmo) = (event.position, gesture.start, &gesture.gizmo);
+        let modifiers = event.modifiers;
+        match gesture.drag {
+            Drag::Move => {
+                let mut delta = p - start;
+                let (mut lock_x, mut lock_y) = (false, false);
+                if modifiers.shift {
+                    if delta.x.abs() > delta.y.abs() {
+                        delta.y = 0.0;
+                        lock_y = true;
+                    } else {
+                        delta.x = 0.0;
+                        lock_x = true;
+                    }
+                }
+                let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)].map(|(x, y)| {
+                    gizmo.to_document(Point::new(x as f32 * gizmo.half.x, y as f32 * gizmo.half.y))
+                        + delta
+                });
+                let (min, max) = snap::bounds(&corners)?;
+                let (offset, mut indicators) = gesture.snapper.snap_box(min, max);
+                if !lock_x {
+                    delta.x += offset.x;
+                }
+                if !lock_y {
+                    delta.y += offset.y;
+                }
+                indicators.retain(|indicator| match indicator {
+                    Indicator::Vertical(_) => !lock_x,
+                    Indicator::Horizontal(_) => !lock_y,
+                    Indicator::Point(_) => true,
+                });
+                Some((Change::Move(delta), indicators))
+            }
+            Drag::Rotate => {
+                let angle = |q: Point| {
+                    let v = q - gizmo.pivot;
+                    v.y.atan2(v.x).to_degrees()
+                };
+                let mut degrees = angle(p) - angle(start);
+                if modifiers.shift {
+                    let total = gizmo.angle + degrees;
+                    degrees = (total / SNAP_DEGREES).round() * SNAP_DEGREES - gizmo.angle;
+                }
+                let change = Change::Rotate {
+                    pivot: gizmo.pivot,
+                    degrees,
+                };
+                Some((change, Vec::new()))
+            }
+            Drag::Scale { x, y } => {
+                let side = Point::new(x as f32, y as f32);
+                let handle = Point::new(side.x * gizmo.half.x, side.y * gizmo.half.y);
+                let fixed = if modifiers.alt {
+                    Point::ZERO
+                } else {
+                    Point::new(-handle.x, -handle.y)
+                };
+                // Keep the pointer's offset from the handle it grabbed, and
+                // snap where the handle goes. Edge handles only move along
+                // one axis.
+                let (moved, mut indicators) = gesture
+                    .snapper
+                    .snap_point(p + gizmo.to_document(handle) - start);
+                indicators.retain(|indicator| match indicator {
+                    Indicator::Vertical(_) => x != 0,
+                    Indicator::Horizontal(_) => y != 0,
+                    Indicator::Point(_) => true,
+                });
+                let pointer = gizmo.to_box(moved);
+                let span = handle - fixed;
+                let ratio = |pointer: f32, fixed: f32, span: f32| {
+                    if span.abs() > f32::EPSILON {
+                        (pointer - fixed) / span
+                    } else {
+                        1.0
+                    }
+                };
+                let mut factor = Point::new(
+                    if x != 0 {
+                        ratio(pointer.x, fixed.x, span.x)
+                    } else {
+                        1.0
+                    },
+                    if y != 0 {
+                        ratio(pointer.y, fixed.y, span.y)
+                    } else {
+                        1.0
+                    },
+                );
+                if modifiers.shift {
+                    factor = if x != 0 && y != 0 {
+                        // Project the pointer onto the diagonal.
+                        let offset = pointer - fixed;
+                        let length = span.x * span.x + span.y * span.y;
+                        let k = if length > f32::EPSILON {
+                            (offset.x * span.x + offset.y * span.y) / length
+                        } else {
+                            1.0
+                        };
+                        Point::new(k, k)
+                    } else if x != 0 {
+                        Point::new(factor.x, factor.x.abs())
+                    } else {
+                        Point::new(factor.y.abs(), factor.y)
+                    };
+                }
+                let change = Change::Scale {
+                    origin: gizmo.to_document(fixed),
+                    angle: gizmo.angle,
+                    factor,
+                };
+                Some((change, indicators))
+            }
+            Drag::Pivot { .. } | Drag::Marquee { .. } => None,
+        }
+    }
+
+    fn update(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        let Some(gesture) = &mut self.gesture else {
+            return;
+        };
+        match gesture.drag {
+            Drag::Marquee { additive, .. } => {
+                gesture.drag = Drag::Marquee {
+                    current: event.position,
+                    additive,
+                }
+            }
+            Drag::Pivot { grab } => {
+                let (target, indicators) = gesture.snapper.snap_point(event.position + grab);
+                gesture.indicators = indicators;
+                match gesture.originals.as_slice() {
+                    [(id, ..)] => {
+                        Self::restore(gesture, ctx.document);
+                        gizmo::set_pivot(ctx.document, *id, ctx.frame, target);
+                    }
+                    originals => {
+                        let ids = originals.iter().map(|(id, ..)| *id).collect();
+                        self.pivot = Some((ids, target));
+                    }
+                }
+            }
+            _ => {
+                let Some((change, indicators)) = Self::change(gesture, event) else {
+                    return;
+                };
+                Self::restore(gesture, ctx.document);
+                for (id, ..) in &gesture.originals {
+                    gizmo::apply(ctx.document, *id, ctx.frame, &change, ctx.auto_key);
+                }
+                gesture.change = Some(change);
+                gesture.indicators = indicators;
+            }
+        }
+    }
+}
+
+impl Tool for SelectTool {
+    fn name(&self) -> &'static str {
+        "Select"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        let (p, modifiers) = (event.position, event.modifiers);
+        let ids = targets(ctx.document, ctx.selection);
+        if let Some(gizmo) = self.gizmo(ctx.document, &ids, ctx.frame) {
+            let drag = if modifiers.control {
+                Some(Drag::Pivot { grab: Point::ZERO })
+            } else {
+                gizmo
+                    .handle_at(p, ctx.pixel_size)
+                    .map(|handle| match handle {
+                        Handle::Scale { x, y } => Drag::Scale { x, y },
+                        Handle::Rotate => Drag::Rotate,
+                        Handle::Pivot => Drag::Pivot {
+                            grab: gizmo.pivot - p,
+                        },
+                    })
+            };
+            if let Some(drag) = drag {
+                self.begin(ctx, drag, p, gizmo);
+                self.update(ctx, event);
+                return;
+            }
+        }
+
+        let hit = render::hit_test(ctx.document, ctx.frame, p, HIT_PIXELS * ctx.pixel_size)
+            .map(|id| selectable(ctx.document, id));
+        match hit {
+            Some(id) if modifiers.shift => {
+                match ctx.selection.iter().position(|&selected| selected == id) {
+                    Some(index) => {
+                        ctx.selection.remove(index);
+                    }
+                    None => ctx.selection.push(id),
+                }
+                return;
+            }
+            Some(id) if !ctx.selection.contains(&id) => *ctx.selection = vec![id],
+            Some(_) => {}
+            None => {
+                let inside = self
+                    .gizmo(ctx.document, &ids, ctx.frame)
+                    .is_some_and(|gizmo| gizmo.contains(p));
+                if !inside || modifiers.shift {
+                    if !modifiers.shift {
+                        ctx.selection.clear();
+                    }
+                    let drag = Drag::Marquee {
+                        current: p,
+                        additive: modifiers.shift,
+                    };
+                    let gizmo = GizmoBox {
+                        center: p,
+                        half: Point::ZERO,
+                        angle: 0.0,
+                        pivot: p,
+                    };
+                    self.begin(ctx, drag, p, gizmo);
+                    return;
+                }
+            }
+        }
+
+        let ids = targets(ctx.document, ctx.selection);
+        if let Some(gizmo) = self.gizmo(ctx.document, &ids, ctx.frame) {
+            self.begin(ctx, Drag::Move, p, gizmo);
+        }
+    }
+
+    fn pointer_move(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.update(ctx, event);
+    }
+
+    fn pointer_up(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.update(ctx, event);
+        let Some(gesture) = self.gesture.take() else {
+            return;
+        };
+        match gesture.drag {
+            Drag::Marquee { additive, current } => {
+                Self::select_in_marquee(ctx, &gesture, additive, current)
+            }
+            // A pivot set on several nodes travels with them.
+            _ => {
+                if let (Some(change), Some((owner, pivot))) = (gesture.change, &mut self.pivot) {
+                    if owner.iter().eq(gesture.originals.iter().map(|(id, ..)| id)) {
+                        *pivot = change.map(*pivot);
+                    }
+                }
+            }
+        }
+    }
+
+    fn key(&mut self, ctx: &mut ToolContext, event: &KeyEvent) -> bool {
+        if event.key != Key::Escape {
+            return false;
+        }
+        match self.gesture.take() {
+            Some(gesture) => {
+                Self::restore(&gesture, ctx.document);
+                self.pivot = gesture.pivot;
+            }
+            None if !ctx.selection.is_empty() => ctx.selection.clear(),
+            None => return false,
+        }
+        true
+    }
+
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {
+        self.gesture = None;
+    }
+
+    fn draw_overlay(&self, canvas: &skia_safe::Canvas, ctx: &OverlayContext) {
+        let pixel_size = ctx.pixel_size;
+        let mut line = Paint::new(Color4f::new(0.2, 0.6, 1.0, 1.0), None);
+        line.set_anti_alias(true);
+        line.set_style(PaintStyle::Stroke);
+        line.set_stroke_width(pixel_size);
+        let mut fill = Paint::new(Color4f::new(1.0, 1.0, 1.0, 1.0), None);
+        fill.set_anti_alias(true);
+
+        if let Some(gesture) = &self.gesture {
+            render::draw_snap_indicators(canvas, &gesture.indicators, pixel_size);
+        }
+        if let Some(Gesture {
+            drag: Drag::Marquee { current, .. },
+            start,
+            ..
+        }) = &self.gesture
+        {
+            let rect = Rect::from_ltrb(
+                start.x.min(current.x),
+                start.y.min(current.y),
+                start.x.max(current.x),
+                start.y.max(current.y),
+            );
+            let mut tint = Paint::new(Color4f::new(0.2, 0.6, 1.0, 0.1), None);
+            tint.set_anti_alias(true);
+            canvas.draw_rect(rect, &tint);
+            canvas.draw_rect(rect, &line);
+            return;
+        }
+
+        // While dragging, the box follows the drag rather than the new
+        // bounds, so it turns and stretches with the selection.
+        let gizmo = match &self.gesture {
+            Some(Gesture {
+                gizmo,
+                change: Some(change),
+                ..
+            }) => Some(gizmo.transformed(change)),
+            _ => {
+                let ids = targets(ctx.document, ctx.selection);
+                self.gizmo(ctx.document, &ids, ctx.frame)
+            }
+        };
+        let Some(gizmo) = gizmo else {
+            return;
+        };
+
+        let to_skia = |p: Point| skia_safe::Point::new(p.x, p.y);
+        let corner = |x, y| to_skia(gizmo.handle_position(Handle::Scale { x, y }, pixel_size));
+        let mut outline = Path::new();
+        outline.add_poly(
+            &[corner(-1, -1), corner(1, -1), corner(1, 1), corner(-1, 1)],
+            true,
+        );
+        canvas.draw_path(&outline, &line);
+
+        let rotate = to_skia(gizmo.handle_position(Handle::Rotate, pixel_size));
+        canvas.draw_line(corner(0, -1), rotate, &line);
+        let size = 3.5 * pixel_size;
+        canvas.draw_circle(rotate, size, &fill);
+        canvas.draw_circle(rotate, size, &line);
+
+        for (x, y) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+        ] {
+            let center = gizmo.handle_position(Handle::Scale { x, y }, pixel_size);
+            let mut square = Path::new();
+            square.add_poly(
+                &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(dx, dy)| {
+                    to_skia(center + Point::new(dx * size, dy * size).rotate(gizmo.angle))
+                }),
+                true,
+            );
+            canvas.draw_path(&square, &fill);
+            canvas.draw_path(&square, &line);
+        }
+
+        let pivot = to_skia(gizmo.pivot);
+        let arm = 2.0 * size;
+        canvas.draw_circle(pivot, size, &line);
+        canvas.draw_line((pivot.x - arm, pivot.y), (pivot.x + arm, pivot.y), &line);
+        canvas.draw_line((pivot.x, pivot.y - arm), (pivot.x, pivot.y + arm), &line);
+    }
+}
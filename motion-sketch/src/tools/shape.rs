@@ -0,0 +1,186 @@
+// Rectangle and ellipse instruments: drag out a box to create the shape.
+// Shift constrains to a square / circle, Alt draws from the centre. Both
+// corners snap.
+
+use skia_safe::{Color4f, Paint, PaintStyle, Rect};
+
+use super::{Modifiers, OverlayContext, PointerEvent, Tool, ToolContext};
+use crate::animation;
+use crate::render;
+use crate::scene::{Geometry, Point, Shape, Transform};
+use crate::snap::{Indicator, Snapper};
+
+/// Boxes smaller than this many screen pixels are treated as a click and
+/// create nothing.
+const MIN_DRAG_PIXELS: f32 = 2.0;
+
+#[derive(Default)]
+struct DragBox {
+    start: Option<Point>,
+    current: Point,
+    modifiers: Modifiers,
+    snapper: Snapper,
+    indicators: Vec<Indicator>,
+}
+
+impl DragBox {
+    fn begin(&mut self, ctx: &ToolContext, event: &PointerEvent) {
+        self.snapper = Snapper::new(ctx.document, ctx.frame, ctx.pixel_size, &[]);
+        self.start = Some(self.snapper.snap_point(event.position).0);
+        self.update(event);
+    }
+
+    fn update(&mut self, event: &PointerEvent) {
+        (self.current, self.indicators) = self.snapper.snap_point(event.position);
+        self.modifiers = event.modifiers;
+    }
+
+    /// Top-left and bottom-right corners with modifiers applied.
+    fn corners(&self) -> Option<(Point, Point)> {
+        let start = self.start?;
+        let mut delta = self.current - start;
+        if self.modifiers.shift {
+            let side = delta.x.abs().max(delta.y.abs());
+            delta = Point::new(side.copysign(delta.x), side.copysign(delta.y));
+        }
+        let (a, b) = if self.modifiers.alt {
+            (start - delta, start + delta)
+        } else {
+            (start, start + delta)
+        };
+        Some((
+            Point::new(a.x.min(b.x), a.y.min(b.y)),
+            Point::new(a.x.max(b.x), a.y.max(b.y)),
+        ))
+    }
+
+    /// Ends the drag, returning the box unless it is too small to keep.
+    fn finish(&mut self, pixel_size: f32) -> Option<(Point, Point)> {
+        let corners = self.corners();
+        self.start = None;
+        self.indicators.clear();
+        let (min, max) = corners?;
+        let min_size = MIN_DRAG_PIXELS * pixel_size;
+        (max.x - min.x >= min_size && max.y - min.y >= min_size).then_some((min, max))
+    }
+
+    fn draw(&self, canvas: &skia_safe::Canvas, pixel_size: f32, oval: bool) {
+        let Some((min, max)) = self.corners() else {
+            return;
+        };
+        let mut paint = Paint::new(Color4f::new(0.2, 0.6, 1.0, 1.0), None);
+        paint.set_anti_alias(true);
+        paint.set_style(PaintStyle::Stroke);
+        paint.set_stroke_width(pixel_size);
+        let rect = Rect::from_ltrb(min.x, min.y, max.x, max.y);
+        if oval {
+            canvas.draw_oval(rect, &paint);
+        } else {
+            canvas.draw_rect(rect, &paint);
+        }
+        render::draw_snap_indicators(canvas, &self.indicators, pixel_size);
+    }
+}
+
+/// Adds a shape whose local origin lands on the document point `origin`.
+/// The target layer's rotation and scale are undone so the shape keeps the
+/// size and orientation it was dragged out with; this is exact unless a
+/// layer combines rotation with a non-uniform scale.
+fn add_shape(ctx: &mut ToolContext, name: &str, geometry: Geometry, origin: Point) {
+    let layer = ctx.target_layer();
+    let chain = animation::transform_chain(ctx.document, layer, ctx.frame);
+    let transform = Transform {
+        position: animation::to_local(ctx.document, layer, ctx.frame, origin),
+        rotation: -chain.iter().map(|t| t.rotation).sum::<f32>(),
+        scale: chain.iter().fold(Point::new(1.0, 1.0), |scale, t| {
+            let inverse = |s: f32| if s == 0.0 { 1.0 } else { 1.0 / s };
+            Point::new(scale.x * inverse(t.scale.x), scale.y * inverse(t.scale.y))
+        }),
+        anchor: Point::ZERO,
+    };
+    let mut shape = Shape::new(geometry);
+    shape.fill = ctx.style.fill.clone();
+    shape.stroke = ctx.style.stroke.clone();
+    if let Ok(id) = ctx.document.add_shape(layer, name, shape) {
+        if let Some(node) = ctx.document.get_mut(id) {
+            node.transform = transform;
+        }
+        *ctx.selection = vec![id];
+    }
+}
+
+#[derive(Default)]
+pub struct RectangleTool {
+    drag: DragBox,
+}
+
+impl Tool for RectangleTool {
+    fn name(&self) -> &'static str {
+        "Rectangle"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.begin(ctx, event);
+    }
+
+    fn pointer_move(&mut self, _ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.update(event);
+    }
+
+    fn pointer_up(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.update(event);
+        if let Some((min, max)) = self.drag.finish(ctx.pixel_size) {
+            let geometry = Geometry::Rect {
+                size: max - min,
+                corner_radius: 0.0,
+            };
+            add_shape(ctx, "Rectangle", geometry, min);
+        }
+    }
+
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {
+        self.drag.start = None;
+        self.drag.indicators.clear();
+    }
+
+    fn draw_overlay(&self, canvas: &skia_safe::Canvas, ctx: &OverlayContext) {
+        self.drag.draw(canvas, ctx.pixel_size, false);
+    }
+}
+
+#[derive(Default)]
+pub struct EllipseTool {
+    drag: DragBox,
+}
+
+impl Tool for EllipseTool {
+    fn name(&self) -> &'static str {
+        "Ellipse"
+    }
+
+    fn pointer_down(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.begin(ctx, event);
+    }
+
+    fn pointer_move(&mut self, _ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.update(event);
+    }
+
+    fn pointer_up(&mut self, ctx: &mut ToolContext, event: &PointerEvent) {
+        self.drag.update(event);
+        if let Some((min, max)) = self.drag.finish(ctx.pixel_size) {
+            let radii = (max - min) * 0.5;
+            let geometry = Geometry::Ellipse { radii };
+            add_shape(ctx, "Ellipse", geometry, min + radii);
+        }
+    }
+
+    fn deactivate(&mut self, _ctx: &mut ToolContext) {
+        self.drag.start = None;
+        self.drag.indicators.clear();
+    }
+
+    fn draw_overlay(&self, canvas: &skia_safe::Canvas, ctx: &OverlayContext) {
+        self.drag.draw(canvas, ctx.pixel_size, true);
+    }
+}
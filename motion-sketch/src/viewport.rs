@@ -0,0 +1,272 @@
+// Editor viewport navigation.
+//
+// The viewport looks at an unbounded document plane through a view
+// transform: the document point at the centre of the panel, a zoom and a
+// rotation. `to_document` converts panel pixels to document coordinates
+// as a pure function of that state, and the renderer applies the same
+// transform, so pointer input and drawing always agree. The artboard is
+// the document's width x height rectangle on the plane; content outside it
+// is still shown, but is not exported.
+
+use crate::scene::{Document, Point};
+
+/// Zoom limits in screen pixels per document unit.
+const MIN_ZOOM: f32 = 0.02;
+const MAX_ZOOM: f32 = 64.0;
+/// Free space left around whatever is fitted into the panel, in pixels.
+const FIT_MARGIN: f32 = 24.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    /// Panel size in logical pixels.
+    pub width: f32,
+    pub height: f32,
+    /// Document point shown at the centre of the panel.
+    pub center: Point,
+    /// Screen pixels per document unit.
+    pub zoom: f32,
+    /// Clockwise rotation of the view in degrees, in -180..=180.
+    pub rotation: f32,
+    /// Fit the artboard as soon as the panel has a size.
+    fit_pending: bool,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            height: 0.0,
+            center: Point::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+            fit_pending: true,
+        }
+    }
+}
+
+impl Viewport {
+    /// Maps a panel position in logical pixels to document coordinates.
+    pub fn to_document(&self, p: Point) -> Point {
+        let offset = (p - self.half_size()).rotate(-self.rotation);
+        self.center + offset * (1.0 / self.zoom)
+    }
+
+    /// Maps a document point to a panel position in logical pixels.
+    pub fn to_screen(&self, p: Point) -> Point {
+        self.half_size() + ((p - self.center) * self.zoom).rotate(self.rotation)
+    }
+
+    /// Size of one panel pixel in document units.
+    pub fn pixel_size(&self) -> f32 {
+        1.0 / self.zoom
+    }
+
+    fn half_size(&self) -> Point {
+        Point::new(self.width, self.height) * 0.5
+    }
+
+    /// Updates the panel size, keeping the centre where it is, and fits
+    /// `doc`'s artboard if that is still due.
+    pub fn resize(&mut self, width: f32, height: f32, doc: &Document) {
+        self.width = width.max(0.0);
+        self.height = height.max(0.0);
+        if self.fit_pending {
+            self.fit_artboard(doc);
+        }
+    }
+
+    /// Moves the view by a drag of `delta` panel pixels.
+    pub fn pan_by(&mut self, delta: Point) {
+        self.center = self.center - delta.rotate(-self.rotation) * (1.0 / self.zoom);
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the document point under
+    /// the panel position `at` in place.
+    pub fn zoom_by(&mut self, factor: f32, at: Point) {
+        let pinned = self.to_document(at);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center = pinned - (at - self.half_size()).rotate(-self.rotation) * (1.0 / self.zoom);
+    }
+
+    /// Shows the document at one panel pixel per unit.
+    pub fn actual_size(&mut self) {
+        self.zoom = 1.0;
+    }
+
+    /// Turns the view around the panel centre.
+    pub fn rotate_by(&mut self, degrees: f32) {
+        self.set_rotation(self.rotation + degrees);
+    }
+
+    pub fn set_rotation(&mut self, degrees: f32) {
+        let degrees = degrees.rem_euclid(360.0);
+        self.rotation = if degrees > 180.0 {
+            degrees - 360.0
+        } else {
+            degrees
+        };
+    }
+
+    /// Fits the artboard into the panel, or does so as soon as the panel
+    /// has a size.
+    pub fn fit_artboard(&mut self, doc: &Document) {
+        if self.width < 1.0 || self.height < 1.0 {
+            self.fit_pending = true;
+            return;
+        }
+        let size = Point::new(doc.width as f32, doc.height as f32);
+        self.fit(Point::ZERO, size);
+    }
+
+    /// Centres the document rectangle `min`..`max` and zooms so all of it
+    /// shows at the current rotation. Empty rectangles are only centred.
+    pub fn fit(&mut self, min: Point, max: Point) {
+        self.fit_pending = false;
+        self.center = (min + max) * 0.5;
+        let size = max - min;
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let width = size.x * cos.abs() + size.y * sin.abs();
+        let height = size.x * sin.abs() + size.y * cos.abs();
+        let room = Point::new(
+            (self.width - 2.0 * FIT_MARGIN).max(1.0),
+            (self.height - 2.0 * FIT_MARGIN).max(1.0),
+        );
+        let zoom = match (width > 0.0, height > 0.0) {
+            (true, true) => (room.x / width).min(room.y / height),
+            (true, false) => room.x / width,
+            (false, true) => room.y / height,
+            (false, false) => return,
+        };
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTATIONS: [f32; 5] = [0.0, 30.0, 90.0, -135.0, 180.0];
+
+    fn view(rotation: f32) -> Viewport {
+        Viewport {
+            width: 800.0,
+            height: 600.0,
+            center: Point::new(50.0, 40.0),
+            zoom: 2.0,
+            rotation,
+            fit_pending: false,
+        }
+    }
+
+    fn assert_near(actual: Point, expected: Point) {
+        assert!(
+            actual.distance(expected) < 1e-3,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn screen_and_document_round_trip() {
+        let screen = [
+            Point::new(0.0, 0.0),
+            Point::new(400.0, 300.0),
+            Point::new(731.5, 12.25),
+        ];
+        for rotation in ROTATIONS {
+            let mut view = view(rotation);
+            view.pan_by(Point::new(13.0, -7.0));
+            view.zoom_by(1.5, Point::new(100.0, 50.0));
+            for p in screen {
+                assert_near(view.to_screen(view.to_document(p)), p);
+                let doc = Point::new(p.x - 200.0, p.y * 0.5);
+                assert_near(view.to_document(view.to_screen(doc)), doc);
+            }
+            assert_near(view.to_screen(view.center), Point::new(400.0, 300.0));
+        }
+    }
+
+    #[test]
+    fn rotation_turns_the_view_clockwise() {
+        let view = view(90.0);
+        let right = view.center + Point::new(10.0, 0.0);
+        assert_near(view.to_screen(right), Point::new(400.0, 320.0));
+    }
+
+    #[test]
+    fn panning_moves_content_with_the_pointer() {
+        for rotation in ROTATIONS {
+            let mut view = view(rotation);
+            let p = Point::new(120.0, -30.0);
+            let before = view.to_screen(p);
+            view.pan_by(Point::new(25.0, 10.0));
+            assert_near(view.to_screen(p), before + Point::new(25.0, 10.0));
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let cursor = Point::new(620.0, 95.0);
+        for rotation in ROTATIONS {
+            let mut view = view(rotation);
+            let pinned = view.to_document(cursor);
+            for factor in [1.25, 0.5, 1000.0] {
+                view.zoom_by(factor, cursor);
+                assert_near(view.to_document(cursor), pinned);
+            }
+            assert_eq!(view.zoom, MAX_ZOOM);
+        }
+    }
+
+    #[test]
+    fn fit_shows_the_whole_rotated_artboard() {
+        let doc = Document::new(400, 200);
+        let corners = [(0.0, 0.0), (400.0, 0.0), (400.0, 200.0), (0.0, 200.0)];
+        for rotation in ROTATIONS {
+            let mut view = view(rotation);
+            view.fit_artboard(&doc);
+            assert_near(view.center, Point::new(200.0, 100.0));
+            let screen: Vec<Point> = corners
+                .iter()
+                .map(|&(x, y)| view.to_screen(Point::new(x, y)))
+                .collect();
+            let left = screen.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+            let right = screen.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+            let top = screen.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+            let bottom = screen.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+            let margins = [left, 800.0 - right, top, 600.0 - bottom];
+            assert!(
+                margins.iter().all(|m| *m >= FIT_MARGIN - 1e-3),
+                "rotation {rotation}: {margins:?}"
+            );
+            // The tighter direction touches the margin.
+            let tightest = margins.iter().copied().fold(f32::MAX, f32::min);
+            assert!((tightest - FIT_MARGIN).abs() < 1e-3, "rotation {rotation}");
+        }
+    }
+
+    #[test]
+    fn the_first_resize_fits_the_artboard() {
+        let doc = Document::new(400, 200);
+        let mut view = Viewport::default();
+        view.resize(0.0, 0.0, &doc);
+        assert_eq!(view.zoom, 1.0);
+        view.resize(800.0, 600.0, &doc);
+        assert_near(view.center, Point::new(200.0, 100.0));
+        assert_eq!(view.zoom, (800.0 - 2.0 * FIT_MARGIN) / 400.0);
+
+        view.actual_size();
+        view.resize(1000.0, 700.0, &doc);
+        assert_eq!(view.zoom, 1.0);
+    }
+
+    #[test]
+    fn rotation_is_normalized() {
+        let mut view = view(0.0);
+        view.set_rotation(190.0);
+        assert_eq!(view.rotation, -170.0);
+        view.rotate_by(-20.0);
+        assert_eq!(view.rotation, 170.0);
+        view.set_rotation(-540.0);
+        assert_eq!(view.rotation, 180.0);
+    }
+}